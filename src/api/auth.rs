@@ -0,0 +1,68 @@
+//! Shared-secret authentication of the solving routes.
+
+use {
+    super::{routes, State},
+    axum::{
+        http::{header, HeaderMap, Request, StatusCode},
+        middleware::Next,
+        response::{IntoResponse, Response},
+        Json,
+    },
+    std::sync::Arc,
+};
+
+/// Rejects requests without the configured API key as bearer token. Requests
+/// are let through unchecked if no API key is configured.
+pub async fn authenticate<B>(
+    state: axum::extract::State<Arc<State>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    match &state.api_key {
+        Some(api_key) if !authorized(request.headers(), api_key) => (
+            StatusCode::UNAUTHORIZED,
+            Json(routes::Response::<()>::Err(routes::Error::new(
+                routes::Reason::Unauthorized,
+                "missing or invalid API key",
+            ))),
+        )
+            .into_response(),
+        _ => next.run(request).await,
+    }
+}
+
+fn authorized(headers: &HeaderMap, api_key: &str) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), api_key.as_bytes()))
+}
+
+/// Compares two byte strings in time that only depends on their lengths, so
+/// that the key cannot be guessed byte by byte from response times.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_bearer_token() {
+        let headers = |value: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(value) = value {
+                headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+            }
+            headers
+        };
+
+        assert!(authorized(&headers(Some("Bearer secret")), "secret"));
+        assert!(!authorized(&headers(Some("Bearer secre")), "secret"));
+        assert!(!authorized(&headers(Some("Bearer secreT")), "secret"));
+        assert!(!authorized(&headers(Some("secret")), "secret"));
+        assert!(!authorized(&headers(None), "secret"));
+    }
+}
@@ -1,17 +1,45 @@
 //! Serve a solver engine API.
 
 use {
-    crate::domain::solver::Solver,
+    crate::{
+        domain::{eth, solver::Solver},
+        infra,
+    },
     axum::extract::DefaultBodyLimit,
-    std::{future::Future, net::SocketAddr, sync::Arc},
+    std::{future::Future, net::SocketAddr, sync::Arc, time::Duration},
     tokio::sync::oneshot,
 };
 
+mod auth;
 mod routes;
 
+/// The maximum size of a request body. Since a `/solve` batch is sent as a
+/// single request, this also bounds the number of auctions in a batch.
+pub const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
+
 pub struct Api {
     pub addr: SocketAddr,
     pub solver: Solver,
+    /// The maximum amount of time a single `/solve` request may take,
+    /// regardless of the auction deadline.
+    pub solve_timeout: Duration,
+    pub gas_price: infra::gas_price::GasPrice,
+    /// Optional key that clients have to present as bearer token in order to
+    /// solve or quote.
+    pub api_key: Option<String>,
+    /// The solver name reported by `/healthz`. Defaults to the backend name.
+    pub name: Option<String>,
+    /// The chain the solver is configured for, if known.
+    pub chain_id: Option<eth::ChainId>,
+}
+
+/// State shared by the API routes.
+struct State {
+    solver: Solver,
+    solve_timeout: Duration,
+    gas_price: infra::gas_price::GasPrice,
+    api_key: Option<String>,
+    health: routes::Health,
 }
 
 impl Api {
@@ -20,16 +48,38 @@ impl Api {
         bind: Option<oneshot::Sender<SocketAddr>>,
         shutdown: impl Future<Output = ()> + Send + 'static,
     ) -> Result<(), hyper::Error> {
+        let health = routes::Health::new(
+            self.name
+                .unwrap_or_else(|| self.solver.backend().to_owned()),
+            self.chain_id,
+            self.solver.backend(),
+        );
+        let state = Arc::new(State {
+            health,
+            solver: self.solver,
+            solve_timeout: self.solve_timeout,
+            gas_price: self.gas_price,
+            api_key: self.api_key,
+        });
+        let authenticated = axum::Router::new()
+            .route("/solve", axum::routing::post(routes::solve))
+            .route("/quote", axum::routing::post(routes::quote))
+            .route_layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                auth::authenticate,
+            ));
         let app = axum::Router::new()
             .route("/metrics", axum::routing::get(routes::metrics))
             .route("/healthz", axum::routing::get(routes::healthz))
-            .route("/solve", axum::routing::post(routes::solve))
-            .layer(DefaultBodyLimit::max(50 * 1024 * 1024))
+            .route("/livez", axum::routing::get(routes::livez))
+            .route("/gas-price", axum::routing::get(routes::gas_price))
+            .merge(authenticated)
+            .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
             .layer(
                 tower::ServiceBuilder::new().layer(tower_http::trace::TraceLayer::new_for_http()),
             )
             .layer(DefaultBodyLimit::disable())
-            .with_state(Arc::new(self.solver));
+            .with_state(state);
 
         let make_svc = observe::make_service_with_request_tracing!(app);
 
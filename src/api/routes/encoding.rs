@@ -0,0 +1,102 @@
+use {
+    axum::{
+        http::{header, HeaderMap, HeaderValue, StatusCode},
+        response::{IntoResponse, Response},
+    },
+    serde::Serialize,
+};
+
+const MESSAGE_PACK: &str = "application/msgpack";
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// The encoding of a response body, negotiated with the `Accept` header.
+/// Defaults to JSON.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl Encoding {
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let accepts_message_pack = accepted_media_types(headers)
+            .any(|media_type| matches!(media_type, MESSAGE_PACK | "application/x-msgpack"));
+        if accepts_message_pack {
+            Self::MessagePack
+        } else {
+            Self::Json
+        }
+    }
+
+    /// Encodes the body into a response with the specified status code.
+    pub fn respond(self, status: StatusCode, body: impl Serialize) -> Response {
+        match self {
+            Self::Json => (status, axum::response::Json(body)).into_response(),
+            Self::MessagePack => match rmp_serde::to_vec_named(&body) {
+                Ok(bytes) => (
+                    status,
+                    [(header::CONTENT_TYPE, HeaderValue::from_static(MESSAGE_PACK))],
+                    bytes,
+                )
+                    .into_response(),
+                Err(err) => {
+                    tracing::error!(?err, "failed to encode MessagePack response");
+                    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+                }
+            },
+        }
+    }
+}
+
+/// Whether the client asked for a stream of server-sent events.
+pub fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    accepted_media_types(headers).any(|media_type| media_type == EVENT_STREAM)
+}
+
+fn accepted_media_types(headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|media_type| media_type.split(';').next().unwrap_or_default().trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_encoding() {
+        for (accept, encoding) in [
+            (None, Encoding::Json),
+            (Some("application/json"), Encoding::Json),
+            (Some("*/*"), Encoding::Json),
+            (Some("application/msgpack"), Encoding::MessagePack),
+            (
+                Some("application/json;q=0.5, application/x-msgpack"),
+                Encoding::MessagePack,
+            ),
+        ] {
+            let mut headers = HeaderMap::new();
+            if let Some(accept) = accept {
+                headers.insert(header::ACCEPT, accept.parse().unwrap());
+            }
+            assert_eq!(Encoding::negotiate(&headers), encoding);
+        }
+    }
+
+    #[test]
+    fn detects_event_stream() {
+        let mut headers = HeaderMap::new();
+        assert!(!accepts_event_stream(&headers));
+        headers.insert(header::ACCEPT, "application/json".parse().unwrap());
+        assert!(!accepts_event_stream(&headers));
+        headers.insert(
+            header::ACCEPT,
+            "text/event-stream; charset=utf-8".parse().unwrap(),
+        );
+        assert!(accepts_event_stream(&headers));
+    }
+}
@@ -0,0 +1,39 @@
+use {
+    super::Response,
+    crate::{api::State, util::serialize},
+    axum::{http::StatusCode, Json},
+    serde::Serialize,
+    serde_with::serde_as,
+    std::sync::Arc,
+};
+
+pub async fn gas_price(
+    state: axum::extract::State<Arc<State>>,
+) -> (StatusCode, Json<Response<GasPrice>>) {
+    match state.gas_price.current().await {
+        Ok(gas_price) => (
+            StatusCode::OK,
+            Json(Response::Ok(GasPrice {
+                gas_price: gas_price.0,
+            })),
+        ),
+        Err(err) => {
+            tracing::warn!(?err, "failed to get gas price");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(Response::Err(super::Error::new(
+                    super::Reason::Internal,
+                    "failed to get gas price",
+                ))),
+            )
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasPrice {
+    #[serde_as(as = "serialize::U256")]
+    gas_price: ethereum_types::U256,
+}
@@ -1,5 +1,50 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use {
+    crate::{api::State, domain::eth},
+    axum::{http::StatusCode, Json},
+    serde::Serialize,
+    std::sync::Arc,
+};
 
-pub async fn healthz() -> impl IntoResponse {
-    StatusCode::OK
+/// Reports that the solver engine is up, along with which build and
+/// configuration it runs to make deployments easy to tell apart.
+pub async fn healthz(state: axum::extract::State<Arc<State>>) -> (StatusCode, Json<Health>) {
+    (StatusCode::OK, Json(state.health.clone()))
+}
+
+/// Reports whether the solver is still able to get quotes from its backend,
+/// so that a deployment with e.g. a revoked upstream API key gets restarted
+/// or alerted on.
+pub async fn livez(state: axum::extract::State<Arc<State>>) -> (StatusCode, Json<Liveness>) {
+    let alive = state.solver.is_alive();
+    let status = if alive {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(Liveness { alive }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct Liveness {
+    alive: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Health {
+    name: String,
+    version: &'static str,
+    chain_id: Option<u64>,
+    dex: &'static str,
+}
+
+impl Health {
+    pub fn new(name: String, chain_id: Option<eth::ChainId>, dex: &'static str) -> Self {
+        Self {
+            name,
+            version: env!("CARGO_PKG_VERSION"),
+            chain_id: chain_id.map(|chain_id| chain_id as u64),
+            dex,
+        }
+    }
 }
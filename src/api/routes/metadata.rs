@@ -0,0 +1,249 @@
+use {
+    crate::{
+        domain::{dex, eth},
+        util::serialize,
+    },
+    bigdecimal::BigDecimal,
+    serde::Serialize,
+    serde_with::{serde_as, DisplayFromStr},
+};
+
+/// Informational data about how a DEX swap was obtained, as returned by the
+/// `/quote` endpoint and alongside the solutions of `/solve`. Fields that are
+/// unknown for a swap are left out.
+#[serde_as]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream_latency_ms: Option<u64>,
+    execution_plan: Vec<Step>,
+    private_liquidity: bool,
+    #[serde_as(as = "Option<serialize::U256>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gas_cost_wei: Option<eth::U256>,
+    gas_confidence: GasConfidence,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    implied_fee_bps: Option<i64>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price_impact: Option<BigDecimal>,
+    /// The swap price in buy token units per sell token unit, normalized for
+    /// token decimals.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalized_price: Option<BigDecimal>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalized_inverse_price: Option<BigDecimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde_as(as = "Option<serialize::U256>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filled_amount: Option<eth::U256>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fill_ratio: Option<BigDecimal>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rejected: Vec<Rejected>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Step {
+    target: eth::H160,
+    #[serde_as(as = "Option<serialize::Hex>")]
+    selector: Option<[u8; 4]>,
+    purpose: Purpose,
+    #[serde_as(as = "Option<serialize::U256>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gas: Option<eth::U256>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Purpose {
+    Approval,
+    Wrap,
+    Swap,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rejected {
+    backend: &'static str,
+    reason: Rejection,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
+    #[serde_as(as = "Option<serialize::U256>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_amount: Option<eth::U256>,
+    #[serde_as(as = "Option<serialize::U256>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_amount: Option<eth::U256>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Rejection {
+    Outranked,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum GasConfidence {
+    Measured,
+    Heuristic,
+    Default,
+}
+
+impl Metadata {
+    pub fn from_domain(metadata: &dex::Metadata, plan: &dex::ExecutionPlan) -> Self {
+        Self {
+            upstream_latency_ms: metadata.upstream_latency_ms,
+            execution_plan: plan
+                .0
+                .iter()
+                .map(|step| Step {
+                    target: step.target.0,
+                    selector: step.selector,
+                    purpose: match step.purpose {
+                        dex::Purpose::Approval => Purpose::Approval,
+                        dex::Purpose::Wrap => Purpose::Wrap,
+                        dex::Purpose::Swap => Purpose::Swap,
+                    },
+                    gas: step.gas.map(|gas| gas.0),
+                })
+                .collect(),
+            private_liquidity: metadata.private_liquidity,
+            gas_cost_wei: metadata.gas_cost_wei,
+            gas_confidence: match metadata.gas_confidence {
+                dex::GasConfidence::Measured => GasConfidence::Measured,
+                dex::GasConfidence::Heuristic => GasConfidence::Heuristic,
+                dex::GasConfidence::Default => GasConfidence::Default,
+            },
+            sources: metadata
+                .sources
+                .iter()
+                .map(|source| source.name().to_owned())
+                .collect(),
+            warnings: metadata.warnings.clone(),
+            implied_fee_bps: metadata.implied_fee_bps,
+            price_impact: metadata.price_impact.clone(),
+            normalized_price: metadata.price.clone(),
+            normalized_inverse_price: metadata.inverse_price.clone(),
+            expires_at: metadata.expires_at,
+            filled_amount: metadata.filled_amount,
+            fill_ratio: metadata
+                .fill_ratio
+                .as_ref()
+                .map(|ratio| ratio.get().clone()),
+            rejected: metadata
+                .rejected
+                .iter()
+                .map(|rejected| Rejected {
+                    backend: rejected.backend,
+                    reason: match rejected.reason {
+                        dex::Rejection::Outranked => Rejection::Outranked,
+                        dex::Rejection::Failed(_) => Rejection::Failed,
+                        dex::Rejection::Cancelled => Rejection::Cancelled,
+                    },
+                    error: match rejected.reason {
+                        dex::Rejection::Failed(error) => Some(error),
+                        _ => None,
+                    },
+                    input_amount: rejected.amounts.map(|(input, _)| input),
+                    output_amount: rejected.amounts.map(|(_, output)| output),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::domain::dex::testing};
+
+    #[test]
+    fn serializes_swap_metadata() {
+        let mut swap = testing::swap(
+            testing::asset(testing::token(1), 100),
+            testing::asset(testing::token(2), 200),
+        );
+        swap.calls = vec![dex::Call::swap(
+            eth::ContractAddress(eth::H160::repeat_byte(3)),
+            vec![0x12, 0x34, 0x56, 0x78, 0x9a],
+        )];
+        swap.gas = eth::Gas(100_000.into());
+        swap.metadata = dex::Metadata {
+            upstream_latency_ms: Some(42),
+            private_liquidity: true,
+            gas_cost_wei: Some(1_000_000.into()),
+            sources: vec![dex::Source::UniswapV3],
+            implied_fee_bps: Some(-5),
+            gas_confidence: dex::GasConfidence::Measured,
+            price_impact: Some("0.01".parse().unwrap()),
+            price: Some("2".parse().unwrap()),
+            inverse_price: Some("0.5".parse().unwrap()),
+            rejected: vec![
+                dex::Rejected {
+                    backend: "zeroex",
+                    amounts: Some((100.into(), 190.into())),
+                    reason: dex::Rejection::Outranked,
+                },
+                dex::Rejected {
+                    backend: "oneinch",
+                    amounts: None,
+                    reason: dex::Rejection::Failed("Timeout"),
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(Metadata::from_domain(
+                &swap.metadata,
+                &swap.execution_plan()
+            ))
+            .unwrap(),
+            serde_json::json!({
+                "upstreamLatencyMs": 42,
+                "executionPlan": [{
+                    "target": "0x0303030303030303030303030303030303030303",
+                    "selector": "0x12345678",
+                    "purpose": "swap",
+                    "gas": "100000",
+                }],
+                "privateLiquidity": true,
+                "gasCostWei": "1000000",
+                "gasConfidence": "measured",
+                "sources": ["uniswap-v3"],
+                "impliedFeeBps": -5,
+                "priceImpact": "0.01",
+                "normalizedPrice": "2",
+                "normalizedInversePrice": "0.5",
+                "rejected": [
+                    {
+                        "backend": "zeroex",
+                        "reason": "outranked",
+                        "inputAmount": "100",
+                        "outputAmount": "190",
+                    },
+                    {
+                        "backend": "oneinch",
+                        "reason": "failed",
+                        "error": "Timeout",
+                    },
+                ],
+            }),
+        );
+    }
+}
@@ -1,10 +1,20 @@
 use serde::Serialize;
 
+mod encoding;
+mod gas_price;
 mod healthz;
+mod metadata;
 mod metrics;
+mod quote;
 mod solve;
 
-pub(super) use {healthz::healthz, metrics::metrics, solve::solve};
+pub(super) use {
+    gas_price::gas_price,
+    healthz::{healthz, livez, Health},
+    metrics::metrics,
+    quote::quote,
+    solve::solve,
+};
 
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
@@ -16,10 +26,93 @@ pub enum Response<T> {
 #[derive(Debug, Serialize)]
 pub struct Error {
     pub message: &'static str,
+    pub reason: Reason,
 }
 
+impl Error {
+    pub fn new(reason: Reason, message: &'static str) -> Self {
+        Self { message, reason }
+    }
+}
+
+/// Input validation errors.
 impl From<&'static str> for Error {
     fn from(message: &'static str) -> Self {
-        Self { message }
+        Self::new(Reason::InvalidRequest, message)
+    }
+}
+
+/// A stable, machine-readable error reason code. Unlike the error message,
+/// these codes are part of the API and do not change.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// The request is malformed or contains invalid data.
+    InvalidRequest,
+    /// The request lacks a valid API key.
+    Unauthorized,
+    /// No swap could be found for the request.
+    NotFound,
+    /// The DEX API lacks the liquidity to swap the order amount.
+    NoLiquidity,
+    /// The only swap found has a price that deviates implausibly from the
+    /// reference prices.
+    PriceDeviationTooHigh,
+    /// The swap calldata is too large to fit into a settlement.
+    CalldataTooLarge,
+    /// The DEX API does not support the kind of order.
+    OrderNotSupported,
+    /// The order is invalid, e.g. it swaps a token for itself.
+    InvalidOrder,
+    /// A token of the order is not a contract.
+    InvalidToken,
+    /// The order value exceeds the maximum the solver handles.
+    OrderTooLarge,
+    /// The slippage tolerance of the order exceeds the maximum.
+    SlippageTooHigh,
+    /// The DEX API refuses to swap the order for legal reasons.
+    UnavailableForLegalReasons,
+    /// The upstream DEX API is rate limiting requests.
+    RateLimited,
+    /// The upstream DEX API is unavailable after repeated failures.
+    Unavailable,
+    /// The request (or a DEX API request it made) took longer than allowed.
+    Timeout,
+    /// The solver engine failed to handle the request.
+    Internal,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_reason_codes() {
+        for (reason, code) in [
+            (Reason::InvalidRequest, "invalid_request"),
+            (Reason::Unauthorized, "unauthorized"),
+            (Reason::NotFound, "not_found"),
+            (Reason::NoLiquidity, "no_liquidity"),
+            (Reason::PriceDeviationTooHigh, "price_deviation_too_high"),
+            (Reason::CalldataTooLarge, "calldata_too_large"),
+            (Reason::OrderNotSupported, "order_not_supported"),
+            (Reason::InvalidOrder, "invalid_order"),
+            (Reason::InvalidToken, "invalid_token"),
+            (Reason::OrderTooLarge, "order_too_large"),
+            (Reason::SlippageTooHigh, "slippage_too_high"),
+            (
+                Reason::UnavailableForLegalReasons,
+                "unavailable_for_legal_reasons",
+            ),
+            (Reason::RateLimited, "rate_limited"),
+            (Reason::Unavailable, "unavailable"),
+            (Reason::Timeout, "timeout"),
+            (Reason::Internal, "internal"),
+        ] {
+            assert_eq!(
+                serde_json::to_value(Error::new(reason, "message")).unwrap(),
+                serde_json::json!({ "message": "message", "reason": code }),
+            );
+        }
     }
 }
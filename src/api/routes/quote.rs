@@ -0,0 +1,516 @@
+use {
+    super::Response,
+    crate::{
+        api::State,
+        domain::{auction, dex, eth, order},
+        infra,
+        util::{conv, serialize},
+    },
+    axum::{
+        http::{header, HeaderMap, HeaderValue, StatusCode},
+        Json,
+    },
+    bigdecimal::{BigDecimal, Zero},
+    serde::{Deserialize, Serialize},
+    serde_with::serde_as,
+    std::sync::Arc,
+    tracing::Instrument,
+};
+
+/// Quotes a single order with the same solver machinery as `/solve`, for
+/// indicative prices without constructing a whole auction.
+///
+/// Failures of the DEX API are reported with distinct status codes, so that
+/// clients can tell whether (and when) to try again: 429 with a `Retry-After`
+/// header if it is rate limiting requests, 503 if it is unavailable and 504
+/// if it timed out.
+///
+/// Instead of an amount, the current and target balance of the buy token can
+/// be specified, in which case the buy amount that is missing to reach the
+/// target is quoted.
+pub async fn quote(
+    state: axum::extract::State<Arc<State>>,
+    quote: Json<Quote>,
+) -> (StatusCode, HeaderMap, Json<Response<Swap>>) {
+    let handle_request = async {
+        let (side, amount) = match quote.amount() {
+            Ok(Some(amount)) => amount,
+            Ok(None) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    HeaderMap::new(),
+                    Response::Err(super::Error::new(
+                        super::Reason::NotFound,
+                        "buy token balance already reaches the target",
+                    )),
+                )
+            }
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    HeaderMap::new(),
+                    Response::Err(err),
+                )
+            }
+        };
+        let order = dex::Order {
+            sell: eth::TokenAddress(quote.sell),
+            buy: eth::TokenAddress(quote.buy),
+            side,
+            amount: dex::Amount::new(amount),
+            // There is no order owner when only quoting.
+            owner: eth::H160::zero(),
+            partially_fillable: false,
+            fill_ratio: None,
+            slippage: None,
+        };
+        let gas_price = match state.gas_price.current().await {
+            Ok(gas_price) => auction::GasPrice(gas_price),
+            Err(err) => {
+                tracing::warn!(?err, "failed to get gas price");
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    HeaderMap::new(),
+                    Response::Err(super::Error::new(
+                        super::Reason::Internal,
+                        "failed to get gas price",
+                    )),
+                );
+            }
+        };
+
+        match state.solver.quote(&order, gas_price, quote.dry_run).await {
+            Ok(swap) => (
+                StatusCode::OK,
+                HeaderMap::new(),
+                Response::Ok(Swap::from_domain(&swap)),
+            ),
+            Err(err) => {
+                tracing::debug!(?err, "failed to quote order");
+                let (status, error) = to_error(&err);
+                (status, retry_after(&err), Response::Err(error))
+            }
+        }
+    };
+
+    let handle_request = async {
+        match tokio::time::timeout(state.solve_timeout, handle_request).await {
+            Ok(response) => response,
+            Err(_) => {
+                tracing::warn!(timeout = ?state.solve_timeout, "quote request timed out");
+                (
+                    StatusCode::GATEWAY_TIMEOUT,
+                    HeaderMap::new(),
+                    Response::Err(super::Error::new(
+                        super::Reason::Timeout,
+                        "quote request timed out",
+                    )),
+                )
+            }
+        }
+    };
+
+    let (status, headers, body) = handle_request
+        .instrument(tracing::info_span!("/quote"))
+        .await;
+    (status, headers, Json(body))
+}
+
+fn to_error(err: &infra::dex::Error) -> (StatusCode, super::Error) {
+    use infra::dex::Error;
+    match err {
+        Error::NotFound => (
+            StatusCode::NOT_FOUND,
+            super::Error::new(super::Reason::NotFound, "no swap found for the order"),
+        ),
+        Error::InsufficientLiquidity => (
+            StatusCode::NOT_FOUND,
+            super::Error::new(
+                super::Reason::NoLiquidity,
+                "not enough liquidity to swap the order amount",
+            ),
+        ),
+        Error::PriceDeviationTooHigh => (
+            StatusCode::NOT_FOUND,
+            super::Error::new(
+                super::Reason::PriceDeviationTooHigh,
+                "swap price deviates too far from the reference prices",
+            ),
+        ),
+        Error::CalldataTooLarge(_) => (
+            StatusCode::NOT_FOUND,
+            super::Error::new(
+                super::Reason::CalldataTooLarge,
+                "swap calldata is too large to settle",
+            ),
+        ),
+        Error::OrderNotSupported => (
+            StatusCode::BAD_REQUEST,
+            super::Error::new(
+                super::Reason::OrderNotSupported,
+                "order type is not supported",
+            ),
+        ),
+        Error::InvalidOrder(_) => (
+            StatusCode::BAD_REQUEST,
+            super::Error::new(super::Reason::InvalidOrder, "the order is invalid"),
+        ),
+        Error::InvalidToken(_) => (
+            StatusCode::BAD_REQUEST,
+            super::Error::new(super::Reason::InvalidToken, "token has no contract code"),
+        ),
+        Error::OrderTooLarge => (
+            StatusCode::BAD_REQUEST,
+            super::Error::new(
+                super::Reason::OrderTooLarge,
+                "order value exceeds the maximum",
+            ),
+        ),
+        Error::SlippageTooHigh => (
+            StatusCode::BAD_REQUEST,
+            super::Error::new(
+                super::Reason::SlippageTooHigh,
+                "slippage tolerance exceeds the maximum",
+            ),
+        ),
+        Error::UnavailableForLegalReasons => (
+            StatusCode::BAD_REQUEST,
+            super::Error::new(
+                super::Reason::UnavailableForLegalReasons,
+                "the order cannot be quoted for legal reasons",
+            ),
+        ),
+        Error::RateLimited { .. } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            super::Error::new(
+                super::Reason::RateLimited,
+                "DEX API is rate limiting requests",
+            ),
+        ),
+        Error::Timeout => (
+            StatusCode::GATEWAY_TIMEOUT,
+            super::Error::new(super::Reason::Timeout, "DEX API request timed out"),
+        ),
+        Error::Unavailable => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            super::Error::new(super::Reason::Unavailable, "DEX API is unavailable"),
+        ),
+        Error::Transient(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            super::Error::new(super::Reason::Unavailable, "DEX API failed temporarily"),
+        ),
+        Error::Other(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            super::Error::new(super::Reason::Internal, "failed to quote order"),
+        ),
+    }
+}
+
+/// The `Retry-After` header with the number of seconds the DEX API asked us
+/// to wait, rounded up.
+fn retry_after(err: &infra::dex::Error) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(retry_after) = err.retry_after() {
+        let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        headers.insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+    }
+    headers
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Quote {
+    sell: eth::H160,
+    buy: eth::H160,
+    #[serde_as(as = "Option<serialize::U256>")]
+    #[serde(default)]
+    amount: Option<eth::U256>,
+    #[serde(default)]
+    side: Option<Side>,
+    /// The buy token balance to reach, instead of an `amount` and `side`.
+    #[serde(default)]
+    buy_balance: Option<BalanceTarget>,
+    /// Whether to drop the calls of the swap, returning its price only.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct BalanceTarget {
+    #[serde_as(as = "serialize::U256")]
+    current: eth::U256,
+    #[serde_as(as = "serialize::U256")]
+    target: eth::U256,
+}
+
+impl Quote {
+    /// The side and amount of the order to quote. A buy balance target is
+    /// quoted as a buy order for the missing balance, or not at all (`None`)
+    /// if the balance already reaches the target.
+    fn amount(&self) -> Result<Option<(order::Side, eth::U256)>, super::Error> {
+        match (self.amount, self.side, &self.buy_balance) {
+            (Some(amount), Some(side), None) => Ok(Some((
+                match side {
+                    Side::Buy => order::Side::Buy,
+                    Side::Sell => order::Side::Sell,
+                },
+                amount,
+            ))),
+            (None, None, Some(balance)) => Ok((balance.current < balance.target)
+                .then(|| (order::Side::Buy, balance.target - balance.current))),
+            _ => Err(super::Error::from(
+                "either amount and side or buyBalance must be specified",
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Side {
+    Buy,
+    Sell,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Swap {
+    sell_token: eth::H160,
+    #[serde_as(as = "serialize::U256")]
+    sell_amount: eth::U256,
+    buy_token: eth::H160,
+    #[serde_as(as = "serialize::U256")]
+    buy_amount: eth::U256,
+    /// The estimated gas of the swap.
+    #[serde_as(as = "serialize::U256")]
+    gas: eth::U256,
+    /// The effective price of the swap in buy token units per sell token
+    /// unit, not normalized for token decimals.
+    #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
+    price: Option<BigDecimal>,
+    allowance: Allowance,
+    calls: Vec<Call>,
+    metadata: super::metadata::Metadata,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Allowance {
+    spender: eth::H160,
+    #[serde_as(as = "serialize::U256")]
+    amount: eth::U256,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Call {
+    target: eth::H160,
+    #[serde_as(as = "serialize::Hex")]
+    calldata: Vec<u8>,
+}
+
+impl Swap {
+    fn from_domain(swap: &dex::Swap) -> Self {
+        let sell = conv::u256_to_bigdecimal(&swap.input.amount);
+        Self {
+            sell_token: swap.input.token.0,
+            sell_amount: swap.input.amount,
+            buy_token: swap.output.token.0,
+            buy_amount: swap.output.amount,
+            gas: swap.gas.0,
+            price: (!sell.is_zero()).then(|| conv::u256_to_bigdecimal(&swap.output.amount) / sell),
+            allowance: Allowance {
+                spender: swap.allowance.spender.0,
+                amount: swap.allowance.amount.get(),
+            },
+            calls: swap
+                .calls
+                .iter()
+                .map(|call| Call {
+                    target: call.to.0,
+                    calldata: call.calldata.clone(),
+                })
+                .collect(),
+            metadata: super::metadata::Metadata::from_domain(
+                &swap.metadata,
+                &swap.execution_plan(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{sync::Arc, time::Duration},
+    };
+
+    #[test]
+    fn reports_dex_api_failures() {
+        let response = |err: infra::dex::Error| {
+            let (status, error) = to_error(&err);
+            (
+                status,
+                serde_json::to_value(error).unwrap()["reason"].clone(),
+                retry_after(&err).get(header::RETRY_AFTER).cloned(),
+            )
+        };
+
+        assert_eq!(
+            response(infra::dex::Error::RateLimited {
+                retry_after: Some(Duration::from_millis(1_500)),
+            }),
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                serde_json::json!("rate_limited"),
+                Some(HeaderValue::from(2)),
+            ),
+        );
+        assert_eq!(
+            response(infra::dex::Error::RateLimited { retry_after: None }),
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                serde_json::json!("rate_limited"),
+                None,
+            ),
+        );
+        assert_eq!(
+            response(infra::dex::Error::Timeout),
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                serde_json::json!("timeout"),
+                None
+            ),
+        );
+        assert_eq!(
+            response(infra::dex::Error::Unavailable),
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                serde_json::json!("unavailable"),
+                None,
+            ),
+        );
+        assert_eq!(
+            response(infra::dex::Error::Transient(Arc::new(std::fmt::Error))),
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                serde_json::json!("unavailable"),
+                None,
+            ),
+        );
+        assert_eq!(
+            response(infra::dex::Error::NotFound),
+            (StatusCode::NOT_FOUND, serde_json::json!("not_found"), None),
+        );
+    }
+
+    #[test]
+    fn quotes_missing_buy_balance() {
+        let amount = |quote: serde_json::Value| {
+            let mut request = serde_json::json!({
+                "sell": "0x0101010101010101010101010101010101010101",
+                "buy": "0x0202020202020202020202020202020202020202",
+            });
+            request
+                .as_object_mut()
+                .unwrap()
+                .extend(quote.as_object().unwrap().clone());
+            serde_json::from_value::<Quote>(request)
+                .unwrap()
+                .amount()
+                .map_err(|err| err.message)
+        };
+
+        assert_eq!(
+            amount(serde_json::json!({ "amount": "100", "side": "sell" })),
+            Ok(Some((order::Side::Sell, 100.into()))),
+        );
+        assert_eq!(
+            amount(serde_json::json!({
+                "buyBalance": { "current": "40", "target": "100" },
+            })),
+            Ok(Some((order::Side::Buy, 60.into()))),
+        );
+        assert_eq!(
+            amount(serde_json::json!({
+                "buyBalance": { "current": "100", "target": "100" },
+            })),
+            Ok(None),
+        );
+        assert!(amount(serde_json::json!({ "amount": "100" })).is_err());
+        assert!(amount(serde_json::json!({
+            "amount": "100",
+            "side": "buy",
+            "buyBalance": { "current": "40", "target": "100" },
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn reports_distinct_reasons_per_error() {
+        let response = |err: infra::dex::Error| {
+            let (status, error) = to_error(&err);
+            (
+                status,
+                serde_json::to_value(error).unwrap()["reason"].clone(),
+            )
+        };
+
+        for (err, status, reason) in [
+            (
+                infra::dex::Error::InsufficientLiquidity,
+                StatusCode::NOT_FOUND,
+                "no_liquidity",
+            ),
+            (
+                infra::dex::Error::PriceDeviationTooHigh,
+                StatusCode::NOT_FOUND,
+                "price_deviation_too_high",
+            ),
+            (
+                infra::dex::Error::CalldataTooLarge(100_000),
+                StatusCode::NOT_FOUND,
+                "calldata_too_large",
+            ),
+            (
+                infra::dex::Error::OrderNotSupported,
+                StatusCode::BAD_REQUEST,
+                "order_not_supported",
+            ),
+            (
+                infra::dex::Error::InvalidOrder("same token"),
+                StatusCode::BAD_REQUEST,
+                "invalid_order",
+            ),
+            (
+                infra::dex::Error::InvalidToken(eth::TokenAddress(eth::H160::zero())),
+                StatusCode::BAD_REQUEST,
+                "invalid_token",
+            ),
+            (
+                infra::dex::Error::OrderTooLarge,
+                StatusCode::BAD_REQUEST,
+                "order_too_large",
+            ),
+            (
+                infra::dex::Error::SlippageTooHigh,
+                StatusCode::BAD_REQUEST,
+                "slippage_too_high",
+            ),
+            (
+                infra::dex::Error::UnavailableForLegalReasons,
+                StatusCode::BAD_REQUEST,
+                "unavailable_for_legal_reasons",
+            ),
+        ] {
+            assert_eq!(response(err), (status, serde_json::json!(reason)));
+        }
+    }
+}
@@ -1,15 +1,95 @@
 use {
+    super::solution,
     crate::{
         api::routes::Error,
         domain::{auction, eth, liquidity, order},
-        util::conv,
+        util::{conv, serialize},
     },
     dto::auction::*,
     itertools::Itertools,
+    serde::Deserialize,
+    serde_with::serde_as,
+    std::collections::{HashMap, HashSet},
 };
 
+/// Fields of the auction schema that are specific to this solver. They are
+/// read from the same request body as the [`Auction`], which ignores them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Extensions {
+    /// The extensions of the auction orders, which are matched to the orders
+    /// by their uid.
+    #[serde(default)]
+    orders: Vec<OrderExtensions>,
+    /// Whether to only quote the orders of the auction without returning any
+    /// solutions.
+    #[serde(default)]
+    dry_run: bool,
+    /// An earlier deadline for solving the auction than its `deadline`, after
+    /// which the solutions found so far are returned. This is the same as the
+    /// `X-Deadline` header, but for a single auction of a batch.
+    #[serde(default)]
+    solve_deadline: Option<chrono::DateTime<chrono::Utc>>,
+    /// Whether to return the metadata of the DEX swap that each solution
+    /// executes along with it.
+    #[serde(default)]
+    verbose: bool,
+    /// Whether to request a fresh swap from the DEX API for every order,
+    /// instead of reusing swaps that were quoted for identical requests
+    /// before. This costs DEX API quota and should be used sparingly.
+    #[serde(default)]
+    force_fresh: bool,
+    /// Whether to return the addresses of the solutions in their EIP-55
+    /// checksummed form instead of lowercase.
+    #[serde(default)]
+    checksum_addresses: bool,
+}
+
+impl Extensions {
+    /// The format to return the solutions of the auction in.
+    pub fn format(&self) -> solution::Format {
+        solution::Format {
+            verbose: self.verbose,
+            checksum_addresses: self.checksum_addresses,
+        }
+    }
+
+    /// The slippage tolerances requested for the orders with the specified
+    /// uids, by uid. Requests for any other order are rejected.
+    fn slippage_bps(
+        &self,
+        uids: impl IntoIterator<Item = [u8; 56]>,
+    ) -> Result<HashMap<[u8; 56], u16>, Error> {
+        let uids = uids.into_iter().collect::<HashSet<_>>();
+        self.orders
+            .iter()
+            .filter_map(|order| Some((order.uid, order.slippage_bps?)))
+            .map(|(uid, slippage_bps)| {
+                if uids.contains(&uid) {
+                    Ok((uid, slippage_bps))
+                } else {
+                    Err(Error::from("slippage tolerance for an unknown order uid"))
+                }
+            })
+            .collect()
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderExtensions {
+    #[serde_as(as = "serialize::Hex")]
+    uid: [u8; 56],
+    /// The slippage tolerance to use for the order in basis points, instead
+    /// of the configured relative slippage.
+    #[serde(default)]
+    slippage_bps: Option<u16>,
+}
+
 /// Converts a data transfer object into its domain object representation.
-pub fn to_domain(auction: &Auction) -> Result<auction::Auction, Error> {
+pub fn to_domain(auction: &Auction, extensions: &Extensions) -> Result<auction::Auction, Error> {
+    let slippage_bps = extensions.slippage_bps(auction.orders.iter().map(|order| order.uid))?;
     Ok(auction::Auction {
         id: match auction.id {
             Some(id) => auction::Id::Solve(id),
@@ -67,6 +147,7 @@ pub fn to_domain(auction: &Auction) -> Result<auction::Auction, Error> {
                         token: eth::TokenAddress(hint.token),
                         amount: hint.amount,
                     }),
+                slippage_bps: slippage_bps.get(&order.uid).copied(),
             })
             .collect(),
         liquidity: auction
@@ -87,7 +168,12 @@ pub fn to_domain(auction: &Auction) -> Result<auction::Auction, Error> {
             })
             .try_collect()?,
         gas_price: auction::GasPrice(eth::Ether(auction.effective_gas_price)),
-        deadline: auction::Deadline(auction.deadline),
+        deadline: match extensions.solve_deadline {
+            Some(deadline) => auction::Deadline(auction.deadline.min(deadline)),
+            None => auction::Deadline(auction.deadline),
+        },
+        dry_run: extensions.dry_run,
+        force_fresh: extensions.force_fresh,
     })
 }
 
@@ -265,3 +351,30 @@ mod foreign_limit_order {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_slippage_to_orders_by_uid() {
+        let uid = |byte: u8| format!("0x{}", hex::encode([byte; 56]));
+        let extensions = |orders: serde_json::Value| {
+            Extensions::deserialize(serde_json::json!({ "orders": orders })).unwrap()
+        };
+
+        let slippage_bps = extensions(serde_json::json!([
+            { "uid": uid(2), "slippageBps": 100 },
+            { "uid": uid(1) },
+        ]))
+        .slippage_bps([[1; 56], [2; 56]])
+        .unwrap();
+        assert_eq!(slippage_bps, HashMap::from([([2; 56], 100)]));
+
+        assert!(extensions(serde_json::json!([
+            { "uid": uid(3), "slippageBps": 100 },
+        ]))
+        .slippage_bps([[1; 56], [2; 56]])
+        .is_err());
+    }
+}
@@ -1,4 +1,7 @@
 pub mod auction;
 pub mod solution;
 
-pub use dto::{auction::Auction, solution::Solutions};
+pub use {
+    self::{auction::Extensions, solution::Solutions},
+    dto::auction::Auction,
+};
@@ -1,44 +1,342 @@
-use {super::Response, tracing::Instrument};
+use {
+    super::{encoding, encoding::Encoding, Response},
+    axum::response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    futures::{future, stream},
+    serde::{Deserialize, Serialize},
+    std::convert::Infallible,
+    tokio::sync::mpsc,
+    tracing::Instrument,
+};
 
 mod dto;
 
-use {crate::domain::solver::Solver, std::sync::Arc};
+use {
+    crate::{api::State, domain::auction},
+    std::{sync::Arc, time::Duration},
+};
 
+/// The optional request header with an RFC 3339 timestamp that further limits
+/// the deadline of the auctions, in addition to their `deadline` field.
+const DEADLINE_HEADER: &str = "x-deadline";
+
+/// The response header that is set if solving was cut short by the deadline,
+/// in which case only the solutions that were found in time are returned.
+const TRUNCATED_HEADER: &str = "x-truncated";
+
+/// Solves a single auction, or a batch of independent auctions when the
+/// request body is a JSON array. Batches are solved concurrently and their
+/// solutions returned in the same order. The size of a batch is only bounded
+/// by the request body limit (see [`crate::api::MAX_BODY_SIZE`]).
+///
+/// Solving stops at the earliest of the auction deadline, its optional
+/// `solveDeadline` field, the optional `X-Deadline` header and the configured
+/// solve timeout. Outstanding swap
+/// requests are then cancelled, and the solutions found so far are returned
+/// with the `X-Truncated` header set.
+///
+/// Clients accepting `text/event-stream` get the solutions of a single auction
+/// streamed as server-sent events instead, see [`solve_streaming`].
 pub async fn solve(
-    state: axum::extract::State<Arc<Solver>>,
-    auction: axum::extract::Json<dto::Auction>,
-) -> (
-    axum::http::StatusCode,
-    axum::response::Json<Response<dto::Solutions>>,
-) {
+    state: axum::extract::State<Arc<State>>,
+    headers: axum::http::HeaderMap,
+    body: axum::extract::Json<serde_json::Value>,
+) -> axum::response::Response {
+    let deadline = match deadline(&headers, state.solve_timeout) {
+        Ok(deadline) => deadline,
+        Err(err) => {
+            tracing::warn!(?err, "invalid deadline");
+            return invalid_request(err);
+        }
+    };
+    if encoding::accepts_event_stream(&headers) && !body.is_array() {
+        return solve_streaming(state.0, body.0, deadline);
+    }
+    let encoding = Encoding::negotiate(&headers);
     let handle_request = async {
-        let auction = match dto::auction::to_domain(&auction) {
+        let request = match Request::parse(body.0) {
             Ok(value) => value,
             Err(err) => {
                 tracing::warn!(?err, "invalid auction");
                 return (
                     axum::http::StatusCode::BAD_REQUEST,
-                    axum::response::Json(Response::Err(err)),
+                    Response::Err(super::Error::from("invalid auction")),
+                    false,
                 );
             }
         };
 
-        let auction_id = auction.id;
-        let solutions = state
-            .solve(auction)
-            .instrument(tracing::info_span!("auction", id = %auction_id))
-            .await;
+        let auctions = match request
+            .auctions()
+            .iter()
+            .map(|(auction, extensions)| {
+                dto::auction::to_domain(auction, extensions)
+                    .map(|auction| (limit_deadline(auction, &deadline), extensions.format()))
+            })
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(value) => value,
+            Err(err) => {
+                tracing::warn!(?err, "invalid auction");
+                return (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    Response::Err(err),
+                    false,
+                );
+            }
+        };
 
-        tracing::trace!(?auction_id, ?solutions);
+        let state = &state;
+        let (solutions, completed): (Vec<_>, Vec<_>) =
+            future::join_all(auctions.into_iter().map(|(auction, format)| {
+                let auction_id = auction.id;
+                async move {
+                    let mut solutions = Vec::new();
+                    let completed = state
+                        .solver
+                        .solve_each(auction, |solution| solutions.push(solution))
+                        .await;
+                    tracing::trace!(?auction_id, ?solutions, completed);
+                    (dto::solution::from_domain(&solutions, format), completed)
+                }
+                .instrument(tracing::info_span!("auction", id = %auction_id))
+            }))
+            .await
+            .into_iter()
+            .unzip();
 
-        let solutions = dto::solution::from_domain(&solutions);
+        let solutions = match request {
+            Request::Single(_) => Solutions::Single(solutions.into_iter().next().unwrap()),
+            Request::Batch(_) => Solutions::Batch(solutions),
+        };
+        let truncated = completed.contains(&false);
         (
             axum::http::StatusCode::OK,
-            axum::response::Json(Response::Ok(solutions)),
+            Response::Ok(solutions),
+            truncated,
         )
     };
 
-    handle_request
+    let handle_request = async {
+        match tokio::time::timeout(state.solve_timeout, handle_request).await {
+            Ok(response) => response,
+            Err(_) => {
+                tracing::warn!(timeout = ?state.solve_timeout, "solve request timed out");
+                (
+                    axum::http::StatusCode::GATEWAY_TIMEOUT,
+                    Response::Err(super::Error::new(
+                        super::Reason::Timeout,
+                        "solve request timed out",
+                    )),
+                    false,
+                )
+            }
+        }
+    };
+
+    let (status, body, truncated) = handle_request
         .instrument(tracing::info_span!("/solve"))
-        .await
+        .await;
+    let mut response = encoding.respond(status, body);
+    if truncated {
+        response.headers_mut().insert(
+            TRUNCATED_HEADER,
+            axum::http::HeaderValue::from_static("true"),
+        );
+    }
+    response
+}
+
+/// Returns the latest deadline the request allows: the `X-Deadline` header if
+/// specified, but no later than the solve timeout from now.
+fn deadline(
+    headers: &axum::http::HeaderMap,
+    solve_timeout: Duration,
+) -> Result<auction::Deadline, super::Error> {
+    let timeout = auction::Deadline(
+        chrono::Utc::now()
+            + chrono::Duration::from_std(solve_timeout).unwrap_or(chrono::Duration::MAX),
+    );
+    let Some(header) = headers.get(DEADLINE_HEADER) else {
+        return Ok(timeout);
+    };
+    let header = header
+        .to_str()
+        .ok()
+        .and_then(|value| chrono::DateTime::parse_from_rfc3339(value).ok())
+        .ok_or(super::Error::from("invalid X-Deadline header"))?;
+    Ok(timeout.min(auction::Deadline(header.with_timezone(&chrono::Utc))))
+}
+
+fn limit_deadline(mut auction: auction::Auction, deadline: &auction::Deadline) -> auction::Auction {
+    auction.deadline = auction.deadline.min(deadline.clone());
+    auction
+}
+
+/// Solves a single auction, emitting a `solution` event as soon as each order
+/// is solved, followed by a final `complete` event (or an `error` event if
+/// the request timed out). This allows the driver to act on early results.
+/// The `complete` event reports whether solving was cut short by the
+/// deadline, e.g. `{"truncated":false}`. Solving stops as soon as the client
+/// disconnects.
+fn solve_streaming(
+    state: Arc<State>,
+    body: serde_json::Value,
+    deadline: auction::Deadline,
+) -> axum::response::Response {
+    let (auction, extensions) = match parse_auction(body) {
+        Ok(auction) => auction,
+        Err(err) => {
+            tracing::warn!(?err, "invalid auction");
+            return invalid_request(super::Error::from("invalid auction"));
+        }
+    };
+    let auction = match dto::auction::to_domain(&auction, &extensions)
+        .map(|auction| limit_deadline(auction, &deadline))
+    {
+        Ok(value) => value,
+        Err(err) => {
+            tracing::warn!(?err, "invalid auction");
+            return invalid_request(err);
+        }
+    };
+
+    let format = extensions.format();
+    let (events, receiver) = mpsc::unbounded_channel();
+    let span = tracing::info_span!("/solve", stream = true);
+    let auction_id = auction.id;
+    tokio::spawn(
+        async move {
+            let solve = state
+                .solver
+                .solve_each(auction, |solution| {
+                    let solutions =
+                        dto::solution::from_domain(std::slice::from_ref(&solution), format);
+                    for solution in solutions.solutions {
+                        match Event::default().event("solution").json_data(solution) {
+                            Ok(event) => {
+                                let _ = events.send(event);
+                            }
+                            Err(err) => tracing::error!(?err, "failed to encode solution"),
+                        }
+                    }
+                })
+                .instrument(tracing::info_span!("auction", id = %auction_id));
+            let solve = tokio::time::timeout(state.solve_timeout, solve);
+            let result = tokio::select! {
+                result = solve => result,
+                _ = events.closed() => {
+                    tracing::debug!("client disconnected; stopping to solve");
+                    return;
+                }
+            };
+            let last = match result {
+                Ok(completed) => Event::default()
+                    .event("complete")
+                    .data(serde_json::json!({ "truncated": !completed }).to_string()),
+                Err(_) => {
+                    tracing::warn!(timeout = ?state.solve_timeout, "solve request timed out");
+                    Event::default()
+                        .event("error")
+                        .json_data(super::Error::new(
+                            super::Reason::Timeout,
+                            "solve request timed out",
+                        ))
+                        .expect("error serializes")
+                }
+            };
+            let _ = events.send(last);
+        }
+        .instrument(span),
+    );
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((Ok::<_, Infallible>(event), receiver))
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn invalid_request(err: super::Error) -> axum::response::Response {
+    (
+        axum::http::StatusCode::BAD_REQUEST,
+        axum::Json(Response::<()>::Err(err)),
+    )
+        .into_response()
+}
+
+/// Parses an auction along with the extensions of its schema.
+fn parse_auction(
+    body: serde_json::Value,
+) -> Result<(dto::Auction, dto::Extensions), serde_json::Error> {
+    let extensions = dto::Extensions::deserialize(&body)?;
+    Ok((serde_json::from_value(body)?, extensions))
+}
+
+enum Request {
+    Single(Box<(dto::Auction, dto::Extensions)>),
+    Batch(Vec<(dto::Auction, dto::Extensions)>),
+}
+
+impl Request {
+    /// Parses the request body, detecting batches by their JSON shape.
+    fn parse(body: serde_json::Value) -> Result<Self, serde_json::Error> {
+        match body {
+            serde_json::Value::Array(auctions) => auctions
+                .into_iter()
+                .map(parse_auction)
+                .collect::<Result<_, _>>()
+                .map(Self::Batch),
+            body => parse_auction(body).map(|auction| Self::Single(Box::new(auction))),
+        }
+    }
+
+    fn auctions(&self) -> &[(dto::Auction, dto::Extensions)] {
+        match self {
+            Self::Single(auction) => std::slice::from_ref(auction),
+            Self::Batch(auctions) => auctions,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Solutions {
+    Single(dto::Solutions),
+    Batch(Vec<dto::Solutions>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_deadline_by_header_and_timeout() {
+        let mut headers = axum::http::HeaderMap::new();
+        let timeout = Duration::from_secs(30);
+        let within_timeout = |deadline: auction::Deadline| {
+            deadline.remaining().unwrap() <= timeout
+                && deadline.remaining().unwrap() > Duration::from_secs(25)
+        };
+
+        assert!(within_timeout(deadline(&headers, timeout).unwrap()));
+
+        headers.insert(DEADLINE_HEADER, "2106-01-01T00:00:00Z".parse().unwrap());
+        assert!(within_timeout(deadline(&headers, timeout).unwrap()));
+
+        headers.insert(DEADLINE_HEADER, "2000-01-01T00:00:00Z".parse().unwrap());
+        assert_eq!(
+            deadline(&headers, timeout).unwrap().0,
+            "2000-01-01T00:00:00Z"
+                .parse::<chrono::DateTime<chrono::Utc>>()
+                .unwrap(),
+        );
+
+        headers.insert(DEADLINE_HEADER, "soon".parse().unwrap());
+        assert!(deadline(&headers, timeout).is_err());
+    }
 }
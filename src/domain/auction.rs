@@ -17,6 +17,12 @@ pub struct Auction {
     pub liquidity: Vec<liquidity::Liquidity>,
     pub gas_price: GasPrice,
     pub deadline: Deadline,
+    /// Whether the orders are only quoted for their prices, without building
+    /// any solutions.
+    pub dry_run: bool,
+    /// Whether to request fresh swaps from the DEX API for the orders,
+    /// bypassing the quote cache and the sharing of identical requests.
+    pub force_fresh: bool,
 }
 
 /// Information about tokens used in the auction.
@@ -100,6 +106,11 @@ impl Deadline {
             .ok()
     }
 
+    /// Returns the earlier of the two deadlines.
+    pub fn min(self, other: Self) -> Self {
+        Self(self.0.min(other.0))
+    }
+
     /// Returns a new deadline with the specified duration subtracted.
     pub fn reduce(self, duration: chrono::Duration) -> Self {
         Self(self.0 - duration)
@@ -6,25 +6,38 @@ use {
     crate::{
         domain::{self, auction, eth, order, solution},
         infra,
-        util,
+        util::{self, conv},
     },
+    bigdecimal::{BigDecimal, ToPrimitive, Zero},
     ethereum_types::U256,
     std::fmt::{self, Debug, Formatter},
 };
 
 pub mod slippage;
+pub mod source;
+#[cfg(test)]
+pub mod testing;
 
-pub use self::slippage::Slippage;
+pub use self::{slippage::Slippage, source::Source};
 
 /// An order for quoting with an external DEX or DEX aggregator. This is a
 /// simplified representation of a CoW Protocol order.
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Order {
     pub sell: eth::TokenAddress,
     pub buy: eth::TokenAddress,
     pub side: order::Side,
     pub amount: Amount,
     pub owner: eth::H160,
+    /// Whether the order may be filled partially.
+    pub partially_fillable: bool,
+    /// The fraction of `amount` that should be quoted for partially fillable
+    /// orders, e.g. because the full amount has no liquidity. The full amount
+    /// is quoted if `None`.
+    pub fill_ratio: Option<FillRatio>,
+    /// The slippage tolerance requested for the order, if it overrides the
+    /// configured relative slippage.
+    pub slippage: Option<Slippage>,
 }
 
 impl Order {
@@ -38,6 +51,51 @@ impl Order {
                 order::Side::Sell => order.sell.amount,
             }),
             owner: order.owner(),
+            partially_fillable: order.partially_fillable,
+            fill_ratio: None,
+            slippage: order.slippage_bps.map(Slippage::from_bps),
+        }
+    }
+
+    /// Returns the order with a fraction of its amount to quote.
+    pub fn with_fill_ratio(self, fill_ratio: FillRatio) -> Self {
+        Self {
+            fill_ratio: Some(fill_ratio),
+            ..self
+        }
+    }
+
+    /// Returns the order with its amount scaled by the fill ratio, which is
+    /// the order that actually gets quoted. Fill-or-kill orders are always
+    /// quoted for their full amount.
+    pub fn scaled(&self) -> Self {
+        let amount = match &self.fill_ratio {
+            Some(fill_ratio) if self.partially_fillable => fill_ratio.scale(self.amount.0),
+            _ => self.amount.0,
+        };
+        Self {
+            amount: Amount(amount),
+            fill_ratio: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the order with the native token it buys replaced by WETH, for
+    /// quoting with DEX APIs that only swap ERC20 tokens.
+    pub fn wrapped(&self, weth: eth::WethAddress) -> Self {
+        Self {
+            sell: self.sell,
+            buy: if self.buy.is_native() {
+                eth::TokenAddress(weth.0)
+            } else {
+                self.buy
+            },
+            side: self.side,
+            amount: Amount(self.amount.0),
+            owner: self.owner,
+            partially_fillable: self.partially_fillable,
+            fill_ratio: self.fill_ratio.clone(),
+            slippage: self.slippage.clone(),
         }
     }
 
@@ -52,14 +110,44 @@ impl Order {
             amount: self.amount.0,
         }
     }
+
+    /// Returns the value in Ether of the order swapped amount at the reference
+    /// price of its token.
+    pub fn value(&self, tokens: &auction::Tokens) -> Option<eth::Ether> {
+        let amount = self.amount();
+        let price = tokens.reference_price(&amount.token)?;
+        conv::decimal_to_ether(
+            &(conv::ether_to_decimal(&eth::Ether(amount.amount))
+                * conv::ether_to_decimal(&price.0)),
+        )
+    }
 }
 
 /// An on-chain Ethereum call for executing a DEX swap.
+#[derive(Clone)]
 pub struct Call {
     /// The address that gets called on-chain.
     pub to: eth::ContractAddress,
     /// The associated calldata for the on-chain call.
     pub calldata: Vec<u8>,
+    /// What the call does as part of the swap.
+    pub purpose: Purpose,
+}
+
+impl Call {
+    /// Creates a new call that executes (part of) the actual swap.
+    pub fn swap(to: eth::ContractAddress, calldata: Vec<u8>) -> Self {
+        Self {
+            to,
+            calldata,
+            purpose: Purpose::Swap,
+        }
+    }
+
+    /// Returns the 4-byte function selector of the call, if any.
+    pub fn selector(&self) -> Option<[u8; 4]> {
+        self.calldata.get(..4)?.try_into().ok()
+    }
 }
 
 impl Debug for Call {
@@ -67,12 +155,54 @@ impl Debug for Call {
         f.debug_struct("Call")
             .field("to", &self.to)
             .field("calldata", &util::fmt::Hex(&self.calldata))
+            .field("purpose", &self.purpose)
             .finish()
     }
 }
 
-/// A DEX swap.
+/// The role of a call within a multi-call swap.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Purpose {
+    /// Grants a token allowance required by a subsequent call.
+    Approval,
+    /// Wraps or unwraps the native token.
+    Wrap,
+    /// Executes (part of) the swap.
+    Swap,
+}
+
+/// A structured description of the calls executed by a swap, in order.
 #[derive(Debug)]
+pub struct ExecutionPlan(pub Vec<Step>);
+
+/// A single step of an [`ExecutionPlan`].
+pub struct Step {
+    /// The address that gets called on-chain.
+    pub target: eth::ContractAddress,
+    /// The 4-byte function selector of the call.
+    pub selector: Option<[u8; 4]>,
+    /// What the call does as part of the swap.
+    pub purpose: Purpose,
+    /// The gas used by this step, when known.
+    pub gas: Option<eth::Gas>,
+}
+
+impl Debug for Step {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Step")
+            .field("target", &self.target)
+            .field(
+                "selector",
+                &self.selector.as_ref().map(|s| util::fmt::Hex(s)),
+            )
+            .field("purpose", &self.purpose)
+            .field("gas", &self.gas)
+            .finish()
+    }
+}
+
+/// A DEX swap.
+#[derive(Clone, Debug)]
 pub struct Swap {
     /// The Ethereum calls for executing the swap.
     pub calls: Vec<Call>,
@@ -89,9 +219,110 @@ pub struct Swap {
     /// This estimate is **not** expected to be accurate, and is purely
     /// indicative.
     pub gas: eth::Gas,
+    /// Informational data about how the swap was obtained. It does not affect
+    /// the resulting solution.
+    pub metadata: Metadata,
+}
+
+/// Additional information about a DEX swap.
+#[derive(Clone, Debug, Default)]
+pub struct Metadata {
+    /// How long the upstream DEX API took to respond with the swap, in
+    /// milliseconds.
+    pub upstream_latency_ms: Option<u64>,
+    /// Whether the swap is routed through private market maker (RFQ)
+    /// liquidity. Such quotes usually expire quickly. This is only detected
+    /// for backends that report the sources of their routes.
+    pub private_liquidity: bool,
+    /// The cost of the swap gas estimate in wei, using the gas price that was
+    /// used for the quote (or the auction gas price otherwise).
+    pub gas_cost_wei: Option<U256>,
+    /// The liquidity sources that the swap is routed through, in canonical
+    /// form. This is only reported by some backends.
+    pub sources: Vec<Source>,
+    /// Non-fatal issues with the swap that operators should be aware of.
+    pub warnings: Vec<String>,
+    /// The fee that the swap implicitly charges compared to the reference
+    /// prices of its tokens, in basis points. `None` if the reference prices
+    /// are unknown.
+    pub implied_fee_bps: Option<i64>,
+    /// How reliable the swap gas estimate is.
+    pub gas_confidence: GasConfidence,
+    /// The fraction of the swap input value that is lost at the reference
+    /// prices of its tokens. `None` if the reference prices are unknown or
+    /// the backend does not report it.
+    pub price_impact: Option<BigDecimal>,
+    /// The swap price in buy token units per sell token unit, normalized for
+    /// token decimals. `None` if the decimals are unknown.
+    pub price: Option<BigDecimal>,
+    /// The swap price in sell token units per buy token unit, normalized for
+    /// token decimals. `None` if the decimals are unknown.
+    pub inverse_price: Option<BigDecimal>,
+    /// When the swap calls stop being valid on-chain, if they expire at all.
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The amount the swap actually fills, if the DEX only routed part of the
+    /// order amount. The swap input and output already reflect it.
+    pub filled_amount: Option<U256>,
+    /// The fraction of the full amount of a partially fillable order that the
+    /// swap fills.
+    pub fill_ratio: Option<FillRatio>,
+    /// The candidates that a meta DEX considered but did not pick for the
+    /// swap.
+    pub rejected: Vec<Rejected>,
+}
+
+/// A swap candidate of a meta DEX backend that was not picked.
+#[derive(Clone, Debug)]
+pub struct Rejected {
+    /// The identifier of the backend, e.g. `zeroex`.
+    pub backend: &'static str,
+    /// The input and output amounts of the swap that the backend quoted, if
+    /// it quoted one.
+    pub amounts: Option<(U256, U256)>,
+    pub reason: Rejection,
+}
+
+/// Why a meta DEX did not pick the swap of a backend.
+#[derive(Clone, Debug)]
+pub enum Rejection {
+    /// Another backend quoted a better swap.
+    Outranked,
+    /// The backend failed to quote a swap, with the name of the error.
+    Failed(&'static str),
+    /// The backend was still quoting when a good enough swap was picked.
+    Cancelled,
+}
+
+/// How a swap gas estimate was obtained, which indicates how much it should be
+/// padded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GasConfidence {
+    /// The DEX API estimated the gas for the specific swap route.
+    Measured,
+    /// The gas was derived locally from the shape of the swap route.
+    Heuristic,
+    /// Nothing is known about how the gas estimate was obtained.
+    #[default]
+    Default,
 }
 
 impl Swap {
+    /// Describes the calls of the swap. The swap gas estimate can only be
+    /// attributed to a step when the swap consists of a single call.
+    pub fn execution_plan(&self) -> ExecutionPlan {
+        ExecutionPlan(
+            self.calls
+                .iter()
+                .map(|call| Step {
+                    target: call.to,
+                    selector: call.selector(),
+                    purpose: call.purpose,
+                    gas: (self.calls.len() == 1).then_some(self.gas),
+                })
+                .collect(),
+        )
+    }
+
     pub fn allowance(&self) -> solution::Allowance {
         solution::Allowance {
             spender: self.allowance.spender.0,
@@ -104,12 +335,16 @@ impl Swap {
 
     /// Constructs a single order `solution::Solution` for this swap. Returns
     /// `None` if the swap is not valid for the specified order.
+    ///
+    /// The swap gas is simulated for limit orders, and for all other orders
+    /// if `simulate_gas` is set.
     pub async fn into_solution(
         self,
         order: order::Order,
         gas_price: auction::GasPrice,
         sell_token: Option<auction::Price>,
         simulator: &infra::dex::Simulator,
+        simulate_gas: bool,
         gas_offset: eth::Gas,
     ) -> Option<solution::Solution> {
         let gas = if order.class == order::Class::Limit {
@@ -121,6 +356,14 @@ impl Swap {
                     return None;
                 }
             }
+        } else if simulate_gas {
+            simulator
+                .gas(order.owner(), &self)
+                .await
+                .unwrap_or_else(|err| {
+                    tracing::debug!(?err, "gas simulation failed; using swap gas estimate");
+                    self.gas
+                })
         } else {
             // We are fine with just using heuristic gas for market orders,
             // since it doesn't really play a role in the final solution.
@@ -158,10 +401,120 @@ impl Swap {
         self.output.amount.full_mul(order.sell.amount)
             >= self.input.amount.full_mul(order.buy.amount)
     }
+
+    /// Returns the swap without any calls, keeping only its amounts, gas
+    /// estimate and allowance. Such a swap can be used as a price but never
+    /// be executed.
+    pub fn without_calls(self) -> Self {
+        Self {
+            calls: Vec::new(),
+            ..self
+        }
+    }
+
+    /// Returns the fraction of the order amount that the swap fills. `None` if
+    /// the swap does not fill the order at all or overfills it.
+    pub fn fill_ratio(&self, order: &Order) -> Option<FillRatio> {
+        let filled = match order.side {
+            order::Side::Buy => self.output.amount,
+            order::Side::Sell => self.input.amount,
+        };
+        FillRatio::new(filled, order.amount.0)
+    }
+
+    /// The total size of the calldata of all swap calls.
+    pub fn calldata_bytes(&self) -> usize {
+        self.calls.iter().map(|call| call.calldata.len()).sum()
+    }
+
+    /// Computes the swap price (output per input) and its inverse in whole
+    /// token units, i.e. normalized for the token decimals.
+    pub fn prices(&self, tokens: &auction::Tokens) -> Option<(BigDecimal, BigDecimal)> {
+        let units = |asset: &eth::Asset| {
+            let decimals = tokens.decimals(&asset.token)?;
+            let unit = BigDecimal::new(1.into(), -i64::from(decimals));
+            Some(conv::u256_to_bigdecimal(&asset.amount) / unit)
+        };
+        let (input, output) = (units(&self.input)?, units(&self.output)?);
+        if input.is_zero() || output.is_zero() {
+            return None;
+        }
+        Some((&output / &input, input / output))
+    }
+
+    /// Computes the spread between the value of the swap input and output at
+    /// the reference prices, in basis points of the input value. A negative
+    /// fee means the swap is better than the reference prices.
+    pub fn implied_fee_bps(&self, tokens: &auction::Tokens) -> Option<i64> {
+        (self.value_loss(tokens)? * BigDecimal::from(10_000))
+            .round(0)
+            .to_i64()
+    }
+
+    /// Computes the price impact of the swap as the fraction of the input
+    /// value that is lost at the reference prices. Swaps that are better than
+    /// the reference prices have no price impact.
+    pub fn price_impact(&self, tokens: &auction::Tokens) -> Option<BigDecimal> {
+        Some(self.value_loss(tokens)?.max(BigDecimal::zero()))
+    }
+
+    /// Computes how far the swap price deviates from the reference prices, as
+    /// the fraction of the input value that is lost or gained.
+    pub fn price_deviation(&self, tokens: &auction::Tokens) -> Option<BigDecimal> {
+        Some(self.value_loss(tokens)?.abs())
+    }
+
+    /// Computes how good the swap is for an order of the specified side, net
+    /// of its gas cost: the output minus the gas cost in output tokens for
+    /// sell orders, and the negated input plus the gas cost in input tokens
+    /// for buy orders. Higher is better, so swaps for the same order can be
+    /// compared with each other. The gas cost is ignored if the reference
+    /// price of the token it is priced in is unknown.
+    pub fn net_output(
+        &self,
+        side: order::Side,
+        gas_price: auction::GasPrice,
+        tokens: &auction::Tokens,
+    ) -> BigDecimal {
+        let asset = match side {
+            order::Side::Sell => &self.output,
+            order::Side::Buy => &self.input,
+        };
+        let gas_cost = self
+            .gas
+            .0
+            .checked_mul(gas_price.0 .0)
+            .and_then(|cost| {
+                tokens
+                    .reference_price(&asset.token)?
+                    .ether_value(eth::Ether(cost))
+            })
+            .unwrap_or_default();
+        let amount = conv::u256_to_bigdecimal(&asset.amount);
+        let gas_cost = conv::u256_to_bigdecimal(&gas_cost);
+        match side {
+            order::Side::Sell => amount - gas_cost,
+            order::Side::Buy => -(amount + gas_cost),
+        }
+    }
+
+    /// The spread between the value of the swap input and output at the
+    /// reference prices, as a fraction of the input value.
+    fn value_loss(&self, tokens: &auction::Tokens) -> Option<BigDecimal> {
+        let value = |asset: &eth::Asset| {
+            let price = tokens.reference_price(&asset.token)?;
+            Some(conv::u256_to_bigdecimal(&asset.amount) * conv::ether_to_decimal(&price.0))
+        };
+        let (input, output) = (value(&self.input)?, value(&self.output)?);
+        if input.is_zero() {
+            return None;
+        }
+        Some((input.clone() - output) / input)
+    }
 }
 
 /// A swap allowance.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Allowance {
     /// The spender address that requires an allowance in order to execute a
     /// swap.
@@ -171,9 +524,36 @@ pub struct Allowance {
 }
 
 /// A token amount.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Amount(U256);
 
+/// The fraction of an order amount that is filled, in the range `(0, 1]`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FillRatio(BigDecimal);
+
+impl FillRatio {
+    /// Returns the fraction `amount` is of `total`, or `None` if it is not a
+    /// valid fill of `total`.
+    pub fn new(amount: U256, total: U256) -> Option<Self> {
+        if amount.is_zero() || amount > total {
+            return None;
+        }
+        Some(Self(
+            conv::u256_to_bigdecimal(&amount) / conv::u256_to_bigdecimal(&total),
+        ))
+    }
+
+    pub fn get(&self) -> &BigDecimal {
+        &self.0
+    }
+
+    /// Scales an amount by the ratio, rounded to the nearest token atom.
+    fn scale(&self, amount: U256) -> U256 {
+        conv::bigdecimal_to_u256(&(conv::u256_to_bigdecimal(&amount) * &self.0).round(0))
+            .expect("fill ratio is at most 1")
+    }
+}
+
 impl Amount {
     pub fn new(value: U256) -> Self {
         Self(value)
@@ -183,3 +563,229 @@ impl Amount {
         self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        testing::{asset, ether, order, swap, token, token_info},
+    };
+
+    #[test]
+    fn implied_fee_from_reference_prices() {
+        let weth = eth::TokenAddress(
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+                .parse()
+                .unwrap(),
+        );
+        let cow = eth::TokenAddress(
+            "0xDEf1CA1fb7FBcDC777520aa7f396b4E015F497aB"
+                .parse()
+                .unwrap(),
+        );
+        let swap = |output: u64| {
+            swap(
+                asset(weth, ether("1.0").0),
+                asset(cow, ether("1.0").0 * output),
+            )
+        };
+
+        let tokens = auction::Tokens(
+            [
+                (weth, token_info(None, Some("1.0"))),
+                (cow, token_info(None, Some("0.000057"))),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        // 17,000 COW are worth 0.969 WETH at the reference price.
+        assert_eq!(swap(17_000).implied_fee_bps(&tokens), Some(310));
+        // 18,000 COW are worth 1.026 WETH, which beats the reference price.
+        assert_eq!(swap(18_000).implied_fee_bps(&tokens), Some(-260));
+        assert_eq!(
+            swap(17_000).price_impact(&tokens),
+            Some("0.031".parse().unwrap())
+        );
+        assert_eq!(swap(18_000).price_impact(&tokens), Some(BigDecimal::zero()));
+        assert_eq!(
+            swap(18_000).price_deviation(&tokens),
+            Some("0.026".parse().unwrap())
+        );
+
+        let tokens = auction::Tokens(
+            [
+                (weth, token_info(None, Some("1.0"))),
+                (cow, token_info(None, None)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(swap(17_000).implied_fee_bps(&tokens), None);
+        assert_eq!(swap(17_000).price_impact(&tokens), None);
+        assert_eq!(swap(17_000).price_deviation(&tokens), None);
+    }
+
+    #[test]
+    fn wraps_native_buy_token() {
+        let weth = eth::WethAddress(eth::H160([0x42; 20]));
+        let order = |sell, buy| Order {
+            sell,
+            buy,
+            ..order(1)
+        };
+
+        let buy_native = order(token(1), eth::TokenAddress::NATIVE).wrapped(weth);
+        assert_eq!(
+            (buy_native.sell, buy_native.buy),
+            (token(1), eth::TokenAddress(weth.0))
+        );
+        // Selling the native token is not supported, and the order is left
+        // as is.
+        let sell_native = order(eth::TokenAddress::NATIVE, token(1)).wrapped(weth);
+        assert_eq!(
+            (sell_native.sell, sell_native.buy),
+            (eth::TokenAddress::NATIVE, token(1))
+        );
+    }
+
+    #[test]
+    fn net_output_subtracts_gas_cost() {
+        let swap = |input: u64, output: u64, gas: u64| Swap {
+            gas: eth::Gas(gas.into()),
+            ..swap(asset(token(1), input), asset(token(2), output))
+        };
+        // The output token is worth half an Ether, so each wei of gas cost
+        // is worth 2 output token atoms.
+        let tokens = auction::Tokens(
+            [(token(2), token_info(None, Some("0.5")))]
+                .into_iter()
+                .collect(),
+        );
+        let gas_price = auction::GasPrice(eth::Ether(10.into()));
+
+        assert_eq!(
+            swap(1_000, 10_000, 100).net_output(order::Side::Sell, gas_price, &tokens),
+            BigDecimal::from(8_000)
+        );
+        // A better output does not make up for a much higher gas cost.
+        assert!(
+            swap(1_000, 10_500, 200).net_output(order::Side::Sell, gas_price, &tokens)
+                < swap(1_000, 10_000, 100).net_output(order::Side::Sell, gas_price, &tokens)
+        );
+        // The input token has no reference price, so gas is ignored.
+        assert_eq!(
+            swap(1_000, 10_000, 100).net_output(order::Side::Buy, gas_price, &tokens),
+            BigDecimal::from(-1_000)
+        );
+    }
+
+    #[test]
+    fn prices_normalized_for_decimals() {
+        let usdc = eth::TokenAddress(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+                .parse()
+                .unwrap(),
+        );
+        let weth = eth::TokenAddress(
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+                .parse()
+                .unwrap(),
+        );
+        // Sell 2,500 USDC for 1 WETH.
+        let swap = swap(asset(usdc, 2_500_000_000_u64), asset(weth, U256::exp10(18)));
+
+        let tokens = auction::Tokens(
+            [
+                (usdc, token_info(Some(6), None)),
+                (weth, token_info(Some(18), None)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(
+            swap.prices(&tokens),
+            Some(("0.0004".parse().unwrap(), "2500".parse().unwrap())),
+        );
+
+        let tokens = auction::Tokens(
+            [
+                (usdc, token_info(Some(6), None)),
+                (weth, token_info(None, None)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(swap.prices(&tokens), None);
+    }
+
+    #[test]
+    fn order_value_from_reference_price() {
+        let usdc = eth::TokenAddress(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+                .parse()
+                .unwrap(),
+        );
+        let weth = eth::TokenAddress(
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+                .parse()
+                .unwrap(),
+        );
+        // 2,000 USDC for sell orders or 2,000 WETH for buy orders.
+        let order = |side| Order {
+            sell: usdc,
+            buy: weth,
+            side,
+            ..order(2_000_000_000_u64)
+        };
+
+        let tokens = auction::Tokens(
+            [
+                (usdc, token_info(None, Some("589783000.0"))),
+                (weth, token_info(None, None)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(
+            order(order::Side::Sell).value(&tokens),
+            Some(eth::Ether(U256::from(1_179_566_000_000_000_000_u64))),
+        );
+        assert_eq!(order(order::Side::Buy).value(&tokens), None);
+    }
+
+    #[test]
+    fn scales_partially_fillable_orders() {
+        let half = FillRatio::new(U256::from(1), U256::from(2)).unwrap();
+        let order = |side, partially_fillable| {
+            Order {
+                side,
+                partially_fillable,
+                ..order(2_000)
+            }
+            .with_fill_ratio(half.clone())
+        };
+        let swap = |input: u64, output: u64| swap(asset(token(1), input), asset(token(2), output));
+
+        for side in [order::Side::Sell, order::Side::Buy] {
+            let scaled = order(side, true).scaled();
+            assert_eq!(scaled.amount.get(), U256::from(1_000));
+            assert_eq!(scaled.fill_ratio, None);
+            // Fill-or-kill orders are quoted for their full amount.
+            assert_eq!(order(side, false).scaled().amount.get(), U256::from(2_000));
+        }
+
+        // 50% fills of the full order amount on both sides.
+        let sell_order = order(order::Side::Sell, true);
+        assert_eq!(
+            swap(1_000, 3_000).fill_ratio(&sell_order),
+            Some(half.clone())
+        );
+        assert_eq!(
+            swap(2_000, 6_000).fill_ratio(&sell_order).unwrap().get(),
+            &BigDecimal::from(1)
+        );
+        let buy_order = order(order::Side::Buy, true);
+        assert_eq!(swap(3_000, 1_000).fill_ratio(&buy_order), Some(half));
+        assert_eq!(swap(3_000, 3_000).fill_ratio(&buy_order), None);
+    }
+}
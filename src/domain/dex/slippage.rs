@@ -21,16 +21,49 @@ pub struct Limits {
     absolute: Option<eth::Ether>,
 }
 
+/// The maximum of all slippage tolerances, which guards against
+/// misconfigurations and absurd requests for all DEX APIs alike.
+#[derive(Clone, Debug)]
+pub struct Cap {
+    max: BigDecimal,
+    policy: CapPolicy,
+}
+
+/// What to do with a slippage tolerance that exceeds the maximum.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CapPolicy {
+    /// Use the maximum slippage tolerance instead.
+    #[default]
+    Clamp,
+    /// Don't swap at all.
+    Reject,
+}
+
+/// Whether a relative slippage value is within the valid range of [0, 1].
+fn valid(value: &BigDecimal) -> bool {
+    *value >= Zero::zero() && *value <= One::one()
+}
+
 impl Limits {
     /// Creates a new [`Limits`] instance. Returns `None` if the `relative`
-    /// slippage limit outside the valid range of [0, 1].
+    /// slippage limit is outside the valid range of [0, 1].
     pub fn new(relative: BigDecimal, absolute: Option<eth::Ether>) -> Option<Self> {
-        (relative >= Zero::zero() && relative <= One::one()).then_some(Self { relative, absolute })
+        valid(&relative).then_some(Self { relative, absolute })
     }
 
     /// Computes the actual slippage tolerance to use for an asset using the
-    /// specified reference prices.
-    pub fn relative(&self, asset: &eth::Asset, tokens: &auction::Tokens) -> Slippage {
+    /// specified reference prices. The slippage `requested` for the order
+    /// replaces the default relative limit.
+    pub fn relative(
+        &self,
+        asset: &eth::Asset,
+        requested: Option<&Slippage>,
+        tokens: &auction::Tokens,
+    ) -> Slippage {
+        let relative = match requested {
+            Some(requested) => requested.0.clone(),
+            None => self.relative.clone(),
+        };
         if let (Some(absolute), Some(price)) =
             (&self.absolute, tokens.reference_price(&asset.token))
         {
@@ -39,21 +72,47 @@ impl Limits {
                 * conv::ether_to_decimal(&price.0);
 
             let max_relative = absolute / amount;
-            let tolerance = cmp::min(max_relative, self.relative.clone());
-
-            Slippage(tolerance)
+            Slippage(cmp::min(max_relative, relative))
         } else {
-            Slippage(self.relative.clone())
+            Slippage(relative)
         }
     }
 }
 
+impl Cap {
+    /// Creates a new [`Cap`] instance. Returns `None` if the `max` slippage is
+    /// outside the valid range of [0, 1].
+    pub fn new(max: BigDecimal, policy: CapPolicy) -> Option<Self> {
+        valid(&max).then_some(Self { max, policy })
+    }
+
+    /// Applies the cap to a slippage tolerance. Returns an error if the
+    /// slippage exceeds the maximum and the policy is to reject it.
+    pub fn apply(&self, slippage: Slippage) -> Result<Slippage, ExceedsMax> {
+        if slippage.0 <= self.max {
+            return Ok(slippage);
+        }
+        match self.policy {
+            CapPolicy::Clamp => {
+                tracing::warn!(?slippage, max = %self.max, "clamping slippage to the maximum");
+                Ok(Slippage(self.max.clone()))
+            }
+            CapPolicy::Reject => Err(ExceedsMax(slippage)),
+        }
+    }
+}
+
+/// A slippage tolerance exceeds the maximum.
+#[derive(Debug, thiserror::Error)]
+#[error("slippage {0:?} exceeds the maximum")]
+pub struct ExceedsMax(pub Slippage);
+
 /// A relative slippage tolerance.
 ///
 /// Relative slippage has saturating semantics. I.e. if adding slippage to a
 /// token amount would overflow a `U256`, then `U256::max_value()` is returned
 /// instead.
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, Hash, PartialEq, Clone)]
 pub struct Slippage(BigDecimal);
 
 impl Slippage {
@@ -61,6 +120,10 @@ impl Slippage {
         Self("0.01".parse().unwrap())
     }
 
+    pub fn from_bps(basis_points: u16) -> Self {
+        Self(BigDecimal::new(basis_points.into(), 4))
+    }
+
     /// Adds slippage to the specified token amount. This can be used to account
     /// for negative slippage in a sell amount.
     pub fn add(&self, amount: U256) -> U256 {
@@ -212,7 +275,7 @@ mod tests {
             let min = U256::from(min);
             let max = U256::from(max);
 
-            let computed = slippage.relative(&asset, &tokens);
+            let computed = slippage.relative(&asset, None, &tokens);
 
             assert_eq!(computed.round(9), relative);
             assert_eq!(computed.sub(asset.amount), min);
@@ -220,6 +283,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn caps_slippage() {
+        let cap = |policy| Cap::new("0.05".parse().unwrap(), policy).unwrap();
+
+        // Misconfigured maximums are rejected upfront.
+        assert!(Cap::new("1.5".parse().unwrap(), CapPolicy::Clamp).is_none());
+
+        for policy in [CapPolicy::Clamp, CapPolicy::Reject] {
+            // Tighter slippage for large orders, and more tolerance for small
+            // ones.
+            for requested in [10, 300, 500] {
+                assert_eq!(
+                    cap(policy).apply(Slippage::from_bps(requested)).unwrap(),
+                    Slippage::from_bps(requested)
+                );
+            }
+        }
+
+        // Absurdly requested slippage is clamped...
+        assert_eq!(
+            cap(CapPolicy::Clamp)
+                .apply(Slippage::from_bps(10_000))
+                .unwrap(),
+            Slippage::from_bps(500)
+        );
+        // ... or rejected.
+        assert!(cap(CapPolicy::Reject)
+            .apply(Slippage::from_bps(10_000))
+            .is_err());
+    }
+
+    #[test]
+    fn replaces_relative_slippage_with_requested() {
+        let tokens = auction::Tokens(Default::default());
+        let asset = eth::Asset {
+            token: eth::TokenAddress(eth::H160([0x01; 20])),
+            amount: 1_000_000.into(),
+        };
+
+        // Misconfigured slippage is rejected upfront.
+        assert!(Limits::new("1.5".parse().unwrap(), None).is_none());
+
+        let slippage = Limits::new("0.01".parse().unwrap(), None).unwrap();
+        assert_eq!(
+            slippage.relative(&asset, None, &tokens),
+            Slippage::from_bps(100)
+        );
+        assert_eq!(
+            slippage.relative(&asset, Some(&Slippage::from_bps(300)), &tokens),
+            Slippage::from_bps(300)
+        );
+    }
+
     #[test]
     fn round_does_not_panic() {
         let slippage = Slippage(
@@ -0,0 +1,92 @@
+//! Canonical taxonomy of on-chain liquidity sources.
+
+use std::fmt::{self, Display, Formatter};
+
+/// A liquidity source that a DEX aggregator routes swaps through. Each
+/// aggregator uses its own naming scheme for the protocols it supports, so
+/// sources are mapped onto this shared taxonomy in order to make them
+/// comparable across backends.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Source {
+    UniswapV2,
+    UniswapV3,
+    SushiSwap,
+    Curve,
+    BalancerV1,
+    BalancerV2,
+    Bancor,
+    DodoV2,
+    Lido,
+    ShibaSwap,
+    /// A liquidity source that is not part of the canonical taxonomy, named
+    /// as the backend reported it.
+    Other(String),
+}
+
+/// The canonical names of the known liquidity sources.
+const NAMES: &[(Source, &str)] = &[
+    (Source::UniswapV2, "uniswap-v2"),
+    (Source::UniswapV3, "uniswap-v3"),
+    (Source::SushiSwap, "sushiswap"),
+    (Source::Curve, "curve"),
+    (Source::BalancerV1, "balancer-v1"),
+    (Source::BalancerV2, "balancer-v2"),
+    (Source::Bancor, "bancor"),
+    (Source::DodoV2, "dodo-v2"),
+    (Source::Lido, "lido"),
+    (Source::ShibaSwap, "shibaswap"),
+];
+
+impl Source {
+    /// Returns the canonical source for the specified canonical name, or
+    /// [`Source::Other`] if the name is not part of the taxonomy.
+    pub fn from_name(name: &str) -> Self {
+        NAMES
+            .iter()
+            .find(|(_, canonical)| *canonical == name)
+            .map(|(source, _)| source.clone())
+            .unwrap_or_else(|| Self::Other(name.to_owned()))
+    }
+
+    /// Whether the source is a public AMM, whose swaps are visible in the
+    /// public mempool and can be sandwiched. Lido stakes at a fixed rate and
+    /// unknown sources are not assumed to be AMMs.
+    pub fn is_public_amm(&self) -> bool {
+        !matches!(self, Self::Lido | Self::Other(_))
+    }
+
+    /// Returns the canonical name of the source.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Other(name) => name,
+            source => NAMES
+                .iter()
+                .find(|(known, _)| known == source)
+                .map(|(_, name)| *name)
+                .expect("all known sources have a canonical name"),
+        }
+    }
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_names_roundtrip() {
+        for (source, name) in NAMES {
+            assert_eq!(source.name(), *name);
+            assert_eq!(Source::from_name(name), *source);
+        }
+        assert_eq!(
+            Source::from_name("Unknown_AMM"),
+            Source::Other("Unknown_AMM".to_owned())
+        );
+    }
+}
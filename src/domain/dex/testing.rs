@@ -0,0 +1,70 @@
+//! Fixtures for unit tests that work with DEX orders and swaps.
+
+use {
+    crate::{
+        domain::{auction, dex, eth, order},
+        util::conv,
+    },
+    ethereum_types::U256,
+};
+
+/// A token address consisting of a single repeated byte.
+pub fn token(byte: u8) -> eth::TokenAddress {
+    eth::TokenAddress(eth::H160::repeat_byte(byte))
+}
+
+/// An amount of a token.
+pub fn asset(token: eth::TokenAddress, amount: impl Into<U256>) -> eth::Asset {
+    eth::Asset {
+        token,
+        amount: amount.into(),
+    }
+}
+
+/// Parses an Ether amount from a decimal string, e.g. "0.5".
+pub fn ether(value: &str) -> eth::Ether {
+    conv::decimal_to_ether(&value.parse().unwrap()).unwrap()
+}
+
+/// A fill-or-kill order without owner that sells `amount` of token `0x01..`
+/// for token `0x02..`.
+pub fn order(amount: impl Into<U256>) -> dex::Order {
+    dex::Order {
+        sell: token(1),
+        buy: token(2),
+        side: order::Side::Sell,
+        amount: dex::Amount::new(amount.into()),
+        owner: eth::H160::zero(),
+        partially_fillable: false,
+        fill_ratio: None,
+        slippage: None,
+    }
+}
+
+/// A swap of `input` for `output` without calls or gas, requiring an
+/// allowance of the input amount for the zero address.
+pub fn swap(input: eth::Asset, output: eth::Asset) -> dex::Swap {
+    dex::Swap {
+        calls: Default::default(),
+        input,
+        output,
+        allowance: dex::Allowance {
+            spender: eth::ContractAddress(Default::default()),
+            amount: dex::Amount::new(input.amount),
+        },
+        gas: eth::Gas(Default::default()),
+        metadata: Default::default(),
+    }
+}
+
+/// The auction data of a token, with its reference price in Ether given as a
+/// decimal string.
+pub fn token_info(decimals: Option<u8>, reference_price: Option<&str>) -> auction::Token {
+    auction::Token {
+        decimals,
+        symbol: Default::default(),
+        reference_price: reference_price.map(|price| auction::Price(ether(price))),
+        available_balance: Default::default(),
+        trusted: Default::default(),
+    }
+}
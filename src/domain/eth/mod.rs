@@ -17,6 +17,16 @@ pub struct ContractAddress(pub H160);
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct TokenAddress(pub H160);
 
+impl TokenAddress {
+    /// The sentinel address that denotes the native token (e.g. Ether on
+    /// mainnet) in orders.
+    pub const NATIVE: Self = Self(H160([0xee; 20]));
+
+    pub fn is_native(&self) -> bool {
+        *self == Self::NATIVE
+    }
+}
+
 impl From<H160> for TokenAddress {
     fn from(inner: H160) -> Self {
         Self(inner)
@@ -16,6 +16,9 @@ pub struct Order {
     pub class: Class,
     pub partially_fillable: bool,
     pub flashloan_hint: Option<FlashloanHint>,
+    /// The slippage tolerance requested for the order in basis points, which
+    /// overrides the configured relative slippage.
+    pub slippage_bps: Option<u16>,
 }
 
 impl Order {
@@ -60,7 +63,7 @@ impl Display for Uid {
 }
 
 /// The trading side of an order.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Side {
     /// An order with a fixed buy amount and maximum sell amount.
     Buy,
@@ -1,6 +1,6 @@
 use {
     crate::{
-        domain::{auction, eth, liquidity, order},
+        domain::{auction, dex, eth, liquidity, order},
         util,
     },
     ethereum_types::{Address, U256},
@@ -27,6 +27,17 @@ pub struct Solution {
     pub post_interactions: Vec<eth::Interaction>,
     pub gas: Option<eth::Gas>,
     pub flashloans: Vec<Flashloan>,
+    /// Informational data about the DEX swap the solution executes, if it was
+    /// built from one. It is reported alongside the solution but does not
+    /// affect it.
+    pub swap: Option<SwapInfo>,
+}
+
+/// Informational data about a DEX swap that a solution executes.
+#[derive(Debug)]
+pub struct SwapInfo {
+    pub metadata: dex::Metadata,
+    pub plan: dex::ExecutionPlan,
 }
 
 impl Solution {
@@ -35,6 +46,25 @@ impl Solution {
         Self { id, ..self }
     }
 
+    /// Returns `self` with information about the DEX swap it executes.
+    pub fn with_swap(self, swap: SwapInfo) -> Self {
+        Self {
+            swap: Some(swap),
+            ..self
+        }
+    }
+
+    /// Returns `self` without the allowances its interactions require, for
+    /// when the settlement contract already approved the spenders.
+    pub fn without_allowances(mut self) -> Self {
+        for interaction in self.interactions.iter_mut() {
+            if let Interaction::Custom(interaction) = interaction {
+                interaction.allowances.clear();
+            }
+        }
+        self
+    }
+
     /// Returns `self` with eligible interactions internalized using the
     /// Settlement contract buffers.
     ///
@@ -212,6 +242,7 @@ impl Single {
                 .into_iter()
                 .collect(),
             trades: vec![Trade::Fulfillment(Fulfillment::new(order, executed, fee)?)],
+            swap: None,
         })
     }
 }
@@ -89,42 +89,8 @@ impl Fills {
             return None;
         }
 
-        // Scale amounts according to the limit price and the chosen fill.
-        let (sell_amount, buy_amount) = match order.side {
-            order::Side::Buy => {
-                let sell_amount = order
-                    .sell
-                    .amount
-                    .full_mul(amount)
-                    .checked_div(order.buy.amount.into())?
-                    .try_into()
-                    .unwrap();
-                (sell_amount, amount)
-            }
-            order::Side::Sell => {
-                let buy_amount = order
-                    .buy
-                    .amount
-                    .full_mul(amount)
-                    .checked_div(order.sell.amount.into())?
-                    .try_into()
-                    .unwrap();
-                (amount, buy_amount)
-            }
-        };
-
         tracing::trace!(?amount, "trying to partially fill order");
-        Some(dex::Order::new(&order::Order {
-            sell: eth::Asset {
-                token: order.sell.token,
-                amount: sell_amount,
-            },
-            buy: eth::Asset {
-                token: order.buy.token,
-                amount: buy_amount,
-            },
-            ..*order
-        }))
+        Some(dex::Order::new(order).with_fill_ratio(dex::FillRatio::new(amount, total_amount)?))
     }
 
     /// Adjusts the next fill amount that should be tried. Always halves the
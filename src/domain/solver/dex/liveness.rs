@@ -0,0 +1,128 @@
+use {
+    crate::{domain::dex, infra},
+    std::{
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Tracks whether the DEX API keeps answering swap requests, so that a solver
+/// that silently fails every request (e.g. because its API key was revoked)
+/// can be reported as unhealthy.
+#[derive(Debug)]
+pub struct Liveness {
+    /// How long requests may fail without a single success before the solver
+    /// is considered unhealthy.
+    window: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The first request since the last successful one.
+    failing_since: Option<Instant>,
+    /// The latest request, successful or not.
+    last_request: Option<Instant>,
+}
+
+impl Liveness {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            state: Default::default(),
+        }
+    }
+
+    /// Records the outcome of a swap request to the DEX API.
+    pub fn record(&self, swap: &Result<dex::Swap, infra::dex::Error>) {
+        if let Some(success) = success(swap) {
+            self.record_at(success, Instant::now())
+        }
+    }
+
+    /// Returns whether a swap request succeeded within the window, or no
+    /// requests were attempted within it at all.
+    pub fn is_alive(&self) -> bool {
+        self.is_alive_at(Instant::now())
+    }
+
+    fn record_at(&self, success: bool, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        state.last_request = Some(now);
+        if success {
+            state.failing_since = None;
+        } else {
+            state.failing_since.get_or_insert(now);
+        }
+    }
+
+    fn is_alive_at(&self, now: Instant) -> bool {
+        let state = self.state.lock().unwrap();
+        let stale = |time: Instant| now.saturating_duration_since(time) > self.window;
+        match (state.failing_since, state.last_request) {
+            (Some(failing_since), Some(last_request)) => {
+                !stale(failing_since) || stale(last_request)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Whether the outcome of a swap request shows that the DEX API works. Only
+/// swaps and answers that there is no swap for the order do, since e.g. a
+/// revoked API key may surface as any other error. Outcomes of requests that
+/// never reached the DEX API are not taken into account.
+fn success(swap: &Result<dex::Swap, infra::dex::Error>) -> Option<bool> {
+    match swap {
+        Ok(_) | Err(infra::dex::Error::NotFound | infra::dex::Error::InsufficientLiquidity) => {
+            Some(true)
+        }
+        Err(infra::dex::Error::OrderNotSupported | infra::dex::Error::Unavailable) => None,
+        Err(_) => Some(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_answers_of_the_dex_api_are_successes() {
+        assert_eq!(success(&Err(infra::dex::Error::NotFound)), Some(true));
+        assert_eq!(
+            success(&Err(infra::dex::Error::InsufficientLiquidity)),
+            Some(true)
+        );
+        assert_eq!(success(&Err(infra::dex::Error::Unavailable)), None);
+        assert_eq!(
+            success(&Err(infra::dex::Error::RateLimited { retry_after: None })),
+            Some(false)
+        );
+        assert_eq!(
+            success(&Err(infra::dex::Error::UnavailableForLegalReasons)),
+            Some(false)
+        );
+        assert_eq!(success(&Err(infra::dex::Error::Timeout)), Some(false));
+    }
+
+    #[test]
+    fn unhealthy_when_requests_keep_failing() {
+        let liveness = Liveness::new(Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(liveness.is_alive_at(at(0)));
+        liveness.record_at(false, at(0));
+        liveness.record_at(false, at(50));
+        assert!(liveness.is_alive_at(at(50)));
+        liveness.record_at(false, at(70));
+        assert!(!liveness.is_alive_at(at(70)));
+        // Without any requests, there is nothing to report.
+        assert!(liveness.is_alive_at(at(200)));
+
+        liveness.record_at(false, at(200));
+        assert!(!liveness.is_alive_at(at(200)));
+        liveness.record_at(true, at(201));
+        assert!(liveness.is_alive_at(at(201)));
+    }
+}
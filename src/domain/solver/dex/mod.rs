@@ -9,30 +9,52 @@ use {
             eth,
             order::{self, Order},
             solution,
-            solver::dex::fills::Fills,
+            solver::dex::{fills::Fills, liveness::Liveness},
         },
         infra,
     },
     futures::{future, stream, FutureExt, StreamExt},
     std::num::NonZeroUsize,
+    tokio::sync::Semaphore,
     tracing::Instrument,
 };
 
 mod fills;
+mod liveness;
+pub mod post_process;
 
 pub struct Dex {
     /// The DEX API client.
-    dex: infra::dex::Dex,
+    dex: infra::dex::Backend,
 
     /// A DEX swap gas simulator for computing limit order fees.
     simulator: infra::dex::Simulator,
 
+    /// Whether to simulate the swap gas for all orders, not just limit
+    /// orders.
+    simulate_gas: bool,
+
+    /// Whether to discard swaps whose calls revert in a simulation.
+    simulate_swaps: bool,
+
+    /// Whether to use the solver as a price oracle only, never building
+    /// solutions and dropping the calls of quoted swaps. Requests can also
+    /// ask for this individually.
+    dry_run: bool,
+
+    /// Unwraps the swaps of orders buying the native token.
+    weth: infra::dex::Weth,
+
     /// The slippage configuration to use for the solver.
     slippage: slippage::Limits,
 
     /// The number of concurrent requests to make.
     concurrent_requests: NonZeroUsize,
 
+    /// Caps the concurrent requests across all auctions that are solved at
+    /// the same time (e.g. the auctions of a batch), not just within one.
+    request_permits: Semaphore,
+
     /// Helps to manage the strategy to fill orders (especially partially
     /// fillable orders).
     fills: Fills,
@@ -40,13 +62,63 @@ pub struct Dex {
     /// Handles 429 Too Many Requests error with a retry mechanism
     rate_limiter: rate_limit::RateLimiter,
 
-    /// Amount of gas that gets added to each swap to tweak the cost coverage of
-    /// the solver.
-    gas_offset: eth::Gas,
+    /// Whether the DEX API recently answered swap requests.
+    liveness: Liveness,
+
+    /// Shares the swaps of identical requests to the DEX API that are made
+    /// concurrently.
+    single_flight: infra::dex::SingleFlight<Request, Result<dex::Swap, infra::dex::Error>>,
+
+    /// Optional cache of the swaps quoted in the current block.
+    quote_cache: Option<infra::dex::QuoteCache>,
+
+    /// Optional check of the on-chain allowances, to leave out approvals of
+    /// spenders that are already allowed to transfer enough tokens.
+    allowances: Option<infra::dex::Allowances>,
 
     /// Whether to internalize the solution interactions using the Settlement
     /// contract buffer.
     internalize_interactions: bool,
+
+    /// Optional check that order tokens have bytecode before requesting a
+    /// swap from the DEX API.
+    token_code: Option<infra::dex::TokenCode>,
+
+    /// Orders worth more than this are not sent to the DEX API.
+    max_order_value: Option<eth::Ether>,
+
+    /// Transformations applied to the slippage and the swap of every order,
+    /// and to the gas of its solution.
+    post_processors: post_process::Pipeline,
+}
+
+/// The parts of a swap request that are sent to the DEX API. Requests for
+/// different orders that agree on these are identical as far as the DEX API is
+/// concerned. The owner is part of it, since some DEX APIs quote for the
+/// owner as the origin of the swap (e.g. to check it may legally trade).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct Request {
+    sell: eth::TokenAddress,
+    buy: eth::TokenAddress,
+    side: order::Side,
+    amount: dex::Amount,
+    owner: eth::H160,
+    slippage: dex::Slippage,
+    gas_price: eth::Ether,
+}
+
+impl Request {
+    fn new(order: &dex::Order, slippage: &dex::Slippage, gas_price: auction::GasPrice) -> Self {
+        Self {
+            sell: order.sell,
+            buy: order.buy,
+            side: order.side,
+            amount: order.amount,
+            owner: order.owner,
+            slippage: slippage.clone(),
+            gas_price: gas_price.0,
+        }
+    }
 }
 
 /// The amount of time we aim the solver to finish before the final deadline is
@@ -55,32 +127,79 @@ const DEADLINE_SLACK: chrono::Duration = chrono::Duration::milliseconds(500);
 
 impl Dex {
     pub fn new(dex: infra::dex::Dex, config: infra::config::dex::Config) -> Self {
+        let backend = config.backend();
         let rate_limiter = rate_limit::RateLimiter::from_strategy(
             config.rate_limiting_strategy,
             "dex_api".to_string(),
         );
         Self {
-            dex,
+            dex: infra::dex::Backend::new(dex, backend),
             simulator: infra::dex::Simulator::new(
                 &config.node_url,
                 config.contracts.settlement,
                 config.contracts.authenticator,
+                config.block_stream.clone(),
             ),
+            simulate_gas: config.simulate_gas,
+            simulate_swaps: config.simulate_swaps,
+            dry_run: config.dry_run,
+            weth: infra::dex::Weth::new(config.contracts.weth),
             slippage: config.slippage,
             concurrent_requests: config.concurrent_requests,
+            request_permits: Semaphore::new(config.concurrent_requests.get()),
             fills: Fills::new(config.smallest_partial_fill),
             rate_limiter,
-            gas_offset: config.gas_offset,
+            liveness: Liveness::new(config.liveness_window),
+            single_flight: infra::dex::SingleFlight::new(),
+            // The configuration ensures that there is a block stream for the
+            // features that need one.
+            quote_cache: config
+                .quote_cache_size
+                .zip(config.block_stream.clone())
+                .map(|(size, block_stream)| infra::dex::QuoteCache::new(size, block_stream)),
+            allowances: config
+                .block_stream
+                .clone()
+                .filter(|_| config.check_allowances)
+                .map(|block_stream| {
+                    infra::dex::Allowances::new(
+                        config.multicall.clone(),
+                        config.contracts.settlement,
+                        block_stream,
+                    )
+                }),
             internalize_interactions: config.internalize_interactions,
+            token_code: config
+                .validate_token_code
+                .then(|| infra::dex::TokenCode::new(&config.node_url)),
+            max_order_value: config.max_order_value,
+            post_processors: post_process::Pipeline::new(config.post_processors),
         }
     }
 
-    pub async fn solve(&self, auction: auction::Auction) -> Vec<solution::Solution> {
-        let mut solutions = Vec::new();
+    /// Whether the DEX API answered swap requests (even if it found no swap)
+    /// within the liveness window, or no requests were attempted.
+    pub fn is_alive(&self) -> bool {
+        self.liveness.is_alive()
+    }
+
+    /// A short name of the DEX API used by the solver.
+    pub fn backend(&self) -> &'static str {
+        self.dex.name()
+    }
+
+    /// Solves the orders of the auction until its deadline, passing each
+    /// solution to `found` as soon as it is found. Returns `false` if the
+    /// deadline cut solving short, cancelling the outstanding swap requests.
+    pub async fn solve_each(
+        &self,
+        auction: auction::Auction,
+        mut found: impl FnMut(solution::Solution),
+    ) -> bool {
         let solve_orders = async {
             let mut stream = self.solution_stream(&auction);
             while let Some(solution) = stream.next().await {
-                solutions.push(solution);
+                found(solution);
             }
         };
 
@@ -90,13 +209,44 @@ impl Dex {
             .reduce(DEADLINE_SLACK)
             .remaining()
             .unwrap_or_default();
-        if tokio::time::timeout(deadline, solve_orders).await.is_err() {
+        let completed = tokio::time::timeout(deadline, solve_orders).await.is_ok();
+        if !completed {
             tracing::debug!("reached deadline; stopping to solve");
         }
 
         self.fills.collect_garbage();
+        completed
+    }
 
-        solutions
+    /// Quotes a single order with the DEX API, going through the same checks
+    /// and post-processing as the orders of an auction.
+    /// The calls of the swap are dropped in dry-run mode.
+    pub async fn quote(
+        &self,
+        order: &dex::Order,
+        gas_price: auction::GasPrice,
+        dry_run: bool,
+    ) -> Result<dex::Swap, infra::dex::Error> {
+        let tokens = auction::Tokens(Default::default());
+        self.rate_limiter
+            .execute_with_back_off(
+                self.swap(order, &tokens, gas_price, false),
+                |result| matches!(result, Err(err) if err.retryable()),
+            )
+            .await
+            .map_err(|err| match err {
+                rate_limit::Error::RateLimited => {
+                    infra::dex::Error::RateLimited { retry_after: None }
+                }
+            })
+            .and_then(|result| result)
+            .map(|swap| {
+                if self.dry_run || dry_run {
+                    swap.without_calls()
+                } else {
+                    swap
+                }
+            })
     }
 
     fn solution_stream<'a>(
@@ -105,11 +255,19 @@ impl Dex {
     ) -> impl stream::Stream<Item = solution::Solution> + 'a {
         stream::iter(auction.orders.iter())
             .enumerate()
-            .map(|(i, order)| {
+            .map(move |(i, order)| {
                 let span = tracing::info_span!("solve", order = %order.uid);
-                self.solve_order(order, &auction.tokens, auction.gas_price)
-                    .map(move |solution| solution.map(|s| s.with_id(solution::Id(i as u64))))
-                    .instrument(span)
+                async move {
+                    let _permit = self
+                        .request_permits
+                        .acquire()
+                        .await
+                        .expect("semaphore is never closed");
+                    self.solve_order(order, auction)
+                        .await
+                        .map(|s| s.with_id(solution::Id(i as u64)))
+                }
+                .instrument(span)
             })
             .buffer_unordered(self.concurrent_requests.get())
             .filter_map(future::ready)
@@ -120,11 +278,15 @@ impl Dex {
         order: &Order,
         dex_order: &dex::Order,
         tokens: &auction::Tokens,
+        gas_price: auction::GasPrice,
+        fresh: bool,
     ) -> Option<dex::Swap> {
         let dex_err_handler = |err: infra::dex::Error| {
             infra::metrics::solve_error(err.format_variant());
             match &err {
-                err @ infra::dex::Error::NotFound => {
+                err @ (infra::dex::Error::NotFound
+                | infra::dex::Error::InsufficientLiquidity
+                | infra::dex::Error::PriceDeviationTooHigh) => {
                     if order.partially_fillable {
                         // Only adjust the amount to try next if we are sure the API
                         // worked
@@ -138,37 +300,64 @@ impl Dex {
                 err @ infra::dex::Error::OrderNotSupported => {
                     tracing::debug!(?err, "skipping order")
                 }
-                err @ infra::dex::Error::RateLimited => {
+                err @ infra::dex::Error::InvalidOrder(_) => {
+                    tracing::debug!(?err, "skipping order")
+                }
+                err @ infra::dex::Error::RateLimited { .. } => {
                     tracing::debug!(?err, "encountered rate limit")
                 }
+                err @ infra::dex::Error::Transient(_) => {
+                    tracing::debug!(?err, "encountered transient error")
+                }
                 err @ infra::dex::Error::UnavailableForLegalReasons => {
                     tracing::debug!(?err, "unavailable for legal reasons")
                 }
+                err @ infra::dex::Error::InvalidToken(_) => {
+                    tracing::debug!(?err, "skipping order")
+                }
+                err @ infra::dex::Error::CalldataTooLarge(_) => {
+                    tracing::debug!(?err, "skipping order")
+                }
+                err @ infra::dex::Error::SlippageTooHigh => {
+                    tracing::warn!(?err, "skipping order")
+                }
+                err @ infra::dex::Error::OrderTooLarge => {
+                    if order.partially_fillable {
+                        // A smaller fill might still be within the limit.
+                        self.fills.reduce_next_try(order.uid);
+                    }
+                    tracing::debug!(?err, "skipping order")
+                }
+                err @ infra::dex::Error::Timeout => {
+                    tracing::debug!(?err, "skipping order")
+                }
+                err @ infra::dex::Error::Unavailable => {
+                    tracing::debug!(?err, "skipping order")
+                }
                 infra::dex::Error::Other(err) => {
                     tracing::warn!(?err, "failed to get swap")
                 }
             }
             err
         };
-        let swap = async {
-            let slippage = self.slippage.relative(&dex_order.amount(), tokens);
-            self.dex
-                .swap(dex_order, &slippage, tokens)
-                .await
-                .inspect(|_| infra::metrics::request_sent())
-                .map_err(dex_err_handler)
-        };
+        let swap = self
+            .swap(dex_order, tokens, gas_price, fresh)
+            .map(|result| result.map_err(dex_err_handler));
         self.rate_limiter
-            .execute_with_back_off(swap, |result| {
-                matches!(result, Err(infra::dex::Error::RateLimited))
-            })
+            .execute_with_back_off(swap, |result| matches!(result, Err(err) if err.retryable()))
             .await
             .map_err(|err| match err {
-                rate_limit::Error::RateLimited => infra::dex::Error::RateLimited,
+                rate_limit::Error::RateLimited => {
+                    infra::dex::Error::RateLimited { retry_after: None }
+                }
             })
             .and_then(|result| result)
             .ok()
             .filter(|swap| {
+                if swap.metadata.filled_amount.is_some() && !order.partially_fillable {
+                    tracing::debug!("partial swap for a fill-or-kill order");
+                    return false;
+                }
                 let valid = swap.satisfies(order);
                 if !valid {
                     tracing::debug!("swap does not satisfy order");
@@ -180,22 +369,116 @@ impl Dex {
             })
     }
 
-    async fn solve_order(
+    /// Requests a swap for the DEX order, after checking that the order is
+    /// one the DEX API should be asked about. A `fresh` swap is always
+    /// requested from the DEX API, instead of being taken from the quote cache
+    /// or shared with an identical request in flight.
+    async fn swap(
         &self,
-        order: &order::Order,
+        order: &dex::Order,
         tokens: &auction::Tokens,
         gas_price: auction::GasPrice,
+        fresh: bool,
+    ) -> Result<dex::Swap, infra::dex::Error> {
+        // Partially fillable orders are quoted for the chosen fraction only,
+        // so that the limits and allowance of the swap are computed against
+        // the scaled amount.
+        let dex_order = &order.scaled();
+        if let Some(max_order_value) = self.max_order_value {
+            if dex_order
+                .value(tokens)
+                .is_some_and(|value| value > max_order_value)
+            {
+                return Err(infra::dex::Error::OrderTooLarge);
+            }
+        }
+        if let Some(token_code) = &self.token_code {
+            token_code.validate(dex_order).await?;
+        }
+        // Native token sells are placed as WETH orders, so the settlement
+        // contract never holds the native token to sell.
+        if dex_order.sell.is_native() {
+            return Err(infra::dex::Error::OrderNotSupported);
+        }
+        let slippage = self.post_processors.slippage(self.slippage.relative(
+            &dex_order.amount(),
+            dex_order.slippage.as_ref(),
+            tokens,
+        ))?;
+        let wrapped = dex_order.wrapped(self.weth.address());
+        let request = async {
+            let block = self.quote_cache.as_ref().map(|cache| cache.block());
+            if let (Some(cache), Some(block), false) = (&self.quote_cache, block, fresh) {
+                if let Some(swap) = cache.get(block, &wrapped, gas_price).await {
+                    return Ok(swap);
+                }
+            }
+            let swap = self
+                .dex
+                .swap(&wrapped, &slippage, tokens, gas_price)
+                .await
+                .inspect(|_| infra::metrics::request_sent());
+            self.liveness.record(&swap);
+            if let (Some(cache), Some(block), Ok(swap)) = (&self.quote_cache, block, &swap) {
+                cache.insert(block, &wrapped, gas_price, swap).await;
+            }
+            swap
+        };
+        let swap = if fresh {
+            request.await
+        } else {
+            self.single_flight
+                .run(Request::new(&wrapped, &slippage, gas_price), request)
+                .await
+        }?;
+        let mut swap = if dex_order.buy.is_native() {
+            self.weth.unwrap(swap)
+        } else {
+            swap
+        };
+        if order.partially_fillable {
+            swap.metadata.fill_ratio = swap.fill_ratio(order);
+        }
+        self.post_processors
+            .run(&mut swap, &post_process::Context { tokens, gas_price })?;
+        if self.simulate_swaps {
+            match self.simulator.succeeds(dex_order.owner, &swap).await {
+                Ok(true) => {}
+                Ok(false) => return Err(infra::dex::Error::NotFound),
+                Err(err) => tracing::debug!(?err, "could not simulate swap"),
+            }
+        }
+        Ok(swap)
+    }
+
+    async fn solve_order(
+        &self,
+        order: &order::Order,
+        auction: &auction::Auction,
     ) -> Option<solution::Solution> {
+        let (tokens, gas_price) = (&auction.tokens, auction.gas_price);
         let dex_order = self.fills.dex_order(order, tokens)?;
-        let swap = self.try_solve(order, &dex_order, tokens).await?;
+        let swap = self
+            .try_solve(order, &dex_order, tokens, gas_price, auction.force_fresh)
+            .await?;
         let sell = tokens.reference_price(&order.sell.token);
+        let metadata = swap.metadata.clone();
+        let plan = swap.execution_plan();
+        let (sell_token, allowance) = (swap.input.token, swap.allowance.clone());
+        if self.dry_run || auction.dry_run {
+            // Swaps are only quoted for their price, so that nothing quoted in
+            // dry-run mode can ever be executed.
+            tracing::debug!(?metadata, ?plan, ?swap.input, ?swap.output, "quoted in dry-run mode");
+            return None;
+        }
         let Some(solution) = swap
             .into_solution(
                 order.clone(),
                 gas_price,
                 sell,
                 &self.simulator,
-                self.gas_offset,
+                self.simulate_gas,
+                self.post_processors.gas_offset(),
             )
             .await
         else {
@@ -203,7 +486,20 @@ impl Dex {
             return None;
         };
 
-        tracing::debug!("solved");
+        // Allowances are only reserved for swaps that made it into a solution.
+        let approve = match &self.allowances {
+            Some(allowances) => !allowances.reserve(sell_token, &allowance).await,
+            None => true,
+        };
+        let solution = if approve {
+            solution
+        } else {
+            tracing::debug!("allowance is already sufficient");
+            solution.without_allowances()
+        };
+
+        tracing::debug!(?metadata, ?plan, "solved");
+        let solution = solution.with_swap(solution::SwapInfo { metadata, plan });
         // Maybe some liquidity appeared that enables a bigger fill.
         self.fills.increase_next_try(order.uid);
 
@@ -214,3 +510,40 @@ impl Dex {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::domain::dex::testing,
+        std::sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[tokio::test]
+    async fn shares_swaps_of_the_same_owner_only() {
+        let flight = infra::dex::SingleFlight::<Request, ()>::new();
+        let sent = AtomicUsize::new(0);
+        let request = || async {
+            sent.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+        };
+        let key = |owner: u8| {
+            let order = dex::Order {
+                owner: eth::H160::repeat_byte(owner),
+                ..testing::order(1_000)
+            };
+            Request::new(
+                &order,
+                &dex::Slippage::one_percent(),
+                auction::GasPrice(eth::Ether(1.into())),
+            )
+        };
+
+        tokio::join!(
+            flight.run(key(1), request()),
+            flight.run(key(1), request()),
+            flight.run(key(2), request()),
+        );
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,306 @@
+//! Transformations that are applied to DEX swaps around quoting them: to the
+//! slippage tolerance before a swap is requested, to the swap after it was
+//! quoted, and to the gas of the solution that is built from it.
+
+use {
+    crate::{
+        domain::{
+            auction,
+            dex::{self, slippage},
+            eth,
+        },
+        infra,
+        util::conv,
+    },
+    bigdecimal::BigDecimal,
+};
+
+/// A single transformation of a swap. All hooks default to leaving the swap
+/// unchanged.
+pub trait QuotePostProcessor: Send + Sync {
+    /// Adjusts the slippage tolerance that a swap is requested with. DEX APIs
+    /// bake it into the swap calls, so it can't be changed after quoting.
+    fn slippage(&self, slippage: dex::Slippage) -> Result<dex::Slippage, infra::dex::Error> {
+        Ok(slippage)
+    }
+
+    /// Adjusts a quoted swap, or rejects it.
+    fn process(&self, _swap: &mut dex::Swap, _context: &Context) -> Result<(), infra::dex::Error> {
+        Ok(())
+    }
+
+    /// Gas that is added to the solution of the swap on top of the swap gas.
+    /// It is not added to the swap itself, since the swap gas of limit orders
+    /// is replaced by a simulation.
+    fn gas_offset(&self) -> eth::Gas {
+        eth::Gas::default()
+    }
+}
+
+/// Auction data available to post-processors.
+pub struct Context<'a> {
+    pub tokens: &'a auction::Tokens,
+    pub gas_price: auction::GasPrice,
+}
+
+/// The available post-processors.
+#[derive(Clone, Debug)]
+pub enum Kind {
+    /// Caps the slippage tolerance of swaps.
+    SlippageCap(slippage::Cap),
+    /// Rejects swaps whose price deviates from the reference prices by more
+    /// than the specified fraction, which points to a bug in the DEX API.
+    PriceCheck(BigDecimal),
+    /// Adds gas to each solution to tweak the cost coverage of the solver.
+    GasOffset(eth::Gas),
+    /// Computes the swap gas cost at the auction gas price if the DEX did not
+    /// report it.
+    GasCost,
+    /// Computes the fee the swap implicitly charges against reference prices.
+    ImpliedFee,
+    /// Computes the decimal-normalized swap prices.
+    Prices,
+}
+
+impl Kind {
+    fn processor(self) -> Box<dyn QuotePostProcessor> {
+        match self {
+            Self::SlippageCap(cap) => Box::new(SlippageCap(cap)),
+            Self::PriceCheck(max_deviation) => Box::new(PriceCheck(max_deviation)),
+            Self::GasOffset(gas) => Box::new(GasOffset(gas)),
+            Self::GasCost => Box::new(GasCost),
+            Self::ImpliedFee => Box::new(ImpliedFee),
+            Self::Prices => Box::new(Prices),
+        }
+    }
+}
+
+/// An ordered list of post-processors that get run on every swap.
+pub struct Pipeline(Vec<Box<dyn QuotePostProcessor>>);
+
+impl Pipeline {
+    pub fn new(kinds: Vec<Kind>) -> Self {
+        Self(kinds.into_iter().map(Kind::processor).collect())
+    }
+
+    /// Passes the slippage tolerance through all post-processors.
+    pub fn slippage(&self, slippage: dex::Slippage) -> Result<dex::Slippage, infra::dex::Error> {
+        self.0
+            .iter()
+            .try_fold(slippage, |slippage, processor| processor.slippage(slippage))
+    }
+
+    /// Passes the swap through all post-processors, stopping at the first one
+    /// that rejects it.
+    pub fn run(&self, swap: &mut dex::Swap, context: &Context) -> Result<(), infra::dex::Error> {
+        self.0
+            .iter()
+            .try_for_each(|processor| processor.process(swap, context))
+    }
+
+    /// The total gas offset of all post-processors.
+    pub fn gas_offset(&self) -> eth::Gas {
+        self.0.iter().fold(eth::Gas::default(), |total, processor| {
+            total + processor.gas_offset()
+        })
+    }
+}
+
+struct SlippageCap(slippage::Cap);
+
+impl QuotePostProcessor for SlippageCap {
+    fn slippage(&self, slippage: dex::Slippage) -> Result<dex::Slippage, infra::dex::Error> {
+        self.0
+            .apply(slippage)
+            .map_err(|_| infra::dex::Error::SlippageTooHigh)
+    }
+}
+
+struct PriceCheck(BigDecimal);
+
+impl QuotePostProcessor for PriceCheck {
+    fn process(&self, swap: &mut dex::Swap, context: &Context) -> Result<(), infra::dex::Error> {
+        let Some(deviation) = swap.price_deviation(context.tokens) else {
+            return Ok(());
+        };
+        if deviation <= self.0 {
+            return Ok(());
+        }
+        let price = |sell: &eth::U256, buy: &eth::U256| {
+            (!buy.is_zero()).then(|| conv::u256_to_bigdecimal(sell) / conv::u256_to_bigdecimal(buy))
+        };
+        let reference_price = context
+            .tokens
+            .reference_price(&swap.input.token)
+            .zip(context.tokens.reference_price(&swap.output.token))
+            .and_then(|(input, output)| price(&input.0 .0, &output.0 .0));
+        tracing::warn!(
+            price = ?price(&swap.output.amount, &swap.input.amount),
+            ?reference_price,
+            %deviation,
+            "discarding swap with implausible price"
+        );
+        Err(infra::dex::Error::PriceDeviationTooHigh)
+    }
+}
+
+struct GasOffset(eth::Gas);
+
+impl QuotePostProcessor for GasOffset {
+    fn gas_offset(&self) -> eth::Gas {
+        self.0
+    }
+}
+
+struct GasCost;
+
+impl QuotePostProcessor for GasCost {
+    fn process(&self, swap: &mut dex::Swap, context: &Context) -> Result<(), infra::dex::Error> {
+        if swap.metadata.gas_cost_wei.is_none() {
+            swap.metadata.gas_cost_wei = swap.gas.0.checked_mul(context.gas_price.0 .0);
+        }
+        Ok(())
+    }
+}
+
+struct ImpliedFee;
+
+impl QuotePostProcessor for ImpliedFee {
+    fn process(&self, swap: &mut dex::Swap, context: &Context) -> Result<(), infra::dex::Error> {
+        swap.metadata.implied_fee_bps = swap.implied_fee_bps(context.tokens);
+        Ok(())
+    }
+}
+
+struct Prices;
+
+impl QuotePostProcessor for Prices {
+    fn process(&self, swap: &mut dex::Swap, context: &Context) -> Result<(), infra::dex::Error> {
+        if let Some((price, inverse_price)) = swap.prices(context.tokens) {
+            swap.metadata.price = Some(price);
+            swap.metadata.inverse_price = Some(inverse_price);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::domain::dex::testing::{asset, token, token_info},
+        ethereum_types::U256,
+        std::collections::HashMap,
+    };
+
+    fn swap(gas_cost_wei: Option<U256>) -> dex::Swap {
+        dex::Swap {
+            gas: eth::Gas(U256::from(100_000)),
+            metadata: dex::Metadata {
+                gas_cost_wei,
+                ..Default::default()
+            },
+            ..dex::testing::swap(asset(token(1), 100), asset(token(2), 200))
+        }
+    }
+
+    fn all() -> Vec<Kind> {
+        vec![
+            Kind::SlippageCap(
+                slippage::Cap::new("0.05".parse().unwrap(), slippage::CapPolicy::Reject).unwrap(),
+            ),
+            Kind::PriceCheck("0.5".parse().unwrap()),
+            Kind::GasOffset(eth::Gas(U256::from(1_000))),
+            Kind::GasCost,
+            Kind::ImpliedFee,
+            Kind::Prices,
+        ]
+    }
+
+    #[test]
+    fn runs_configured_processors() {
+        let tokens = auction::Tokens(HashMap::new());
+        let context = Context {
+            tokens: &tokens,
+            gas_price: auction::GasPrice(eth::Ether(U256::from(10))),
+        };
+
+        let mut quoted = swap(None);
+        Pipeline::new(all()).run(&mut quoted, &context).unwrap();
+        assert_eq!(quoted.metadata.gas_cost_wei, Some(U256::from(1_000_000)));
+
+        // The gas cost reported by the DEX takes precedence.
+        let mut reported = swap(Some(U256::from(42)));
+        Pipeline::new(all()).run(&mut reported, &context).unwrap();
+        assert_eq!(reported.metadata.gas_cost_wei, Some(U256::from(42)));
+
+        let mut disabled = swap(None);
+        Pipeline::new(vec![Kind::ImpliedFee, Kind::Prices])
+            .run(&mut disabled, &context)
+            .unwrap();
+        assert_eq!(disabled.metadata.gas_cost_wei, None);
+    }
+
+    #[test]
+    fn caps_slippage() {
+        let slippage = dex::Slippage::from_bps(1_000);
+        assert!(matches!(
+            Pipeline::new(all()).slippage(slippage.clone()),
+            Err(infra::dex::Error::SlippageTooHigh)
+        ));
+        assert_eq!(
+            Pipeline::new(vec![Kind::GasCost])
+                .slippage(slippage.clone())
+                .unwrap(),
+            slippage
+        );
+    }
+
+    #[test]
+    fn rejects_implausible_prices() {
+        // Both tokens are worth the same, but the swap doubles the amount.
+        let tokens = auction::Tokens(
+            [
+                (token(1), token_info(Some(18), Some("1"))),
+                (token(2), token_info(Some(18), Some("1"))),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let context = Context {
+            tokens: &tokens,
+            gas_price: auction::GasPrice(eth::Ether(U256::from(10))),
+        };
+
+        assert!(matches!(
+            Pipeline::new(all()).run(&mut swap(None), &context),
+            Err(infra::dex::Error::PriceDeviationTooHigh)
+        ));
+        assert!(
+            Pipeline::new(vec![Kind::PriceCheck("1.5".parse().unwrap())])
+                .run(&mut swap(None), &context)
+                .is_ok()
+        );
+        assert!(Pipeline::new(vec![Kind::GasCost])
+            .run(&mut swap(None), &context)
+            .is_ok());
+    }
+
+    #[test]
+    fn sums_gas_offsets() {
+        assert_eq!(Pipeline::new(all()).gas_offset().0, U256::from(1_000));
+        assert_eq!(
+            Pipeline::new(vec![
+                Kind::GasOffset(eth::Gas(U256::from(1_000))),
+                Kind::GasOffset(eth::Gas(U256::from(2_000))),
+            ])
+            .gas_offset()
+            .0,
+            U256::from(3_000)
+        );
+        assert_eq!(
+            Pipeline::new(vec![Kind::GasCost]).gas_offset().0,
+            U256::zero()
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    domain::{auction, solution},
-    infra::metrics,
+    domain::{self, auction, solution},
+    infra::{self, metrics},
 };
 
 pub mod dex;
@@ -16,12 +16,57 @@ impl Solver {
     /// returning multiple solutions to later merge multiple non-overlapping
     /// solutions to get one big more gas efficient solution.
     pub async fn solve(&self, auction: auction::Auction) -> Vec<solution::Solution> {
+        let mut solutions = Vec::new();
+        self.solve_each(auction, |solution| solutions.push(solution))
+            .await;
+        solutions
+    }
+
+    /// Solves a given auction like [`Self::solve`], but passes each solution
+    /// to `found` as soon as it is found. Returns `false` if solving was cut
+    /// short by the auction deadline.
+    pub async fn solve_each(
+        &self,
+        auction: auction::Auction,
+        mut found: impl FnMut(solution::Solution),
+    ) -> bool {
         metrics::solve(&auction);
         let deadline = auction.deadline.clone();
-        let solutions = match self {
-            Solver::Dex(solver) => solver.solve(auction).await,
+        let mut solutions = 0;
+        let found = |solution| {
+            solutions += 1;
+            found(solution);
         };
-        metrics::solved(&deadline, &solutions);
-        solutions
+        let completed = match self {
+            Solver::Dex(solver) => solver.solve_each(auction, found).await,
+        };
+        metrics::solved(&deadline, solutions);
+        completed
+    }
+
+    /// Whether the solver is still able to get quotes from its backend.
+    pub fn is_alive(&self) -> bool {
+        match self {
+            Solver::Dex(solver) => solver.is_alive(),
+        }
+    }
+
+    /// A short name of the backend the solver uses.
+    pub fn backend(&self) -> &'static str {
+        match self {
+            Solver::Dex(solver) => solver.backend(),
+        }
+    }
+
+    /// Quotes a single order for price discovery, without an auction.
+    pub async fn quote(
+        &self,
+        order: &domain::dex::Order,
+        gas_price: auction::GasPrice,
+        dry_run: bool,
+    ) -> Result<domain::dex::Swap, infra::dex::Error> {
+        match self {
+            Solver::Dex(solver) => solver.quote(order, gas_price, dry_run).await,
+        }
     }
 }
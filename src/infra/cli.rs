@@ -2,7 +2,7 @@
 
 use {
     clap::{Parser, Subcommand},
-    std::{net::SocketAddr, path::PathBuf},
+    std::{net::SocketAddr, path::PathBuf, time::Duration},
 };
 
 /// Run a solver engine
@@ -21,6 +21,20 @@ pub struct Args {
     #[arg(long, env, default_value = "127.0.0.1:7872")]
     pub addr: SocketAddr,
 
+    /// The maximum amount of time a single `/solve` request may take across
+    /// all DEX requests and retries. This is an outer bound on top of the
+    /// auction deadline, so it should comfortably exceed the time limits the
+    /// driver usually grants.
+    #[arg(long, env, default_value = "30s", value_parser = humantime::parse_duration)]
+    pub solve_timeout: Duration,
+
+    /// Whether to include the headers and bodies of DEX API requests and
+    /// responses in their `TRACE` logs. Bodies can be large and contain
+    /// secrets, so only the method, URL and status are logged by default.
+    /// Credential headers are always redacted.
+    #[arg(long, env)]
+    pub log_http_bodies: bool,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -55,4 +69,20 @@ pub enum Command {
         #[clap(long, env)]
         config: PathBuf,
     },
+    /// solve individual orders from recorded swap fixtures
+    Replay {
+        #[clap(long, env)]
+        config: PathBuf,
+    },
+    /// solve individual orders using the best swap across multiple DEX APIs
+    Multi {
+        #[clap(long, env)]
+        config: PathBuf,
+    },
+    /// solve individual orders using the first of several DEX APIs, in order
+    /// of priority, that finds a swap
+    Fallback {
+        #[clap(long, env)]
+        config: PathBuf,
+    },
 }
@@ -2,13 +2,13 @@ use {
     crate::{
         domain::eth,
         infra::{self, config::dex::file, dex},
-        util::serialize,
+        util::{self, serialize},
     },
     contracts::BalancerV2Vault,
     ethereum_types::H160,
     serde::Deserialize,
     serde_with::serde_as,
-    std::path::Path,
+    std::{path::Path, time::Duration},
 };
 
 #[serde_as]
@@ -39,6 +39,97 @@ struct Config {
     /// Whether to run `queryBatchSwap` to update the return amount with most
     /// up-to-date on-chain values.
     query_batch_swap: Option<bool>,
+
+    /// How long the swap calls remain valid on-chain after the quote was
+    /// computed. If not specified, the swaps never expire.
+    #[serde(with = "humantime_serde", default)]
+    swap_deadline: Option<Duration>,
+
+    /// Pool IDs (V2) or addresses (V3) of deprecated or risky pools. Routes
+    /// through these pools produce a warning.
+    #[serde(default)]
+    deprecated_pools: Vec<dex::balancer::PoolId>,
+
+    /// Whether to reject routes through deprecated pools instead of just
+    /// warning about them.
+    #[serde(default)]
+    reject_deprecated_pools: bool,
+
+    /// The approximate gas that an individual swap uses, by pool kind.
+    #[serde(default)]
+    gas_per_swap: GasPerSwap,
+
+    /// How failed requests to the SOR API are retried. Requests are only sent
+    /// once if not specified.
+    retry: Option<util::http::RetryPolicy>,
+
+    /// Only route through pools of this Balancer version (2 or 3). If not
+    /// specified, the SOR picks the best route across versions.
+    protocol_version: Option<dex::balancer::ProtocolVersion>,
+
+    /// Whether V2 swaps pay the input token from the settlement contract's
+    /// Vault internal balance. Requires `protocol-version` to be 2.
+    #[serde(default)]
+    use_internal_balance_in: bool,
+
+    /// Whether V2 swaps credit the output token to the settlement contract's
+    /// Vault internal balance. Requires `protocol-version` to be 2.
+    #[serde(default)]
+    use_internal_balance_out: bool,
+
+    /// Optional address that receives the output of V2 swaps. If not
+    /// specified, the output goes to the settlement contract. Requires
+    /// `protocol-version` to be 2.
+    recipient: Option<H160>,
+
+    /// Fetch the decimals of tokens that are missing from the auction from
+    /// the node, caching them. If not specified, orders for such tokens are
+    /// skipped.
+    decimals_cache: Option<DecimalsCache>,
+}
+
+impl Config {
+    /// Whether settings that only apply to V2 swaps are used while the SOR may
+    /// also route through V3 pools, for which they would silently be ignored.
+    fn ignores_v2_settings(&self) -> bool {
+        let v2_settings = self.use_internal_balance_in
+            || self.use_internal_balance_out
+            || self.recipient.is_some();
+        v2_settings && self.protocol_version != Some(dex::balancer::ProtocolVersion::V2)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct DecimalsCache {
+    /// How long fetched decimals are cached for.
+    #[serde(with = "humantime_serde", default = "default_decimals_cache_ttl")]
+    ttl: Duration,
+
+    /// The maximum number of tokens to cache decimals for.
+    #[serde(default = "default_decimals_cache_size")]
+    max_size: u64,
+}
+
+fn default_decimals_cache_ttl() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+fn default_decimals_cache_size() -> u64 {
+    10_000
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct GasPerSwap {
+    /// V2 pools with the general specialization.
+    general: Option<u64>,
+    /// V2 pools with the minimal swap info specialization.
+    minimal_swap_info: Option<u64>,
+    /// V2 pools with the two token specialization.
+    two_token: Option<u64>,
+    /// V3 pools.
+    v3: Option<u64>,
 }
 
 /// Load the driver configuration from a TOML file.
@@ -48,6 +139,27 @@ struct Config {
 /// This method panics if the config is invalid or on I/O errors.
 pub async fn load(path: &Path) -> super::Config {
     let (base, config) = file::load::<Config>(path).await;
+    build(config, base)
+}
+
+/// Load the Balancer configuration of a meta-solver backend from a TOML file.
+/// Only the `[dex]` table of the file is used, all other settings are taken
+/// from the base configuration of the meta-solver.
+///
+/// # Panics
+///
+/// This method panics if the config is invalid or on I/O errors.
+pub async fn load_backend(path: &Path, base: &infra::config::dex::Config) -> super::Config {
+    build(file::load_dex::<Config>(path).await, base.clone())
+}
+
+fn build(config: Config, base: infra::config::dex::Config) -> super::Config {
+    assert!(
+        !config.ignores_v2_settings(),
+        "use-internal-balance-in, use-internal-balance-out and recipient only apply to V2 swaps \
+         and require protocol-version = 2"
+    );
+    let base = base.with_chain(config.chain_id);
     let contracts = infra::contracts::Contracts::for_chain(config.chain_id);
     let vault_contract = infra::contracts::contract_address_for_chain(
         config.chain_id,
@@ -75,9 +187,74 @@ pub async fn load(path: &Path) -> super::Config {
                 .unwrap_or(contracts.permit2),
             settlement: base.contracts.settlement,
             block_stream: base.block_stream.clone(),
+            follow_redirects: base.follow_redirects,
+            rate_limit: base.rate_limit,
+            connect_timeout: base.connect_timeout,
+            request_timeout: base.request_timeout,
+            shared_client: None,
+            retry: config.retry,
             chain_id: config.chain_id,
             query_batch_swap: config.query_batch_swap.unwrap_or(false),
+            swap_deadline: config.swap_deadline,
+            deprecated_pools: config.deprecated_pools,
+            reject_deprecated_pools: config.reject_deprecated_pools,
+            gas_per_swap: dex::balancer::GasPerSwap {
+                general: config
+                    .gas_per_swap
+                    .general
+                    .unwrap_or(dex::balancer::GasPerSwap::DEFAULT),
+                minimal_swap_info: config
+                    .gas_per_swap
+                    .minimal_swap_info
+                    .unwrap_or(dex::balancer::GasPerSwap::DEFAULT),
+                two_token: config
+                    .gas_per_swap
+                    .two_token
+                    .unwrap_or(dex::balancer::GasPerSwap::DEFAULT),
+                v3: config
+                    .gas_per_swap
+                    .v3
+                    .unwrap_or(dex::balancer::GasPerSwap::DEFAULT),
+            },
+            protocol_version: config.protocol_version,
+            use_internal_balance_in: config.use_internal_balance_in,
+            use_internal_balance_out: config.use_internal_balance_out,
+            recipient: config.recipient,
+            decimals: config.decimals_cache.map(|cache| dex::decimals::Config {
+                multicall: base.multicall.clone(),
+                ttl: cache.ttl,
+                max_size: cache.max_size,
+            }),
         },
         base,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v2_settings_require_v2_routes() {
+        let config = |settings: &str| {
+            toml::from_str::<Config>(&format!(
+                "endpoint = 'https://apiv3.balancer.fi/'\nchain-id = '1'\n{settings}"
+            ))
+            .unwrap()
+        };
+
+        assert!(!config("").ignores_v2_settings());
+        assert!(!config(
+            "recipient = '0x0000000000000000000000000000000000000001'\nprotocol-version = 2"
+        )
+        .ignores_v2_settings());
+        assert!(
+            config("recipient = '0x0000000000000000000000000000000000000001'")
+                .ignores_v2_settings()
+        );
+        assert!(
+            config("use-internal-balance-in = true\nprotocol-version = 3").ignores_v2_settings()
+        );
+        assert!(!config("protocol-version = 3").ignores_v2_settings());
+    }
+}
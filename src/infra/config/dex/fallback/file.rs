@@ -0,0 +1,46 @@
+use {
+    crate::infra::config::dex::{
+        file,
+        multi::file::{load_backends, BackendPath},
+    },
+    serde::Deserialize,
+    std::{path::Path, time::Duration},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    /// The DEX APIs to try in order of priority, each by the path of its
+    /// solver configuration file. Only the DEX specific `[dex]` section of
+    /// these files is used, all other solver settings are taken from the
+    /// fallback solver configuration. All backends must be configured for the
+    /// same chain.
+    backends: Vec<BackendPath>,
+
+    /// How long to wait for a single backend to quote an order before falling
+    /// back to the next one.
+    #[serde(with = "humantime_serde", default = "default_request_timeout")]
+    request_timeout: Duration,
+}
+
+fn default_request_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// Load the fallback solver configuration from a TOML file.
+///
+/// # Panics
+///
+/// This method panics if the config is invalid, no backend is configured or
+/// on I/O errors.
+pub async fn load(path: &Path) -> super::Config {
+    let (base, config) = file::load::<Config>(path).await;
+
+    let (backends, base) = load_backends(config.backends, base).await;
+
+    super::Config {
+        backends,
+        request_timeout: config.request_timeout,
+        base,
+    }
+}
@@ -0,0 +1,10 @@
+use {super::multi::Backend, std::time::Duration};
+
+pub mod file;
+
+pub struct Config {
+    /// The DEX APIs to try, in order of priority.
+    pub backends: Vec<Backend>,
+    pub request_timeout: Duration,
+    pub base: super::Config,
+}
@@ -2,8 +2,8 @@
 
 use {
     crate::{
-        domain::{dex::slippage, eth},
-        infra::{blockchain, config::unwrap_or_log, contracts},
+        domain::{dex::slippage, eth, solver::dex::post_process},
+        infra::{blockchain, config::unwrap_or_log, contracts, dex, multicall},
         util::serialize,
     },
     bigdecimal::BigDecimal,
@@ -17,14 +17,26 @@ use {
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 struct Config {
+    /// Optional name of the solver, reported by `/healthz` to identify the
+    /// deployment. Defaults to the name of the DEX backend.
+    name: Option<String>,
+
     /// The node URL to use for simulations.
     #[serde_as(as = "serde_with::DisplayFromStr")]
     node_url: reqwest::Url,
 
-    /// Optional CoW Protocol Settlement contract address. If not specified,
-    /// the default Settlement contract address will be used.
+    /// Optional address of the settlement contract that executes the swaps.
+    /// If not specified, the default CoW Protocol Settlement contract address
+    /// will be used. Custom integrations can specify their own settlement (or
+    /// executor) contract, in which case they usually need to specify the
+    /// `authenticator` as well.
     settlement: Option<eth::H160>,
 
+    /// Optional solver authenticator contract address, used for simulating
+    /// swaps. If not specified, it is read from the configured settlement
+    /// contract (or the default one is used).
+    authenticator: Option<eth::H160>,
+
     /// The relative slippage allowed by the solver.
     #[serde(default = "default_relative_slippage")]
     #[serde_as(as = "serde_with::DisplayFromStr")]
@@ -34,6 +46,20 @@ struct Config {
     #[serde_as(as = "Option<serialize::U256>")]
     absolute_slippage: Option<eth::U256>,
 
+    /// The maximum slippage in basis points of any swap, including the
+    /// slippage that `/solve` requests ask for on single orders (with their
+    /// `slippageBps` field). This guards against misconfigured absolute
+    /// slippage as well as absurd requests. It is enforced by the
+    /// `slippage-cap` post-processor, and while that is enabled the
+    /// `relative-slippage` must not exceed it.
+    #[serde(default = "default_max_slippage_bps")]
+    max_slippage_bps: u16,
+
+    /// Whether slippage exceeding `max-slippage-bps` is clamped to it (with
+    /// a warning) or the order is not swapped at all.
+    #[serde(default)]
+    slippage_cap_policy: SlippageCapPolicy,
+
     /// The number of concurrent requests to make to the DEX aggregator API.
     #[serde(default = "default_concurrent_requests")]
     concurrent_requests: NonZeroUsize,
@@ -60,7 +86,7 @@ struct Config {
     dex: toml::Value,
 
     /// Amount of gas that gets added to each swap to adjust the cost coverage
-    /// of the solver.
+    /// of the solver, by the `gas-offset` post-processor.
     #[serde(default = "default_gas_offset")]
     #[serde_as(as = "serialize::U256")]
     gas_offset: eth::U256,
@@ -76,12 +102,233 @@ struct Config {
     /// contract buffers.
     #[serde(default = "default_internalize_interactions")]
     internalize_interactions: bool,
+
+    /// Whether to check that the sell and buy tokens of an order have
+    /// bytecode before requesting a swap. This costs an additional RPC call
+    /// per token (results are cached).
+    #[serde(default)]
+    validate_token_code: bool,
+
+    /// Whether to simulate the gas used by the swaps of all orders instead of
+    /// only limit orders, rather than trusting the DEX API estimate. This
+    /// costs an additional RPC call per swap. Swaps whose simulation fails
+    /// fall back to the DEX API estimate.
+    #[serde(default)]
+    simulate_gas: bool,
+
+    /// Whether to simulate the swaps returned by the DEX API before using
+    /// them, and to discard swaps that revert (e.g. because of a stale route
+    /// or a paused pool). This costs an additional RPC call per swap.
+    #[serde(default)]
+    simulate_swaps: bool,
+
+    /// Whether to run the solver as a price oracle only. Swaps are still
+    /// quoted and checked as usual, but `/solve` never returns solutions for
+    /// them and `/quote` drops their calls, so no calldata that could be
+    /// executed is ever returned. Single requests can ask for the same with
+    /// their `dryRun` field.
+    #[serde(default)]
+    dry_run: bool,
+
+    /// Whether to follow HTTP redirects from the DEX API. Redirects are not
+    /// followed by default, so that API credentials are never sent to an
+    /// unexpected host.
+    #[serde(default)]
+    follow_redirects: bool,
+
+    /// Optional request quota of the DEX API, shared by all orders of an
+    /// auction. Requests beyond it are delayed instead of being sent and
+    /// rejected with a 429.
+    rate_limit: Option<dex::RateLimit>,
+
+    /// How long to wait for a connection to the DEX API to be established.
+    #[serde(with = "humantime_serde", default = "default_connect_timeout")]
+    connect_timeout: Duration,
+
+    /// How long to wait for a response from the DEX API, including
+    /// connecting, before a request is abandoned.
+    #[serde(with = "humantime_serde", default = "default_request_timeout")]
+    request_timeout: Duration,
+
+    /// Optional circuit breaker that stops requesting swaps from the DEX API
+    /// after repeated failures (timeouts and unexpected errors) until a
+    /// cooldown elapses. The backends of the fallback and multi DEXs each
+    /// have a breaker of their own.
+    circuit_breaker: Option<CircuitBreaker>,
+
+    /// Optional cache of the swaps quoted by the DEX API for the current
+    /// block, which is dropped whenever a new block arrives. Requires
+    /// `current-block-poll-interval` to be set.
+    quote_cache: Option<QuoteCache>,
+
+    /// Whether to check the on-chain allowances of the settlement contract
+    /// before approving the spender of a swap, and to leave out the approval
+    /// if the allowance is already sufficient. Solutions relying on the same
+    /// allowance in a block must fit into it together. This costs an
+    /// additional RPC call per swap and block. Requires
+    /// `current-block-poll-interval` to be set.
+    #[serde(default)]
+    check_allowances: bool,
+
+    /// The maximum value of an order (at its reference price) that the solver
+    /// requests swaps for, denominated in wei. Larger orders are skipped.
+    #[serde_as(as = "Option<serialize::U256>")]
+    max_order_value: Option<eth::U256>,
+
+    /// The maximum fraction by which the value of a swap's output may deviate
+    /// from the value of its input at the auction's reference prices, e.g.
+    /// `0.5` for 50%. Swaps beyond it are assumed to stem from a faulty DEX
+    /// API response and are discarded by the `price-check` post-processor.
+    /// Unbounded if not specified.
+    #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
+    max_price_deviation: Option<BigDecimal>,
+
+    /// The maximum total calldata size of a swap in bytes. Larger swaps (e.g.
+    /// pathological multi-hop routes) are discarded, since they might not fit
+    /// into a settlement. The fallback solver moves on to its next backend
+    /// instead.
+    #[serde(default = "default_max_calldata_bytes")]
+    max_calldata_bytes: usize,
+
+    /// The priority fee in wei that is added to the base fee of the current
+    /// block for the solver's gas price estimate.
+    #[serde(default)]
+    #[serde_as(as = "serialize::U256")]
+    gas_price_tip: eth::U256,
+
+    /// How long requests to the DEX API may fail without a single success
+    /// before `/livez` reports the solver as unhealthy. Only swaps and answers
+    /// that there is no swap for an order count as successes.
+    #[serde(with = "humantime_serde", default = "default_liveness_window")]
+    liveness_window: Duration,
+
+    /// Optional key that clients of this solver engine have to send as
+    /// `Authorization: Bearer <key>` header to `/solve` and `/quote`. Not to
+    /// be confused with the API key of the DEX, which is configured in the
+    /// `[dex]` table.
+    api_key: Option<String>,
+
+    /// The post-processors to run on every swap, in order. Leaving one out
+    /// disables it. By default all of them run: `slippage-cap`,
+    /// `price-check`, `gas-offset`, `gas-cost`, `implied-fee` and `prices`.
+    #[serde(default = "default_post_processors")]
+    post_processors: Vec<PostProcessor>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct CircuitBreaker {
+    /// The number of consecutive failures that opens the breaker.
+    #[serde(default = "default_circuit_breaker_failures")]
+    failures: u32,
+
+    /// The time within which the consecutive failures have to occur.
+    #[serde(with = "humantime_serde", default = "default_circuit_breaker_window")]
+    window: Duration,
+
+    /// How long the breaker stays open before a single probe request is sent.
+    #[serde(with = "humantime_serde", default = "default_circuit_breaker_cooldown")]
+    cooldown: Duration,
+}
+
+fn default_circuit_breaker_failures() -> u32 {
+    5
+}
+
+fn default_circuit_breaker_window() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_circuit_breaker_cooldown() -> Duration {
+    Duration::from_secs(60)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct QuoteCache {
+    /// The maximum number of quotes to cache.
+    #[serde(default = "default_quote_cache_size")]
+    max_size: u64,
+}
+
+fn default_max_calldata_bytes() -> usize {
+    100_000
+}
+
+fn default_connect_timeout() -> Duration {
+    dex::DEFAULT_CONNECT_TIMEOUT
+}
+
+fn default_request_timeout() -> Duration {
+    dex::DEFAULT_REQUEST_TIMEOUT
+}
+
+fn default_liveness_window() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+fn default_quote_cache_size() -> u64 {
+    1_000
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum PostProcessor {
+    /// Caps slippage tolerances at `max-slippage-bps`.
+    SlippageCap,
+    /// Discards swaps with prices beyond `max-price-deviation`. Does nothing
+    /// if that is not specified.
+    PriceCheck,
+    /// Adds `gas-offset` to the gas of each solution.
+    GasOffset,
+    GasCost,
+    ImpliedFee,
+    Prices,
+}
+
+impl PostProcessor {
+    fn to_domain(
+        self,
+        slippage_cap: &slippage::Cap,
+        max_price_deviation: Option<&BigDecimal>,
+        gas_offset: eth::Gas,
+    ) -> Option<post_process::Kind> {
+        Some(match self {
+            Self::SlippageCap => post_process::Kind::SlippageCap(slippage_cap.clone()),
+            Self::PriceCheck => post_process::Kind::PriceCheck(max_price_deviation?.clone()),
+            Self::GasOffset => post_process::Kind::GasOffset(gas_offset),
+            Self::GasCost => post_process::Kind::GasCost,
+            Self::ImpliedFee => post_process::Kind::ImpliedFee,
+            Self::Prices => post_process::Kind::Prices,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum SlippageCapPolicy {
+    #[default]
+    Clamp,
+    Reject,
+}
+
+impl SlippageCapPolicy {
+    fn to_domain(self) -> slippage::CapPolicy {
+        match self {
+            Self::Clamp => slippage::CapPolicy::Clamp,
+            Self::Reject => slippage::CapPolicy::Reject,
+        }
+    }
 }
 
 fn default_relative_slippage() -> BigDecimal {
     BigDecimal::new(1.into(), 2) // 1%
 }
 
+fn default_max_slippage_bps() -> u16 {
+    500 // 5%
+}
+
 fn default_concurrent_requests() -> NonZeroUsize {
     NonZeroUsize::new(1).unwrap()
 }
@@ -112,38 +359,67 @@ fn default_internalize_interactions() -> bool {
     true
 }
 
+fn default_post_processors() -> Vec<PostProcessor> {
+    vec![
+        PostProcessor::SlippageCap,
+        PostProcessor::PriceCheck,
+        PostProcessor::GasOffset,
+        PostProcessor::GasCost,
+        PostProcessor::ImpliedFee,
+        PostProcessor::Prices,
+    ]
+}
+
 /// Loads the base solver configuration from a TOML file.
 ///
 /// # Panics
 ///
 /// This method panics if the config is invalid or on I/O errors.
 pub async fn load<T: DeserializeOwned>(path: &Path) -> (super::Config, T) {
-    let data = fs::read_to_string(path)
-        .await
-        .unwrap_or_else(|e| panic!("I/O error while reading {path:?}: {e:?}"));
+    let data = read(path).await;
 
     // Not printing detailed error because it could potentially leak secrets.
     let config = unwrap_or_log(toml::de::from_str::<Config>(&data), &path);
 
     let dex: T = unwrap_or_log(config.dex.try_into(), &path);
 
+    // Features that depend on the current block are validated upfront, naming
+    // the keys to fix.
+    assert!(
+        config.quote_cache.is_none() || config.current_block_poll_interval.is_some(),
+        "invalid config {path:?}: quote-cache requires current-block-poll-interval to be set"
+    );
+    assert!(
+        !config.check_allowances || config.current_block_poll_interval.is_some(),
+        "invalid config {path:?}: check-allowances requires current-block-poll-interval to be set"
+    );
+
     // Take advantage of the fact that deterministic deployment means that all
     // CoW Protocol contracts have the same address.
     let contracts = contracts::Contracts::for_chain(eth::ChainId::Mainnet);
-    let (settlement, authenticator) = if let Some(settlement) = config.settlement {
-        let authenticator = eth::ContractAddress({
-            let web3 = blockchain::rpc(&config.node_url);
-            let settlement = ::contracts::GPv2Settlement::at(&web3, settlement);
+    let (settlement, authenticator) = match (config.settlement, config.authenticator) {
+        (settlement, Some(authenticator)) => (
             settlement
-                .methods()
-                .authenticator()
-                .call()
-                .await
-                .unwrap_or_else(|e| panic!("error reading authenticator contract address: {e:?}"))
-        });
-        (eth::ContractAddress(settlement), authenticator)
-    } else {
-        (contracts.settlement, contracts.authenticator)
+                .map(eth::ContractAddress)
+                .unwrap_or(contracts.settlement),
+            eth::ContractAddress(authenticator),
+        ),
+        (Some(settlement), None) => {
+            let authenticator = eth::ContractAddress({
+                let web3 = blockchain::rpc(&config.node_url);
+                let settlement = ::contracts::GPv2Settlement::at(&web3, settlement);
+                settlement
+                    .methods()
+                    .authenticator()
+                    .call()
+                    .await
+                    .unwrap_or_else(|e| {
+                        panic!("error reading authenticator contract address: {e:?}")
+                    })
+            });
+            (eth::ContractAddress(settlement), authenticator)
+        }
+        (None, None) => (contracts.settlement, contracts.authenticator),
     };
 
     let block_stream = match config.current_block_poll_interval {
@@ -155,17 +431,46 @@ pub async fn load<T: DeserializeOwned>(path: &Path) -> (super::Config, T) {
         None => None,
     };
 
+    let multicall = multicall::Multicall::new(&config.node_url);
+
+    let max_slippage = BigDecimal::new(config.max_slippage_bps.into(), 4);
+    assert!(
+        !config
+            .post_processors
+            .iter()
+            .any(|processor| matches!(processor, PostProcessor::SlippageCap))
+            || config.relative_slippage <= max_slippage,
+        "relative-slippage must not exceed max-slippage-bps"
+    );
+    let slippage_cap = slippage::Cap::new(max_slippage, config.slippage_cap_policy.to_domain())
+        .expect("invalid max-slippage-bps; must not exceed 10000");
+    let post_processors = config
+        .post_processors
+        .iter()
+        .filter_map(|processor| {
+            processor.to_domain(
+                &slippage_cap,
+                config.max_price_deviation.as_ref(),
+                eth::Gas(config.gas_offset),
+            )
+        })
+        .collect();
+
     let config = super::Config {
+        name: config.name,
         node_url: config.node_url,
+        chain_id: None,
         contracts: super::Contracts {
             settlement,
             authenticator,
+            weth: contracts.weth,
         },
+        custom_settlement: config.settlement.map(eth::ContractAddress),
         slippage: slippage::Limits::new(
             config.relative_slippage,
             config.absolute_slippage.map(eth::Ether),
         )
-        .expect("invalid slippage limits"),
+        .expect("invalid relative-slippage; must be in the range [0, 1]"),
         concurrent_requests: config.concurrent_requests,
         smallest_partial_fill: eth::Ether(config.smallest_partial_fill),
         rate_limiting_strategy: rate_limit::Strategy::try_new(
@@ -174,9 +479,91 @@ pub async fn load<T: DeserializeOwned>(path: &Path) -> (super::Config, T) {
             config.max_back_off,
         )
         .unwrap(),
-        gas_offset: eth::Gas(config.gas_offset),
         block_stream,
+        multicall,
         internalize_interactions: config.internalize_interactions,
+        validate_token_code: config.validate_token_code,
+        simulate_gas: config.simulate_gas,
+        simulate_swaps: config.simulate_swaps,
+        dry_run: config.dry_run,
+        follow_redirects: config.follow_redirects,
+        rate_limit: config.rate_limit,
+        connect_timeout: config.connect_timeout,
+        request_timeout: config.request_timeout,
+        circuit_breaker: config.circuit_breaker.map(|breaker| dex::breaker::Config {
+            failures: breaker.failures,
+            window: breaker.window,
+            cooldown: breaker.cooldown,
+        }),
+        quote_cache_size: config.quote_cache.map(|cache| cache.max_size),
+        check_allowances: config.check_allowances,
+        liveness_window: config.liveness_window,
+        api_key: config.api_key,
+        max_order_value: config.max_order_value.map(eth::Ether),
+        max_calldata_bytes: config.max_calldata_bytes,
+        gas_price_tip: eth::Ether(config.gas_price_tip),
+        post_processors,
     };
     (config, dex)
 }
+
+/// Loads only the DEX specific `[dex]` table of a solver configuration file,
+/// ignoring all other settings.
+///
+/// # Panics
+///
+/// This method panics if the `[dex]` table is missing or invalid or on I/O
+/// errors.
+pub async fn load_dex<T: DeserializeOwned>(path: &Path) -> T {
+    #[derive(Deserialize)]
+    struct File {
+        dex: toml::Value,
+    }
+
+    let data = read(path).await;
+    let file = unwrap_or_log(toml::de::from_str::<File>(&data), &path);
+    unwrap_or_log(file.dex.try_into(), &path)
+}
+
+async fn read(path: &Path) -> String {
+    fs::read_to_string(path)
+        .await
+        .unwrap_or_else(|e| panic!("I/O error while reading {path:?}: {e:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn load_config(config: &str) {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), config).unwrap();
+        load::<toml::Value>(file.path()).await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "quote-cache requires current-block-poll-interval")]
+    async fn quote_cache_requires_block_stream() {
+        load_config(
+            r#"
+            node-url = "http://localhost:8545"
+            [quote-cache]
+            [dex]
+            "#,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "check-allowances requires current-block-poll-interval")]
+    async fn allowance_checks_require_block_stream() {
+        load_config(
+            r#"
+            node-url = "http://localhost:8545"
+            check-allowances = true
+            [dex]
+            "#,
+        )
+        .await;
+    }
+}
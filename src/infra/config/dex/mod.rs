@@ -1,31 +1,80 @@
 pub mod balancer;
+pub mod fallback;
 mod file;
+pub mod multi;
 pub mod okx;
 pub mod oneinch;
 pub mod paraswap;
+pub mod replay;
 pub mod zeroex;
 
 use {
-    crate::domain::{dex::slippage, eth},
+    crate::{
+        domain::{dex::slippage, eth, solver::dex::post_process},
+        infra,
+    },
     ethrpc::block_stream::CurrentBlockWatcher,
-    std::num::NonZeroUsize,
+    std::{num::NonZeroUsize, time::Duration},
 };
 
 #[derive(Clone)]
 pub struct Contracts {
     pub settlement: eth::ContractAddress,
     pub authenticator: eth::ContractAddress,
+    pub weth: eth::WethAddress,
 }
 
 #[derive(Clone)]
 pub struct Config {
+    pub name: Option<String>,
     pub node_url: reqwest::Url,
+    pub chain_id: Option<eth::ChainId>,
     pub contracts: Contracts,
+    /// The settlement contract if it was configured explicitly, instead of
+    /// defaulting to the CoW Protocol deployment in `contracts`.
+    pub custom_settlement: Option<eth::ContractAddress>,
     pub slippage: slippage::Limits,
     pub concurrent_requests: NonZeroUsize,
     pub smallest_partial_fill: eth::Ether,
     pub rate_limiting_strategy: rate_limit::Strategy,
-    pub gas_offset: eth::Gas,
     pub block_stream: Option<CurrentBlockWatcher>,
+    /// Batches the contract calls of the solver (e.g. token decimals and
+    /// allowances) into shared `eth_call`s to the node.
+    pub multicall: infra::multicall::Multicall,
     pub internalize_interactions: bool,
+    pub validate_token_code: bool,
+    pub simulate_gas: bool,
+    pub simulate_swaps: bool,
+    pub dry_run: bool,
+    pub follow_redirects: bool,
+    pub rate_limit: Option<infra::dex::RateLimit>,
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    pub circuit_breaker: Option<infra::dex::breaker::Config>,
+    pub quote_cache_size: Option<u64>,
+    pub check_allowances: bool,
+    pub liveness_window: Duration,
+    pub api_key: Option<String>,
+    pub max_order_value: Option<eth::Ether>,
+    pub max_calldata_bytes: usize,
+    pub gas_price_tip: eth::Ether,
+    pub post_processors: Vec<post_process::Kind>,
+}
+
+impl Config {
+    /// Uses the WETH deployment of the chain that the DEX API is configured
+    /// for, and reports the chain. Without a chain, the mainnet WETH is used.
+    pub fn with_chain(mut self, chain_id: eth::ChainId) -> Self {
+        self.contracts.weth = infra::contracts::Contracts::for_chain(chain_id).weth;
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// The checks applied to the swaps of each DEX API on its own.
+    pub fn backend(&self) -> infra::dex::backend::Config {
+        infra::dex::backend::Config {
+            max_calldata_bytes: self.max_calldata_bytes,
+            circuit_breaker: self.circuit_breaker,
+        }
+    }
 }
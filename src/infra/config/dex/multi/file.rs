@@ -0,0 +1,282 @@
+use {
+    crate::{
+        domain::eth,
+        infra::{
+            config::dex::{self, file},
+            dex::multi,
+        },
+    },
+    bigdecimal::BigDecimal,
+    serde::Deserialize,
+    serde_with::serde_as,
+    std::{
+        path::{Path, PathBuf},
+        str::FromStr,
+        time::Duration,
+    },
+};
+
+/// The DEX APIs to quote, by the path of their solver configuration file.
+/// Only the DEX specific `[dex]` section of these files is used, all other
+/// solver settings are taken from the meta-solver configuration. The chain of
+/// the meta-solver is the one that its backends are configured for.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    balancer: Option<PathBuf>,
+    zeroex: Option<PathBuf>,
+    oneinch: Option<PathBuf>,
+    paraswap: Option<PathBuf>,
+    okx: Option<PathBuf>,
+
+    /// How long to wait for a single backend to quote an order before
+    /// skipping it, so that a slow backend does not hold up the others.
+    #[serde(with = "humantime_serde", default = "default_request_timeout")]
+    request_timeout: Duration,
+
+    /// Rules pinning the orders of token pairs to a single backend. The first
+    /// matching rule wins; orders without one are quoted by all backends.
+    #[serde(default)]
+    routes: Vec<Route>,
+
+    /// Return a good enough swap before all backends responded. Disabled if
+    /// not specified, in which case every backend is awaited (up to the
+    /// request timeout).
+    early_return: Option<EarlyReturn>,
+
+    /// Penalize swaps through public AMMs when ranking the swaps of the
+    /// backends, since they are exposed to MEV (e.g. sandwiching) and might
+    /// settle for less than quoted.
+    #[serde(default)]
+    mev_aware: bool,
+
+    /// The penalty of a MEV-aware ranking per public AMM that a swap is
+    /// routed through, in basis points of its net output.
+    #[serde(default = "default_mev_penalty_bps")]
+    mev_penalty_bps: u32,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct EarlyReturn {
+    /// How long to wait for the backends in any case, so that a fast but
+    /// mediocre backend does not always win.
+    #[serde(with = "humantime_serde")]
+    min_wait: Duration,
+
+    /// The quality threshold: the maximum price impact of the best swap so
+    /// far at the reference prices of its tokens, as a fraction (e.g. `0.001`
+    /// for 0.1%), for it to be returned without waiting for the remaining
+    /// backends.
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    max_price_impact: BigDecimal,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Route {
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    sell: Token,
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    buy: Token,
+    backend: String,
+}
+
+/// A token address, or `*` to match any token.
+struct Token(Option<eth::TokenAddress>);
+
+impl FromStr for Token {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "*" => Ok(Self(None)),
+            _ => eth::H160::from_str(s)
+                .map(|address| Self(Some(eth::TokenAddress(address))))
+                .map_err(|_| format!("invalid token {s:?}")),
+        }
+    }
+}
+
+/// Resolves the backends of the routes by their identifier, which is the key
+/// the backend is configured with (e.g. `zeroex`).
+fn resolve_routes(routes: Vec<Route>, backends: &[&str]) -> Result<Vec<multi::Route>, String> {
+    routes
+        .into_iter()
+        .map(|route| {
+            let backend = backends
+                .iter()
+                .position(|backend| *backend == route.backend)
+                .ok_or_else(|| {
+                    format!(
+                        "dex.routes refers to backend {:?}, but only {backends:?} are configured",
+                        route.backend,
+                    )
+                })?;
+            Ok(multi::Route {
+                sell: route.sell.0,
+                buy: route.buy.0,
+                backend,
+            })
+        })
+        .collect()
+}
+
+fn default_request_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_mev_penalty_bps() -> u32 {
+    5
+}
+
+/// Load the meta-solver configuration from a TOML file.
+///
+/// # Panics
+///
+/// This method panics if the config is invalid, no backend is enabled or on
+/// I/O errors.
+pub async fn load(path: &Path) -> super::Config {
+    let (base, config) = file::load::<Config>(path).await;
+
+    let paths = [
+        config.balancer.map(BackendPath::Balancer),
+        config.zeroex.map(BackendPath::Zeroex),
+        config.oneinch.map(BackendPath::Oneinch),
+        config.paraswap.map(BackendPath::Paraswap),
+        config.okx.map(BackendPath::Okx),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let (backends, base) = load_backends(paths, base).await;
+    let enabled = backends
+        .iter()
+        .map(super::Backend::name)
+        .collect::<Vec<_>>();
+    let routes = resolve_routes(config.routes, &enabled)
+        .unwrap_or_else(|err| panic!("invalid config {path:?}: {err}"));
+
+    super::Config {
+        backends,
+        request_timeout: config.request_timeout,
+        routes,
+        early_return: config.early_return.map(|early_return| multi::EarlyReturn {
+            min_wait: early_return.min_wait,
+            max_price_impact: early_return.max_price_impact,
+        }),
+        mev_penalty: config
+            .mev_aware
+            .then(|| BigDecimal::new(config.mev_penalty_bps.into(), 4)),
+        base,
+    }
+}
+
+/// A DEX API backend of the meta-solvers, by the path of its solver
+/// configuration file.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(in crate::infra::config::dex) enum BackendPath {
+    Balancer(PathBuf),
+    Zeroex(PathBuf),
+    Oneinch(PathBuf),
+    Paraswap(PathBuf),
+    Okx(PathBuf),
+}
+
+impl BackendPath {
+    /// Loads the backend from the `[dex]` table of its configuration file,
+    /// with all other settings taken from the base configuration.
+    async fn load(self, base: &dex::Config) -> super::Backend {
+        match self {
+            Self::Balancer(path) => {
+                super::Backend::Balancer(dex::balancer::file::load_backend(&path, base).await.sor)
+            }
+            Self::Zeroex(path) => {
+                super::Backend::ZeroEx(dex::zeroex::file::load_backend(&path, base).await.zeroex)
+            }
+            Self::Oneinch(path) => {
+                super::Backend::OneInch(dex::oneinch::file::load_backend(&path, base).await.oneinch)
+            }
+            Self::Paraswap(path) => super::Backend::ParaSwap(
+                dex::paraswap::file::load_backend(&path, base)
+                    .await
+                    .paraswap,
+            ),
+            Self::Okx(path) => {
+                super::Backend::Okx(dex::okx::file::load_backend(&path, base).await.okx)
+            }
+        }
+    }
+}
+
+/// Loads the backends of a meta-solver in the given order, and configures the
+/// base configuration of the meta-solver for the chain of its backends.
+///
+/// # Panics
+///
+/// This method panics if a backend config is invalid, no backend is given,
+/// the backends are configured for different chains or on I/O errors.
+pub(in crate::infra::config::dex) async fn load_backends(
+    paths: Vec<BackendPath>,
+    base: dex::Config,
+) -> (Vec<super::Backend>, dex::Config) {
+    let mut backends = Vec::new();
+    for path in paths {
+        backends.push(path.load(&base).await);
+    }
+    assert!(
+        !backends.is_empty(),
+        "at least one DEX backend must be configured"
+    );
+    let base = base.with_chain(chain_id(&backends));
+    (backends, base)
+}
+
+/// The chain that all backends are configured for.
+///
+/// # Panics
+///
+/// This method panics if the backends are configured for different chains.
+fn chain_id(backends: &[super::Backend]) -> eth::ChainId {
+    let chain_id = backends[0].chain_id();
+    for backend in backends {
+        assert_eq!(
+            backend.chain_id(),
+            chain_id,
+            "DEX backends {:?} and {:?} are configured for different chains",
+            backends[0].name(),
+            backend.name(),
+        );
+    }
+    chain_id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_routes_by_backend_identifier() {
+        let route = |backend: &str| Route {
+            sell: Token(None),
+            buy: Token(None),
+            backend: backend.to_owned(),
+        };
+
+        let routes = resolve_routes(
+            vec![route("zeroex"), route("balancer")],
+            &["balancer", "zeroex"],
+        )
+        .unwrap();
+        assert_eq!(
+            routes.iter().map(|route| route.backend).collect::<Vec<_>>(),
+            [1, 0]
+        );
+
+        let err = resolve_routes(vec![route("0x")], &["balancer", "zeroex"]).unwrap_err();
+        assert!(err.contains("\"0x\""));
+    }
+}
@@ -0,0 +1,50 @@
+use {
+    crate::{domain::eth, infra::dex},
+    bigdecimal::BigDecimal,
+    std::time::Duration,
+};
+
+pub mod file;
+
+pub struct Config {
+    pub backends: Vec<Backend>,
+    pub request_timeout: Duration,
+    pub routes: Vec<dex::multi::Route>,
+    pub early_return: Option<dex::multi::EarlyReturn>,
+    pub mev_penalty: Option<BigDecimal>,
+    pub base: super::Config,
+}
+
+/// The configuration of a DEX API that the meta-solver quotes.
+pub enum Backend {
+    Balancer(dex::balancer::Config),
+    ZeroEx(dex::zeroex::Config),
+    OneInch(dex::oneinch::Config),
+    ParaSwap(dex::paraswap::Config),
+    Okx(dex::okx::Config),
+}
+
+impl Backend {
+    /// The identifier of the backend, which is the key it is configured with
+    /// and how routes refer to it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Balancer(_) => "balancer",
+            Self::ZeroEx(_) => "zeroex",
+            Self::OneInch(_) => "oneinch",
+            Self::ParaSwap(_) => "paraswap",
+            Self::Okx(_) => "okx",
+        }
+    }
+
+    /// The chain that the backend is configured for.
+    pub fn chain_id(&self) -> eth::ChainId {
+        match self {
+            Self::Balancer(config) => config.chain_id,
+            Self::ZeroEx(config) => config.chain_id,
+            Self::OneInch(config) => config.chain_id,
+            Self::ParaSwap(config) => config.chain_id,
+            Self::Okx(config) => config.chain_id,
+        }
+    }
+}
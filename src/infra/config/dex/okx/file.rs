@@ -1,8 +1,8 @@
 use {
     crate::{
         domain::eth,
-        infra::{config::dex::file, dex::okx},
-        util::serialize,
+        infra::{self, config::dex::file, dex::okx},
+        util::{self, serialize},
     },
     serde::Deserialize,
     serde_with::serde_as,
@@ -25,6 +25,10 @@ struct Config {
     /// OKX API credentials
     #[serde(flatten)]
     okx_credentials: OkxCredentialsConfig,
+
+    /// How failed requests to the OKX API are retried. Requests are only sent
+    /// once if not specified.
+    retry: Option<util::http::RetryPolicy>,
 }
 
 #[derive(Deserialize)]
@@ -72,6 +76,22 @@ fn default_endpoint() -> reqwest::Url {
 /// This method panics if the config is invalid or on I/O errors.
 pub async fn load(path: &Path) -> super::Config {
     let (base, config) = file::load::<Config>(path).await;
+    build(config, base)
+}
+
+/// Load the OKX configuration of a meta-solver backend from a TOML file. Only
+/// the `[dex]` table of the file is used, all other settings are taken from
+/// the base configuration of the meta-solver.
+///
+/// # Panics
+///
+/// This method panics if the config is invalid or on I/O errors.
+pub async fn load_backend(path: &Path, base: &infra::config::dex::Config) -> super::Config {
+    build(file::load_dex::<Config>(path).await, base.clone())
+}
+
+fn build(config: Config, base: infra::config::dex::Config) -> super::Config {
+    let base = base.with_chain(config.chain_id);
 
     super::Config {
         okx: okx::Config {
@@ -79,7 +99,13 @@ pub async fn load(path: &Path) -> super::Config {
             chain_id: config.chain_id,
             okx_credentials: config.okx_credentials.into(),
             block_stream: base.block_stream.clone(),
+            follow_redirects: base.follow_redirects,
+            rate_limit: base.rate_limit,
+            connect_timeout: base.connect_timeout,
+            request_timeout: base.request_timeout,
+            shared_client: None,
             settlement_contract: base.contracts.settlement.0.into(),
+            retry: config.retry,
         },
         base,
     }
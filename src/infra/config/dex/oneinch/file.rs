@@ -1,20 +1,25 @@
 use {
     crate::{
         domain::eth,
-        infra::{config::dex::file, contracts, dex::oneinch},
-        util::serialize,
+        infra::{self, config::dex::file, dex::oneinch},
+        util::{self, serialize},
     },
+    ethereum_types::U256,
     serde::Deserialize,
     serde_with::serde_as,
-    std::path::Path,
+    std::{
+        collections::HashSet,
+        path::{Path, PathBuf},
+        str::FromStr,
+        time::Duration,
+    },
 };
 
 #[serde_as]
 #[derive(Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 struct Config {
-    /// Chain ID used to automatically determine the address of the settlement
-    /// contract and for metrics.
+    /// Chain ID of the network the solver is running on.
     #[serde_as(as = "serialize::ChainId")]
     chain_id: eth::ChainId,
 
@@ -22,16 +27,33 @@ struct Config {
     #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
     endpoint: Option<reqwest::Url>,
 
-    /// The 1Inch liquidity sources to consider when swapping.
+    /// The version of the 1Inch swap API to use.
+    #[serde(default)]
+    version: Version,
+
+    /// The key to authenticate with as bearer token. The public v6 API
+    /// rejects requests without one.
+    api_key: Option<String>,
+
+    /// The 1Inch liquidity sources to consider when swapping. Sources can be
+    /// specified either by their canonical name (e.g. `uniswap-v3`) or their
+    /// 1Inch protocol ID.
     include_liquidity: Option<Vec<String>>,
 
-    /// The 1Inch liquidity sources to exclude when swapping.
+    /// The 1Inch liquidity sources to exclude when swapping, specified in the
+    /// same way as `include_liquidity`. When both are set, the excluded
+    /// sources are removed from the included ones.
     exclude_liquidity: Option<Vec<String>>,
 
     /// The referrer address to use. Referrers are entitled to a portion of
     /// the positive slippage that 1Inch collects.
     referrer: Option<eth::H160>,
 
+    /// The partner fee in basis points of the sell amount. 1Inch deducts it
+    /// from the sell amount before swapping and sends it to the referrer. The
+    /// API accepts fees of up to 300 basis points.
+    fee_bps: Option<u32>,
+
     // The following configuration options tweak the complexity of the 1Inch
     // route that the API returns. Unfortunately, the exact definition (and
     // what each field actually controls) is fairly opaque and not well
@@ -39,6 +61,121 @@ struct Config {
     main_route_parts: Option<u32>,
     connector_tokens: Option<u32>,
     complexity_level: Option<u32>,
+
+    /// The gas price 1Inch should assume when deciding whether a more complex
+    /// route is worth its gas cost: either `auction` to use the effective gas
+    /// price of the auction, or a fixed amount in wei. By default, 1Inch uses
+    /// its own estimate.
+    #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
+    #[serde(default)]
+    gas_price: Option<GasPrice>,
+
+    /// Whether 1Inch may return a route that only swaps part of the sell
+    /// amount when there is not enough liquidity for all of it. Such swaps
+    /// are only used for partially fillable orders.
+    #[serde(default)]
+    allow_partial_fill: bool,
+
+    /// API error status codes that indicate that no swap could be found for
+    /// an order.
+    #[serde(default = "default_not_found_status_codes")]
+    not_found_status_codes: Vec<u16>,
+
+    /// How rate limited or otherwise transiently failing quote requests are
+    /// retried. Defaults to up to three attempts.
+    #[serde(default)]
+    retry: util::http::RetryPolicy,
+
+    /// How often the list of available liquidity sources is refreshed when
+    /// excluding liquidity sources. The refresh is disabled by `false` or a
+    /// zero interval.
+    #[serde(
+        deserialize_with = "deserialize_liquidity_refresh_interval",
+        default = "default_liquidity_refresh_interval"
+    )]
+    liquidity_refresh_interval: Option<Duration>,
+
+    /// How long to try initializing the solver before giving up.
+    #[serde(with = "humantime_serde", default)]
+    init_timeout: Option<Duration>,
+
+    /// How long to wait before trying to initialize the solver again.
+    #[serde(with = "humantime_serde", default)]
+    init_retry_delay: Option<Duration>,
+
+    /// A file for caching the spender address. The cached address is used if
+    /// fetching it from the API fails during initialization.
+    spender_cache_path: Option<PathBuf>,
+
+    /// The only contracts that swaps returned by the 1Inch API may call or
+    /// approve. Swaps calling or approving any other contract are rejected.
+    /// Unrestricted if not specified.
+    allowed_targets: Option<HashSet<eth::H160>>,
+
+    /// How the requests for bootstrapping the solver (fetching the spender
+    /// and liquidity sources) are retried before the whole initialization is
+    /// attempted again.
+    #[serde(default)]
+    bootstrap_retry: util::http::RetryPolicy,
+}
+
+struct GasPrice(oneinch::GasPrice);
+
+impl FromStr for GasPrice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auction" => Ok(Self(oneinch::GasPrice::Auction)),
+            _ => U256::from_dec_str(s)
+                .map(|price| Self(oneinch::GasPrice::Fixed(price)))
+                .map_err(|_| format!("invalid gas price {s:?}")),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Version {
+    V5,
+    #[default]
+    V6,
+}
+
+fn default_liquidity_refresh_interval() -> Option<Duration> {
+    Some(Duration::from_secs(60 * 60))
+}
+
+/// Deserializes the liquidity refresh interval from a duration or a boolean,
+/// where `false` and a zero duration disable the refresh and `true` keeps the
+/// default interval.
+fn deserialize_liquidity_refresh_interval<'de, D>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Interval {
+        Enabled(bool),
+        Every(#[serde(with = "humantime_serde")] Duration),
+    }
+
+    Ok(match Interval::deserialize(deserializer)? {
+        Interval::Enabled(true) => default_liquidity_refresh_interval(),
+        Interval::Enabled(false) => None,
+        Interval::Every(interval) => Some(interval).filter(|interval| !interval.is_zero()),
+    })
+}
+
+fn default_not_found_status_codes() -> Vec<u16> {
+    // Unfortunately, AFAIK these codes aren't documented anywhere. These are
+    // based on empirical observations of what the API has returned in the
+    // past. 403 is returned when the 1inch quote API is forbidden due to legal
+    // reason for a specific address or an artificial address was used in the
+    // request.
+    vec![400, 403]
 }
 
 /// Load the 1inch solver configuration from a TOML file.
@@ -48,27 +185,96 @@ struct Config {
 /// This method panics if the config is invalid or on I/O errors.
 pub async fn load(path: &Path) -> super::Config {
     let (base, config) = file::load::<Config>(path).await;
+    build(config, base)
+}
 
-    let settlement = contracts::Contracts::for_chain(config.chain_id).settlement;
+/// Load the 1inch configuration of a meta-solver backend from a TOML file. Only
+/// the `[dex]` table of the file is used, all other settings are taken from
+/// the base configuration of the meta-solver.
+///
+/// # Panics
+///
+/// This method panics if the config is invalid or on I/O errors.
+pub async fn load_backend(path: &Path, base: &infra::config::dex::Config) -> super::Config {
+    build(file::load_dex::<Config>(path).await, base.clone())
+}
+
+fn build(config: Config, base: infra::config::dex::Config) -> super::Config {
+    let base = base.with_chain(config.chain_id);
 
     super::Config {
         oneinch: oneinch::Config {
-            settlement,
+            settlement: base.contracts.settlement,
+            chain_id: config.chain_id,
             endpoint: config.endpoint,
-            liquidity: match (config.include_liquidity, config.exclude_liquidity) {
-                (Some(include_liquidity), None) => oneinch::Liquidity::Only(include_liquidity),
-                (None, Some(exclude_liquidity)) => oneinch::Liquidity::Exclude(exclude_liquidity),
-                (None, None) => oneinch::Liquidity::Any,
-                (Some(_), Some(_)) => {
-                    panic!("cannot specify both include-liquidity and exclude-liquidity")
-                }
+            version: match config.version {
+                Version::V5 => oneinch::Version::V5,
+                Version::V6 => oneinch::Version::V6,
+            },
+            api_key: config.api_key,
+            liquidity: oneinch::Liquidity {
+                include: config.include_liquidity,
+                exclude: config.exclude_liquidity.unwrap_or_default(),
             },
             referrer: config.referrer,
+            fee_bps: config.fee_bps,
             main_route_parts: config.main_route_parts,
             connector_tokens: config.connector_tokens,
             complexity_level: config.complexity_level,
+            gas_price: config.gas_price.map(|GasPrice(price)| price),
+            allow_partial_fill: config.allow_partial_fill,
+            not_found_status_codes: config.not_found_status_codes,
+            retry: config.retry,
+            liquidity_refresh_interval: config.liquidity_refresh_interval,
+            spender_cache_path: config.spender_cache_path,
+            init_timeout: config.init_timeout,
+            init_retry_delay: config.init_retry_delay,
+            bootstrap_retry: config.bootstrap_retry,
             block_stream: base.block_stream.clone(),
+            follow_redirects: base.follow_redirects,
+            rate_limit: base.rate_limit,
+            connect_timeout: base.connect_timeout,
+            request_timeout: base.request_timeout,
+            allowed_targets: config
+                .allowed_targets
+                .map(|targets| targets.into_iter().map(eth::ContractAddress).collect()),
+            shared_client: None,
         },
         base,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disables_liquidity_refresh() {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "kebab-case")]
+        struct Config {
+            #[serde(
+                deserialize_with = "deserialize_liquidity_refresh_interval",
+                default = "default_liquidity_refresh_interval"
+            )]
+            liquidity_refresh_interval: Option<Duration>,
+        }
+        let interval = |toml: &str| {
+            toml::from_str::<Config>(toml)
+                .unwrap()
+                .liquidity_refresh_interval
+        };
+
+        assert_eq!(interval(""), default_liquidity_refresh_interval());
+        assert_eq!(
+            interval("liquidity-refresh-interval = '5m'"),
+            Some(Duration::from_secs(5 * 60))
+        );
+        assert_eq!(
+            interval("liquidity-refresh-interval = true"),
+            default_liquidity_refresh_interval()
+        );
+        assert_eq!(interval("liquidity-refresh-interval = false"), None);
+        assert_eq!(interval("liquidity-refresh-interval = '0s'"), None);
+    }
+}
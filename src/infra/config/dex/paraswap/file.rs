@@ -1,7 +1,8 @@
 use {
     crate::{
         domain::eth::{self, ChainId},
-        infra::{config::dex::file, dex::paraswap},
+        infra::{self, config::dex::file, dex::paraswap},
+        util,
     },
     serde::Deserialize,
     serde_with::serde_as,
@@ -36,6 +37,10 @@ struct Config {
 
     /// Which chain the solver is serving.
     pub chain_id: u64,
+
+    /// How failed requests to the ParaSwap API are retried. Requests are only
+    /// sent once if not specified.
+    pub retry: Option<util::http::RetryPolicy>,
 }
 
 /// Load the ParaSwap solver configuration from a TOML file.
@@ -45,6 +50,23 @@ struct Config {
 /// This method panics if the config is invalid or on I/O errors.
 pub async fn load(path: &Path) -> super::Config {
     let (base, config) = file::load::<Config>(path).await;
+    build(config, base)
+}
+
+/// Load the ParaSwap configuration of a meta-solver backend from a TOML file.
+/// Only the `[dex]` table of the file is used, all other settings are taken
+/// from the base configuration of the meta-solver.
+///
+/// # Panics
+///
+/// This method panics if the config is invalid or on I/O errors.
+pub async fn load_backend(path: &Path, base: &infra::config::dex::Config) -> super::Config {
+    build(file::load_dex::<Config>(path).await, base.clone())
+}
+
+fn build(config: Config, base: infra::config::dex::Config) -> super::Config {
+    let chain_id = ChainId::new(config.chain_id.into()).unwrap();
+    let base = base.with_chain(chain_id);
 
     super::Config {
         paraswap: paraswap::Config {
@@ -56,8 +78,14 @@ pub async fn load(path: &Path) -> super::Config {
             address: config.address,
             api_key: config.api_key,
             partner: config.partner,
-            chain_id: ChainId::new(config.chain_id.into()).unwrap(),
+            chain_id,
             block_stream: base.block_stream.clone(),
+            follow_redirects: base.follow_redirects,
+            rate_limit: base.rate_limit,
+            connect_timeout: base.connect_timeout,
+            request_timeout: base.request_timeout,
+            shared_client: None,
+            retry: config.retry,
         },
         base,
     }
@@ -0,0 +1,28 @@
+use {
+    crate::infra::{config::dex::file, dex::replay},
+    serde::Deserialize,
+    std::path::{Path, PathBuf},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    /// The directory containing the recorded swap fixtures.
+    fixtures: PathBuf,
+}
+
+/// Load the replay solver configuration from a TOML file.
+///
+/// # Panics
+///
+/// This method panics if the config is invalid or on I/O errors.
+pub async fn load(path: &Path) -> super::Config {
+    let (base, config) = file::load::<Config>(path).await;
+
+    super::Config {
+        replay: replay::Config {
+            fixtures: config.fixtures,
+        },
+        base,
+    }
+}
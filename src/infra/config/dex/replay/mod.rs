@@ -0,0 +1,6 @@
+pub mod file;
+
+pub struct Config {
+    pub replay: crate::infra::dex::replay::Config,
+    pub base: super::Config,
+}
@@ -1,20 +1,22 @@
 use {
     crate::{
         domain::eth,
-        infra::{config::dex::file, contracts, dex::zeroex},
-        util::serialize,
+        infra::{self, config::dex::file, dex::zeroex},
+        util::{self, serialize},
     },
     serde::Deserialize,
     serde_with::serde_as,
-    std::path::Path,
+    std::{
+        collections::{HashMap, HashSet},
+        path::Path,
+    },
 };
 
 #[serde_as]
 #[derive(Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 struct Config {
-    /// Chain ID used to automatically determine the address of the settlement
-    /// contract and for metrics.
+    /// Chain ID of the network to request quotes for.
     #[serde_as(as = "serialize::ChainId")]
     chain_id: eth::ChainId,
 
@@ -28,15 +30,45 @@ struct Config {
     api_key: String,
 
     /// The list of excluded liquidity sources. Liquidity from these sources
-    /// will not be considered when solving.
+    /// will not be considered when solving. Sources can be specified either
+    /// by their canonical name (e.g. `balancer-v2`) or their 0x name.
     #[serde(default)]
     excluded_sources: Vec<String>,
+
+    /// The list of included liquidity sources. When set, only liquidity from
+    /// these sources will be considered when solving. Cannot be combined with
+    /// `excluded-sources`, neither list takes precedence over the other.
+    #[serde(default)]
+    included_sources: Vec<String>,
+
+    /// Overrides for whether 0x API error codes are retriable (`true`) or
+    /// terminal (`false`). These are merged with a built-in mapping.
+    #[serde(default)]
+    #[serde_as(as = "HashMap<serde_with::DisplayFromStr, _>")]
+    retriable_error_codes: HashMap<i64, bool>,
+
+    /// How failed requests to the 0x API are retried. Requests are only sent
+    /// once if not specified.
+    retry: Option<util::http::RetryPolicy>,
+
+    /// The only contracts that swaps returned by the 0x API may call or
+    /// approve. Swaps calling or approving any other contract are rejected.
+    /// Unrestricted if not specified.
+    allowed_targets: Option<HashSet<eth::H160>>,
 }
 
 fn default_endpoint() -> reqwest::Url {
     "https://api.0x.org/swap/allowance-holder/".parse().unwrap()
 }
 
+fn default_retriable_error_codes() -> HashMap<i64, bool> {
+    // Unfortunately, AFAIK these codes aren't documented anywhere. These are
+    // based on empirical observations of what the API has returned in the
+    // past: 103 and 429 are returned when throttling requests, 100 and 101
+    // when the request fails validation.
+    HashMap::from([(100, false), (101, false), (103, true), (429, true)])
+}
+
 /// Load the 0x solver configuration from a TOML file.
 ///
 /// # Panics
@@ -44,8 +76,27 @@ fn default_endpoint() -> reqwest::Url {
 /// This method panics if the config is invalid or on I/O errors.
 pub async fn load(path: &Path) -> super::Config {
     let (base, config) = file::load::<Config>(path).await;
+    build(config, base)
+}
+
+/// Load the 0x configuration of a meta-solver backend from a TOML file. Only
+/// the `[dex]` table of the file is used, all other settings are taken from
+/// the base configuration of the meta-solver.
+///
+/// # Panics
+///
+/// This method panics if the config is invalid or on I/O errors.
+pub async fn load_backend(path: &Path, base: &infra::config::dex::Config) -> super::Config {
+    build(file::load_dex::<Config>(path).await, base.clone())
+}
+
+fn build(config: Config, base: infra::config::dex::Config) -> super::Config {
+    let base = base.with_chain(config.chain_id);
+
+    let settlement = settlement(base.custom_settlement, config.chain_id);
 
-    let settlement = contracts::Contracts::for_chain(config.chain_id).settlement;
+    let mut retriable_error_codes = default_retriable_error_codes();
+    retriable_error_codes.extend(config.retriable_error_codes);
 
     super::Config {
         zeroex: zeroex::Config {
@@ -53,9 +104,59 @@ pub async fn load(path: &Path) -> super::Config {
             endpoint: config.endpoint,
             api_key: config.api_key,
             excluded_sources: config.excluded_sources,
+            included_sources: config.included_sources,
+            retriable_error_codes,
             settlement,
             block_stream: base.block_stream.clone(),
+            follow_redirects: base.follow_redirects,
+            rate_limit: base.rate_limit,
+            connect_timeout: base.connect_timeout,
+            request_timeout: base.request_timeout,
+            allowed_targets: config
+                .allowed_targets
+                .map(|targets| targets.into_iter().map(eth::ContractAddress).collect()),
+            shared_client: None,
+            retry: config.retry,
         },
         base,
     }
 }
+
+/// The settlement contract that swaps are settled by: the configured one, or
+/// else the CoW Protocol deployment on the chain that 0x is queried for.
+///
+/// # Panics
+///
+/// This method panics if no settlement contract is configured and none is
+/// known for the chain, instead of settling to a wrong address.
+fn settlement(
+    configured: Option<eth::ContractAddress>,
+    chain_id: eth::ChainId,
+) -> eth::ContractAddress {
+    configured.unwrap_or_else(|| {
+        infra::contracts::try_contract_address_for_chain(
+            chain_id,
+            contracts::GPv2Settlement::raw_contract(),
+        )
+        .unwrap_or_else(|| {
+            panic!("no settlement contract known for chain {chain_id:?}, please configure one")
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_configured_settlement() {
+        let custom = eth::ContractAddress(eth::H160([0x42; 20]));
+        for chain_id in [eth::ChainId::Mainnet, eth::ChainId::Gnosis] {
+            assert_eq!(settlement(Some(custom), chain_id), custom);
+        }
+        assert_eq!(
+            settlement(None, eth::ChainId::Gnosis),
+            infra::contracts::Contracts::for_chain(eth::ChainId::Gnosis).settlement,
+        );
+    }
+}
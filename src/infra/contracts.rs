@@ -27,15 +27,29 @@ impl Contracts {
     }
 }
 
+/// The Multicall3 contract, which is deployed at the same address on all
+/// chains that it is available on.
+pub const MULTICALL3: eth::ContractAddress = eth::ContractAddress(eth::H160([
+    0xca, 0x11, 0xbd, 0xe0, 0x59, 0x77, 0xb3, 0x63, 0x11, 0x67, 0x02, 0x88, 0x62, 0xbe, 0x2a, 0x17,
+    0x39, 0x76, 0xca, 0x11,
+]));
+
 pub fn contract_address_for_chain(
     chain: eth::ChainId,
     contract: &contracts::ethcontract::Contract,
 ) -> eth::ContractAddress {
-    eth::ContractAddress(
-        contract
-            .networks
-            .get(chain.network_id())
-            .expect("contract address for all supported chains")
-            .address,
-    )
+    try_contract_address_for_chain(chain, contract)
+        .expect("contract address for all supported chains")
+}
+
+/// Returns the address of the contract on the specified chain, or `None` if
+/// the contract is not known to be deployed there.
+pub fn try_contract_address_for_chain(
+    chain: eth::ChainId,
+    contract: &contracts::ethcontract::Contract,
+) -> Option<eth::ContractAddress> {
+    contract
+        .networks
+        .get(chain.network_id())
+        .map(|network| eth::ContractAddress(network.address))
 }
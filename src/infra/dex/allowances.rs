@@ -0,0 +1,151 @@
+use {
+    crate::{
+        domain::{dex, eth},
+        infra::multicall::{self, Multicall},
+    },
+    ethrpc::block_stream::CurrentBlockWatcher,
+    moka::future::Cache,
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+};
+
+const DEFAULT_CACHE_SIZE: u64 = 10_000;
+
+/// Reads the allowances the settlement contract has granted on-chain, so that
+/// solutions don't approve spenders that are already allowed to transfer
+/// enough tokens. Allowances are cached for the current block, and concurrent
+/// lookups (e.g. for the swaps of an auction) are batched into a single RPC
+/// request.
+///
+/// Solutions relying on the same allowance may end up being settled together,
+/// so the amounts of the swaps that already rely on an allowance in the
+/// current block are reserved, and only the remainder is available to other
+/// swaps.
+pub struct Allowances {
+    multicall: Multicall,
+    settlement: eth::ContractAddress,
+    blocks: CurrentBlockWatcher,
+    cache: Cache<(u64, eth::TokenAddress, eth::ContractAddress), eth::U256>,
+    reserved: Mutex<Reserved>,
+}
+
+impl Allowances {
+    pub fn new(
+        multicall: Multicall,
+        settlement: eth::ContractAddress,
+        blocks: CurrentBlockWatcher,
+    ) -> Self {
+        let cache = Cache::new(DEFAULT_CACHE_SIZE);
+        tokio::spawn(invalidate_on_new_block(blocks.clone(), cache.clone()));
+        Self {
+            multicall,
+            settlement,
+            blocks,
+            cache,
+            reserved: Default::default(),
+        }
+    }
+
+    /// Returns whether the settlement contract already allows the spender to
+    /// transfer at least the required amount of the token, on top of the
+    /// amounts reserved by other swaps in the current block. If so, the
+    /// required amount is reserved. Allowances that can't be read are assumed
+    /// to be insufficient.
+    pub async fn reserve(&self, token: eth::TokenAddress, required: &dex::Allowance) -> bool {
+        let spender = required.spender;
+        let block = self.blocks.borrow().number;
+        let allowance = self
+            .cache
+            .try_get_with((block, token, spender), async {
+                let call = multicall::Call::allowance(token, self.settlement.0, spender.0);
+                let data = self.multicall.call(call, Some(block)).await?;
+                multicall::decode_uint(&data).ok_or(multicall::Error::InvalidReturnData)
+            })
+            .await
+            .inspect_err(|err: &Arc<multicall::Error>| {
+                tracing::debug!(?token, ?spender, ?err, "failed to fetch allowance");
+            });
+        let Ok(allowance) = allowance else {
+            return false;
+        };
+        self.reserved.lock().unwrap().reserve(
+            block,
+            (token, spender),
+            allowance,
+            required.amount.get(),
+        )
+    }
+}
+
+/// The allowance amounts reserved by swaps in a block.
+#[derive(Default)]
+struct Reserved {
+    block: u64,
+    amounts: HashMap<(eth::TokenAddress, eth::ContractAddress), eth::U256>,
+}
+
+impl Reserved {
+    /// Reserves the amount of the allowance, unless the remainder of the
+    /// allowance is insufficient. Reservations of earlier blocks are dropped.
+    fn reserve(
+        &mut self,
+        block: u64,
+        key: (eth::TokenAddress, eth::ContractAddress),
+        allowance: eth::U256,
+        amount: eth::U256,
+    ) -> bool {
+        if block != self.block {
+            self.block = block;
+            self.amounts.clear();
+        }
+        let reserved = self.amounts.entry(key).or_default();
+        match reserved.checked_add(amount) {
+            Some(total) if total <= allowance => {
+                *reserved = total;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Drops all cached allowances whenever a new block arrives, since they are
+/// keyed on the block number and never requested again.
+async fn invalidate_on_new_block(
+    mut blocks: CurrentBlockWatcher,
+    cache: Cache<(u64, eth::TokenAddress, eth::ContractAddress), eth::U256>,
+) {
+    while blocks.changed().await.is_ok() {
+        cache.invalidate_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserves_allowances_across_swaps() {
+        let key = |token: u8| {
+            (
+                eth::TokenAddress(eth::H160([token; 20])),
+                eth::ContractAddress(eth::H160([0xff; 20])),
+            )
+        };
+        let mut reserved = Reserved::default();
+
+        // Two swaps fit into the allowance on their own, but not together.
+        assert!(reserved.reserve(1, key(1), 150.into(), 100.into()));
+        assert!(!reserved.reserve(1, key(1), 150.into(), 100.into()));
+        assert!(reserved.reserve(1, key(1), 150.into(), 50.into()));
+        // Allowances of other tokens are reserved separately.
+        assert!(reserved.reserve(1, key(2), 150.into(), 100.into()));
+        // Reservations only last for the block.
+        assert!(reserved.reserve(2, key(1), 150.into(), 100.into()));
+        // Unlimited allowances never run out.
+        assert!(reserved.reserve(2, key(3), eth::U256::MAX, eth::U256::MAX));
+        assert!(reserved.reserve(2, key(3), eth::U256::MAX, 0.into()));
+    }
+}
@@ -0,0 +1,239 @@
+use {
+    super::{breaker, Dex, Error},
+    crate::{
+        domain::{auction, dex},
+        infra,
+    },
+    std::{
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// The longest a DEX API may hold off our requests with a `Retry-After`
+/// header, so that a bogus value can't disable a backend for good.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// A DEX API along with the state that is tracked for it on its own. The meta
+/// DEXs keep one for each of their backends, so that e.g. a single rate
+/// limited or failing backend does not hold off all the others, and only pass
+/// through the requests made to themselves.
+pub struct Backend {
+    dex: Dex,
+    config: Config,
+
+    /// Stops requesting swaps from the DEX API while it keeps failing.
+    circuit_breaker: Option<breaker::CircuitBreaker>,
+
+    /// Until when the DEX API asked us not to send any requests in the
+    /// `Retry-After` header of a rate limited response.
+    retry_after: Mutex<Option<Instant>>,
+}
+
+/// The checks applied to the swaps of each DEX API on its own, so that the
+/// fallback DEX moves on to the next backend if a swap fails them.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Swaps with more calldata than this are discarded, since they might not
+    /// fit into a settlement.
+    pub max_calldata_bytes: usize,
+    /// Optional circuit breaker that stops requesting swaps from a DEX API
+    /// after repeated failures.
+    pub circuit_breaker: Option<breaker::Config>,
+}
+
+impl Backend {
+    pub fn new(dex: Dex, config: Config) -> Self {
+        let circuit_breaker = config
+            .circuit_breaker
+            .map(|breaker| breaker::CircuitBreaker::new(dex.name(), breaker));
+        Self {
+            dex,
+            config,
+            circuit_breaker,
+            retry_after: Default::default(),
+        }
+    }
+
+    /// A short name of the DEX API for logging.
+    pub fn name(&self) -> &'static str {
+        self.dex.name()
+    }
+
+    /// Requests a swap from the DEX API, unless it asked us to hold off or
+    /// keeps failing, and checks it.
+    pub async fn swap(
+        &self,
+        order: &dex::Order,
+        slippage: &dex::Slippage,
+        tokens: &auction::Tokens,
+        gas_price: auction::GasPrice,
+    ) -> Result<dex::Swap, Error> {
+        if self.dex.is_meta() {
+            return self.dex.swap(order, slippage, tokens, gas_price).await;
+        }
+        if let Some(retry_after) = self.remaining_retry_after(Instant::now()) {
+            return Err(Error::RateLimited {
+                retry_after: Some(retry_after),
+            });
+        }
+        if !self
+            .circuit_breaker
+            .as_ref()
+            .is_none_or(|breaker| breaker.permit())
+        {
+            return Err(Error::Unavailable);
+        }
+        let swap = self
+            .dex
+            .swap(order, slippage, tokens, gas_price)
+            .await
+            .inspect(|_| self.record(true))
+            .inspect_err(|err| self.record(!err.is_upstream_failure()))
+            .and_then(|swap| {
+                infra::metrics::swap_calldata(self.name(), swap.calldata_bytes());
+                self.check_calldata(swap)
+            });
+        if let Some(retry_after) = swap.as_ref().err().and_then(Error::retry_after) {
+            self.hold_off(retry_after, Instant::now());
+        }
+        swap
+    }
+
+    /// Reports the outcome of a permitted request to the circuit breaker.
+    fn record(&self, success: bool) {
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.record(success);
+        }
+    }
+
+    /// Discards swaps with more calldata than allowed.
+    fn check_calldata(&self, swap: dex::Swap) -> Result<dex::Swap, Error> {
+        let calldata_bytes = swap.calldata_bytes();
+        if calldata_bytes > self.config.max_calldata_bytes {
+            return Err(Error::CalldataTooLarge(calldata_bytes));
+        }
+        Ok(swap)
+    }
+
+    /// Holds off requests for as long as the DEX API asked us to, but no
+    /// longer than [`MAX_RETRY_AFTER`].
+    fn hold_off(&self, retry_after: Duration, now: Instant) {
+        let retry_after = retry_after.min(MAX_RETRY_AFTER);
+        tracing::debug!(
+            dex = self.name(),
+            ?retry_after,
+            "DEX API asked to retry later"
+        );
+        *self.retry_after.lock().unwrap() = Some(now + retry_after);
+    }
+
+    /// Returns how much longer requests are held off as asked for by the DEX
+    /// API, if at all.
+    fn remaining_retry_after(&self, now: Instant) -> Option<Duration> {
+        let until = (*self.retry_after.lock().unwrap())?;
+        Some(until.saturating_duration_since(now)).filter(|delay| !delay.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            domain::{
+                dex::testing::{asset, order, swap, token},
+                eth,
+            },
+            infra::dex::replay,
+        },
+        ethereum_types::U256,
+    };
+
+    fn backend(max_calldata_bytes: usize) -> Backend {
+        backend_with(Config {
+            max_calldata_bytes,
+            circuit_breaker: None,
+        })
+    }
+
+    fn backend_with(config: Config) -> Backend {
+        let fixtures = tempfile::tempdir().unwrap();
+        Backend::new(
+            Dex::Replay(
+                replay::Replay::new(replay::Config {
+                    fixtures: fixtures.path().to_owned(),
+                })
+                .unwrap(),
+            ),
+            config,
+        )
+    }
+
+    #[test]
+    fn caps_retry_after() {
+        let backend = backend(usize::MAX);
+        let now = Instant::now();
+        assert_eq!(backend.remaining_retry_after(now), None);
+
+        backend.hold_off(Duration::from_secs(5), now);
+        assert_eq!(
+            backend.remaining_retry_after(now),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            backend.remaining_retry_after(now + Duration::from_secs(5)),
+            None
+        );
+
+        backend.hold_off(Duration::from_secs(60 * 60), now);
+        assert_eq!(backend.remaining_retry_after(now), Some(MAX_RETRY_AFTER));
+    }
+
+    #[test]
+    fn discards_swaps_with_too_much_calldata() {
+        let swap = |calldata_bytes| dex::Swap {
+            calls: vec![dex::Call::swap(
+                eth::ContractAddress(Default::default()),
+                vec![0; calldata_bytes],
+            )],
+            ..swap(asset(token(1), 1), asset(token(2), 1))
+        };
+        let backend = backend(100);
+
+        assert!(backend.check_calldata(swap(100)).is_ok());
+        assert!(matches!(
+            backend.check_calldata(swap(101)),
+            Err(Error::CalldataTooLarge(101))
+        ));
+    }
+
+    async fn quote(backend: &Backend) -> Result<dex::Swap, Error> {
+        backend
+            .swap(
+                &order(1_000),
+                &dex::Slippage::one_percent(),
+                &auction::Tokens(Default::default()),
+                auction::GasPrice(eth::Ether(U256::zero())),
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn breaks_the_circuit_of_failing_backends_only() {
+        let config = Config {
+            max_calldata_bytes: usize::MAX,
+            circuit_breaker: Some(breaker::Config {
+                failures: 1,
+                window: Duration::from_secs(60),
+                cooldown: Duration::from_secs(60),
+            }),
+        };
+        let failing = backend_with(config.clone());
+        let healthy = backend_with(config);
+        failing.record(false);
+
+        assert!(matches!(quote(&failing).await, Err(Error::Unavailable)));
+        assert!(matches!(quote(&healthy).await, Err(Error::NotFound)));
+    }
+}
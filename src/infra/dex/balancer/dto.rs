@@ -1,6 +1,6 @@
 use {
     crate::{
-        domain::{auction, dex, eth, order},
+        domain::{dex, eth, order},
         infra::dex::balancer::Error,
         util::serialize,
     },
@@ -9,11 +9,12 @@ use {
     number::conversions::u256_to_big_decimal,
     serde::{Deserialize, Serialize, Serializer},
     serde_with::serde_as,
+    std::fmt,
 };
 
 /// Get swap quote from the SOR v2 for the V2 vault.
 const QUERY: &str = r#"
-query sorGetSwapPaths($callDataInput: GqlSwapCallDataInput!, $chain: GqlChain!, $queryBatchSwap: Boolean!, $swapAmount: AmountHumanReadable!, $swapType: GqlSorSwapType!, $tokenIn: String!, $tokenOut: String!) {
+query sorGetSwapPaths($callDataInput: GqlSwapCallDataInput!, $chain: GqlChain!, $queryBatchSwap: Boolean!, $swapAmount: AmountHumanReadable!, $swapType: GqlSorSwapType!, $tokenIn: String!, $tokenOut: String!, $useProtocolVersion: Int) {
     sorGetSwapPaths(
         callDataInput: $callDataInput,
         chain: $chain,
@@ -22,6 +23,7 @@ query sorGetSwapPaths($callDataInput: GqlSwapCallDataInput!, $chain: GqlChain!,
         swapType: $swapType,
         tokenIn: $tokenIn,
         tokenOut: $tokenOut,
+        useProtocolVersion: $useProtocolVersion,
     ) {
         tokenAddresses
         swaps {
@@ -58,23 +60,17 @@ pub struct Query<'a> {
 }
 
 impl Query<'_> {
+    /// Creates a query for the order, with the decimals of the token of the
+    /// swapped amount (see [`dex::Order::amount`]).
     pub fn from_domain(
         order: &dex::Order,
-        tokens: &auction::Tokens,
+        token_decimals: u8,
         slippage: &dex::Slippage,
         chain: Chain,
         contract_address: eth::ContractAddress,
         query_batch_swap: bool,
         swap_deadline: Option<u64>,
-    ) -> Result<Self, Error> {
-        let token_decimals = match order.side {
-            order::Side::Buy => tokens
-                .decimals(&order.buy)
-                .ok_or(Error::MissingDecimals(order.buy)),
-            order::Side::Sell => tokens
-                .decimals(&order.sell)
-                .ok_or(Error::MissingDecimals(order.sell)),
-        }?;
+    ) -> Self {
         let variables = Variables {
             call_data_input: CallDataInput {
                 deadline: swap_deadline,
@@ -88,11 +84,19 @@ impl Query<'_> {
             swap_type: SwapType::from_domain(order.side),
             token_in: order.sell.0,
             token_out: order.buy.0,
+            use_protocol_version: None,
         };
-        Ok(Self {
+        Self {
             query: QUERY,
             variables,
-        })
+        }
+    }
+
+    /// Restricts the SOR to routes through the specified Balancer version.
+    /// By default, the SOR picks the best route across versions.
+    pub fn with_protocol_version(mut self, version: Option<ProtocolVersion>) -> Self {
+        self.variables.use_protocol_version = version.map(|version| version as u8);
+        self
     }
 }
 
@@ -152,6 +156,9 @@ struct Variables {
     token_in: H160,
     /// Token address of the tokenOut.
     token_out: H160,
+    /// Only consider routes through this Balancer version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    use_protocol_version: Option<u8>,
 }
 
 /// Inputs for the call data to create the swap transaction. If this input is
@@ -236,6 +243,73 @@ pub struct Data {
     pub sor_get_swap_paths: Quote,
 }
 
+/// The GraphQL errors returned by the Balancer SOR service when it fails to
+/// compute a route, for example because the `queryBatchSwap` call it makes to
+/// update the quote with on-chain values reverted.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct ErrorResponse {
+    pub errors: Vec<ErrorMessage>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct ErrorMessage {
+    pub message: String,
+}
+
+impl ErrorResponse {
+    /// The selector of the standard Solidity `Error(string)` revert.
+    const ERROR_SELECTOR: &'static str = "0x08c379a0";
+
+    /// Returns the reason of the reverted on-chain query, if any of the
+    /// errors is a revert. Reasons that are encoded as a standard
+    /// `Error(string)` revert are decoded, otherwise the raw error message is
+    /// returned.
+    pub fn revert_reason(&self) -> Option<String> {
+        self.errors.iter().find_map(|error| {
+            let start = error.message.find(Self::ERROR_SELECTOR);
+            if start.is_none() && !error.message.to_lowercase().contains("revert") {
+                return None;
+            }
+            Some(
+                start
+                    .and_then(|start| {
+                        let data = &error.message[start + 2..];
+                        let end = data
+                            .find(|c: char| !c.is_ascii_hexdigit())
+                            .unwrap_or(data.len());
+                        decode_revert_reason(&hex::decode(&data[..end]).ok()?)
+                    })
+                    .unwrap_or_else(|| error.message.clone()),
+            )
+        })
+    }
+}
+
+impl fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let messages = self
+            .errors
+            .iter()
+            .map(|error| error.message.as_str())
+            .collect::<Vec<_>>();
+        f.write_str(&messages.join("; "))
+    }
+}
+
+/// Decodes the message of ABI-encoded `Error(string)` revert data.
+fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    let word = |offset: usize| -> Option<usize> {
+        U256::from_big_endian(data.get(offset..offset.checked_add(32)?)?)
+            .try_into()
+            .ok()
+    };
+    let offset = word(4)?.checked_add(4)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let message = data.get(start..start.checked_add(len)?)?;
+    String::from_utf8(message.to_vec()).ok()
+}
+
 /// The swap route found by the Balancer SOR service.
 #[serde_as]
 #[derive(Debug, Default, PartialEq, Deserialize)]
@@ -268,7 +342,7 @@ pub struct Quote {
     pub protocol_version: ProtocolVersion,
 }
 
-#[derive(serde_repr::Deserialize_repr, PartialEq, Eq, Default, Debug)]
+#[derive(serde_repr::Deserialize_repr, Clone, Copy, PartialEq, Eq, Default, Debug)]
 #[serde(untagged)]
 #[repr(u8)]
 pub enum ProtocolVersion {
@@ -278,6 +352,14 @@ pub enum ProtocolVersion {
 }
 
 impl Quote {
+    /// Returns the IDs of all pools that the swap route goes through.
+    pub fn pools(&self) -> impl Iterator<Item = &PoolId> {
+        self.swaps
+            .iter()
+            .map(|swap| &swap.pool_id)
+            .chain(self.paths.iter().flat_map(|path| &path.pools))
+    }
+
     /// Check for "empty" quotes - i.e. all 0's with no swaps. Balancer SOR API
     /// returns this in case it fails to find a route for whatever reason (not
     /// enough liquidity, no trading path, etc.). We don't consider this an
@@ -334,6 +416,15 @@ impl PoolId {
     }
 }
 
+impl fmt::Display for PoolId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::V2(id) => write!(f, "{id:?}"),
+            Self::V3(address) => write!(f, "{address:?}"),
+        }
+    }
+}
+
 impl Default for PoolId {
     fn default() -> Self {
         Self::V2(H256::default())
@@ -415,6 +506,7 @@ mod value_or_string {
 mod tests {
     use {
         super::*,
+        crate::domain::auction,
         maplit::hashmap,
         number::conversions::big_decimal_to_u256,
         serde_json::json,
@@ -449,6 +541,9 @@ mod tests {
             side: order::Side::Buy,
             amount: dex::Amount::new(U256::from(1000)),
             owner: H160::from_str("0x9008d19f58aabd9ed0d60971565aa8510560ab41").unwrap(),
+            partially_fillable: false,
+            fill_ratio: None,
+            slippage: None,
         };
         let slippage = dex::Slippage::one_percent();
         let chain = Chain::Mainnet;
@@ -457,16 +552,15 @@ mod tests {
         );
         let query = Query::from_domain(
             &order,
-            &tokens,
+            tokens.decimals(&order.amount().token).unwrap(),
             &slippage,
             chain,
             contract_address,
             false,
             Some(12345_u64),
-        )
-        .unwrap();
+        );
 
-        let actual = serde_json::to_value(query).unwrap();
+        let actual = serde_json::to_value(&query).unwrap();
         let expected = json!({
             "query": QUERY,
             "variables": {
@@ -486,6 +580,37 @@ mod tests {
         });
 
         assert_eq!(actual, expected);
+
+        let query = query.with_protocol_version(Some(ProtocolVersion::V3));
+        assert_eq!(
+            serde_json::to_value(&query).unwrap()["variables"]["useProtocolVersion"],
+            json!(3)
+        );
+    }
+
+    #[test]
+    fn decodes_query_revert_reason() {
+        let errors = |message: &str| ErrorResponse {
+            errors: vec![ErrorMessage {
+                message: message.to_owned(),
+            }],
+        };
+        let encoded = format!(
+            "0x08c379a0{:0>64x}{:0>64x}{:0<64}",
+            32,
+            7,
+            hex::encode("BAL#507"),
+        );
+
+        assert_eq!(
+            errors(&format!("execution reverted (data=\"{encoded}\")")).revert_reason(),
+            Some("BAL#507".to_owned())
+        );
+        assert_eq!(
+            errors("execution reverted").revert_reason(),
+            Some("execution reverted".to_owned())
+        );
+        assert_eq!(errors("unsupported token").revert_reason(), None);
     }
 
     #[test]
@@ -12,11 +12,9 @@ use {
     contracts::ethcontract::I256,
     ethereum_types::U256,
     ethrpc::block_stream::CurrentBlockWatcher,
-    num::ToPrimitive,
     std::{
-        ops::Add,
         sync::atomic::{self, AtomicU64},
-        time::Duration,
+        time::{Duration, SystemTime},
     },
     tracing::Instrument,
 };
@@ -25,9 +23,12 @@ mod dto;
 mod v2;
 mod v3;
 
+pub use self::dto::{PoolId, ProtocolVersion};
+
 /// Bindings to the Balancer Smart Order Router (SOR) API.
 pub struct Sor {
     client: super::Client,
+    retry: util::http::RetryPolicy,
     endpoint: reqwest::Url,
     v2_vault: v2::Vault,
     v3_batch_router: v3::Router,
@@ -35,12 +36,42 @@ pub struct Sor {
     settlement: eth::ContractAddress,
     chain_id: Chain,
     query_batch_swap: bool,
+    swap_deadline: Option<Duration>,
+    deprecated_pools: Vec<PoolId>,
+    reject_deprecated_pools: bool,
+    gas_per_swap: GasPerSwap,
+    protocol_version: Option<ProtocolVersion>,
+    use_internal_balance_in: bool,
+    use_internal_balance_out: bool,
+    recipient: eth::H160,
+    decimals: Option<super::Decimals>,
 }
 
 pub struct Config {
     /// Stream that yields every new block.
     pub block_stream: Option<CurrentBlockWatcher>,
 
+    /// Whether to follow HTTP redirects from the API.
+    pub follow_redirects: bool,
+
+    /// Optional request quota of the API, shared by all requests made by
+    /// this solver.
+    pub rate_limit: Option<super::RateLimit>,
+
+    /// How long to wait for a connection to the API to be established.
+    pub connect_timeout: Duration,
+
+    /// How long to wait for a response from the API, including connecting.
+    pub request_timeout: Duration,
+
+    /// An HTTP client shared with other DEX APIs to send requests with,
+    /// instead of building a dedicated one.
+    pub shared_client: Option<reqwest::Client>,
+
+    /// How failed requests are retried. Requests are only sent once if not
+    /// specified.
+    pub retry: Option<util::http::RetryPolicy>,
+
     /// The URL for the Balancer SOR API.
     pub endpoint: reqwest::Url,
 
@@ -63,18 +94,124 @@ pub struct Config {
     /// Whether to run `queryBatchSwap` to update the return amount with most
     /// up-to-date on-chain values.
     pub query_batch_swap: bool,
+
+    /// How long the swap calls remain valid on-chain. It is also used as the
+    /// deadline of the SOR query. If not set, the swaps never expire.
+    pub swap_deadline: Option<Duration>,
+
+    /// Pools that are deprecated or considered risky. Routes through these
+    /// pools produce a warning.
+    pub deprecated_pools: Vec<PoolId>,
+
+    /// Whether to reject routes through deprecated pools instead of just
+    /// warning about them.
+    pub reject_deprecated_pools: bool,
+
+    /// The approximate gas that an individual swap uses, by pool kind.
+    pub gas_per_swap: GasPerSwap,
+
+    /// Only route through pools of this Balancer version. If not set, the SOR
+    /// picks the best route across V2 and V3 pools.
+    pub protocol_version: Option<ProtocolVersion>,
+
+    /// Whether V2 swaps pay the input token from the settlement contract's
+    /// Vault internal balance instead of transferring it from its ERC20
+    /// balance.
+    pub use_internal_balance_in: bool,
+
+    /// Whether V2 swaps credit the output token to the recipient's Vault
+    /// internal balance instead of transferring it as ERC20 tokens.
+    pub use_internal_balance_out: bool,
+
+    /// The address that receives the output of V2 swaps. If not set, the
+    /// output goes to the settlement contract.
+    pub recipient: Option<eth::H160>,
+
+    /// Fetches the decimals of tokens that are missing from the auction. If
+    /// not set, orders for such tokens are skipped.
+    pub decimals: Option<super::decimals::Config>,
 }
 
-impl Sor {
-    /// An approximate gas an individual Balancer swap uses.
+/// The approximate gas an individual Balancer swap uses, depending on the kind
+/// of pool it goes through. V2 pools are classified by the specialization
+/// that is encoded in their pool ID.
+#[derive(Debug, Clone, Copy)]
+pub struct GasPerSwap {
+    /// V2 pools with the general specialization (e.g. composable stable
+    /// pools).
+    pub general: u64,
+    /// V2 pools with the minimal swap info specialization (e.g. weighted
+    /// pools with more than two tokens).
+    pub minimal_swap_info: u64,
+    /// V2 pools with the two token specialization.
+    pub two_token: u64,
+    /// V3 pools.
+    pub v3: u64,
+}
+
+impl GasPerSwap {
+    /// The gas an individual swap uses when nothing is known about the pool.
     ///
     /// This value was determined heuristically using a Dune query that has been
     /// lost to time... See <https://github.com/cowprotocol/services/pull/171>.
-    const GAS_PER_SWAP: u64 = 88_892;
+    pub const DEFAULT: u64 = 88_892;
+
+    /// Returns the approximate gas of all swaps of the quoted route.
+    fn route(&self, quote: &dto::Quote) -> u64 {
+        quote
+            .swaps
+            .iter()
+            .map(|swap| self.pool(&swap.pool_id))
+            .sum()
+    }
+
+    fn pool(&self, pool_id: &PoolId) -> u64 {
+        match pool_id {
+            // The two bytes after the pool address encode the specialization.
+            PoolId::V2(id) => match u16::from_be_bytes([id[20], id[21]]) {
+                1 => self.minimal_swap_info,
+                2 => self.two_token,
+                _ => self.general,
+            },
+            PoolId::V3(_) => self.v3,
+        }
+    }
+}
 
+impl Default for GasPerSwap {
+    fn default() -> Self {
+        Self {
+            general: Self::DEFAULT,
+            minimal_swap_info: Self::DEFAULT,
+            two_token: Self::DEFAULT,
+            v3: Self::DEFAULT,
+        }
+    }
+}
+
+impl Sor {
     pub fn new(config: Config) -> Result<Self, Error> {
+        let recipient = match config.recipient {
+            Some(recipient) if recipient.is_zero() => return Err(Error::InvalidRecipient),
+            Some(recipient) => recipient,
+            None => config.settlement.0,
+        };
         Ok(Self {
-            client: super::Client::new(Default::default(), config.block_stream),
+            client: match config.shared_client {
+                Some(client) => {
+                    super::Client::shared(client, config.request_timeout, config.block_stream)
+                }
+                None => super::Client::new(
+                    reqwest::Client::builder()
+                        .redirect(super::redirect_policy(config.follow_redirects)),
+                    config.connect_timeout,
+                    config.request_timeout,
+                    config.block_stream,
+                )
+                .map_err(|err| Error::Http(err.into()))?,
+            }
+            .with_rate_limit(config.rate_limit),
+            retry: config.retry.unwrap_or_else(util::http::RetryPolicy::none),
             endpoint: config.endpoint,
             v2_vault: v2::Vault::new(config.vault),
             v3_batch_router: v3::Router::new(config.v3_batch_router),
@@ -82,6 +219,15 @@ impl Sor {
             settlement: config.settlement,
             chain_id: Chain::from_domain(config.chain_id)?,
             query_batch_swap: config.query_batch_swap,
+            swap_deadline: config.swap_deadline,
+            deprecated_pools: config.deprecated_pools,
+            reject_deprecated_pools: config.reject_deprecated_pools,
+            gas_per_swap: config.gas_per_swap,
+            protocol_version: config.protocol_version,
+            use_internal_balance_in: config.use_internal_balance_in,
+            use_internal_balance_out: config.use_internal_balance_out,
+            recipient,
+            decimals: config.decimals.map(super::Decimals::new),
         })
     }
 
@@ -91,34 +237,55 @@ impl Sor {
         slippage: &dex::Slippage,
         tokens: &auction::Tokens,
     ) -> Result<dex::Swap, Error> {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("system time after Unix epoch");
         let query = dto::Query::from_domain(
             order,
-            tokens,
+            self.decimals(order, tokens).await?,
             slippage,
             self.chain_id,
             self.settlement,
             self.query_batch_swap,
-            // 2 minutes from now
-            chrono::Utc::now()
-                .add(Duration::from_secs(120))
-                .timestamp()
-                .to_u64(),
-        )?;
+            Some(query_deadline(self.swap_deadline, now)),
+        )
+        .with_protocol_version(self.protocol_version);
         let quote = {
             // Set up a tracing span to make debugging of API requests easier.
             // Historically, debugging API requests to external DEXs was a bit
             // of a headache.
             static ID: AtomicU64 = AtomicU64::new(0);
             let id = ID.fetch_add(1, atomic::Ordering::Relaxed);
-            self.quote(&query)
+            match self
+                .quote(&query)
                 .instrument(tracing::trace_span!("quote", id = %id))
-                .await?
+                .await
+            {
+                Err(Error::QueryReverted { reason }) => {
+                    tracing::warn!(
+                        %reason,
+                        sell = ?order.sell,
+                        buy = ?order.buy,
+                        "SOR queryBatchSwap reverted"
+                    );
+                    return Err(Error::QueryReverted { reason });
+                }
+                result => result?,
+            }
         };
 
         if quote.is_empty() {
             return Err(Error::NotFound);
         }
 
+        let deprecated = deprecated_pools(&quote, &self.deprecated_pools);
+        if !deprecated.is_empty() {
+            if self.reject_deprecated_pools {
+                return Err(Error::DeprecatedPool(deprecated.join(", ")));
+            }
+            tracing::warn!(?deprecated, "route crosses deprecated pools");
+        }
+
         let (input, output) = match order.side {
             order::Side::Buy => (quote.return_amount_raw, quote.swap_amount_raw),
             order::Side::Sell => (quote.swap_amount_raw, quote.return_amount_raw),
@@ -129,18 +296,19 @@ impl Sor {
             order::Side::Sell => (input, slippage.sub(output)),
         };
 
-        let gas = U256::from(quote.swaps.len()) * Self::GAS_PER_SWAP;
+        let gas = U256::from(self.gas_per_swap.route(&quote));
+        let deadline = swap_deadline(self.swap_deadline, now);
         let (spender, calls) = match quote.protocol_version {
             dto::ProtocolVersion::V2 => (
                 self.v2_vault.address(),
-                self.encode_v2_swap(order, &quote, max_input, min_output)?,
+                self.encode_v2_swap(order, &quote, max_input, min_output, deadline)?,
             ),
             dto::ProtocolVersion::V3 => {
                 // In Balancer v3, the spender must be the Permit2 contract, as it's the one
                 // doing the transfer of funds from the settlement
                 (
                     self.permit2.address(),
-                    self.encode_v3_swap(order, &quote, max_input)?,
+                    self.encode_v3_swap(order, &quote, max_input, deadline)?,
                 )
             }
         };
@@ -160,15 +328,41 @@ impl Sor {
                 amount: dex::Amount::new(max_input),
             },
             gas: eth::Gas(gas),
+            metadata: dex::Metadata {
+                warnings: deprecated
+                    .into_iter()
+                    .map(|pool| format!("route crosses deprecated pool {pool}"))
+                    .collect(),
+                gas_confidence: dex::GasConfidence::Heuristic,
+                expires_at: self
+                    .swap_deadline
+                    .and_then(|_| chrono::DateTime::from_timestamp(deadline.low_u64() as i64, 0)),
+                ..Default::default()
+            },
         })
     }
 
+    /// Returns the decimals of the token of the swapped amount, from the
+    /// auction or fetched on-chain if they are missing from it.
+    async fn decimals(&self, order: &dex::Order, tokens: &auction::Tokens) -> Result<u8, Error> {
+        let token = order.amount().token;
+        if let Some(decimals) = tokens.decimals(&token) {
+            return Ok(decimals);
+        }
+        match &self.decimals {
+            Some(decimals) => decimals.get(token).await,
+            None => None,
+        }
+        .ok_or(Error::MissingDecimals(token))
+    }
+
     fn encode_v2_swap(
         &self,
         order: &dex::Order,
         quote: &dto::Quote,
         max_input: U256,
         min_output: U256,
+        deadline: U256,
     ) -> Result<Vec<dex::Call>, Error> {
         let kind = match order.side {
             order::Side::Sell => v2::SwapKind::GivenIn,
@@ -190,9 +384,9 @@ impl Sor {
         let assets = quote.token_addresses.clone();
         let funds = v2::Funds {
             sender: self.settlement.0,
-            from_internal_balance: false,
-            recipient: self.settlement.0,
-            to_internal_balance: false,
+            from_internal_balance: self.use_internal_balance_in,
+            recipient: self.recipient,
+            to_internal_balance: self.use_internal_balance_out,
         };
         let limits = quote
             .token_addresses
@@ -213,7 +407,9 @@ impl Sor {
             })
             .collect();
 
-        Ok(self.v2_vault.batch_swap(kind, swaps, assets, funds, limits))
+        Ok(self
+            .v2_vault
+            .batch_swap(kind, swaps, assets, funds, limits, deadline))
     }
 
     fn encode_v3_swap(
@@ -221,6 +417,7 @@ impl Sor {
         order: &dex::Order,
         quote: &dto::Quote,
         max_input: U256,
+        deadline: U256,
     ) -> Result<Vec<dex::Call>, Error> {
         let paths = quote
             .paths
@@ -261,28 +458,65 @@ impl Sor {
                 &self.permit2,
                 quote.token_in,
                 max_input,
+                deadline,
             ),
             Side::Sell => self.v3_batch_router.swap_exact_amount_in(
                 paths,
                 &self.permit2,
                 quote.token_in,
                 max_input,
+                deadline,
             ),
         })
     }
 
     async fn quote(&self, query: &dto::Query<'_>) -> Result<dto::Quote, Error> {
-        let response = util::http::roundtrip!(
-            <dto::GetSwapPathsResponse, util::serialize::Never>;
-            self.client
-                .request(reqwest::Method::POST, self.endpoint.clone())
-                .json(query)
+        let response = util::http::roundtrip_retry!(
+            <dto::GetSwapPathsResponse, dto::ErrorResponse>;
+            &self.retry,
+            || async move {
+                self.client
+                    .request(reqwest::Method::POST, self.endpoint.clone())
+                    .await
+                    .json(query)
+            }
         )
         .await?;
         Ok(response.data.sor_get_swap_paths)
     }
 }
 
+/// Computes the deadline timestamp sent to the SOR API, which it uses for the
+/// swap calldata it builds. Without a validity window, it defaults to 2 minutes
+/// from now.
+fn query_deadline(window: Option<Duration>, now: Duration) -> u64 {
+    const DEFAULT_WINDOW: Duration = Duration::from_secs(120);
+    now.saturating_add(window.unwrap_or(DEFAULT_WINDOW))
+        .as_secs()
+}
+
+/// Computes the on-chain swap deadline for the optional validity window at the
+/// specified time since the Unix epoch. Without a window, the swaps should
+/// never expire, since the settlement itself is only valid for a short period
+/// of time anyway. In that case, this returns a value that is sufficiently
+/// large (the Vault compares it against the block timestamp) with as many 0's
+/// as possible for some small gas savings on calldata (i.e. b1000...0000).
+fn swap_deadline(window: Option<Duration>, now: Duration) -> U256 {
+    match window {
+        Some(window) => U256::from(now.saturating_add(window).as_secs()),
+        None => U256::one() << 255,
+    }
+}
+
+/// Returns the deprecated pools that the quoted route goes through.
+fn deprecated_pools(quote: &dto::Quote, deprecated: &[PoolId]) -> Vec<String> {
+    quote
+        .pools()
+        .filter(|pool| deprecated.contains(pool))
+        .map(ToString::to_string)
+        .collect()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("no valid swap interaction could be found")]
@@ -297,19 +531,201 @@ pub enum Error {
     MissingDecimals(TokenAddress),
     #[error("invalid pool id format")]
     InvalidPoolIdFormat,
+    #[error("route crosses deprecated pools: {0}")]
+    DeprecatedPool(String),
     #[error("invalid path")]
     InvalidPath,
+    #[error("swap recipient must not be the zero address")]
+    InvalidRecipient,
+    #[error("queryBatchSwap reverted: {reason}")]
+    QueryReverted { reason: String },
+    #[error("SOR API error: {0}")]
+    Api(String),
 }
 
-impl From<util::http::RoundtripError<util::serialize::Never>> for Error {
-    fn from(err: util::http::RoundtripError<util::serialize::Never>) -> Self {
+impl From<util::http::RoundtripError<dto::ErrorResponse>> for Error {
+    fn from(err: util::http::RoundtripError<dto::ErrorResponse>) -> Self {
         match err {
-            util::http::RoundtripError::Http(util::http::Error::Status(status_code, _))
-                if status_code.as_u16() == 429 =>
-            {
+            util::http::RoundtripError::Http(util::http::Error::TooManyRequests { .. }) => {
                 Self::RateLimited
             }
-            other_err => Self::Http(other_err.into()),
+            util::http::RoundtripError::Http(err) => Self::Http(err),
+            util::http::RoundtripError::Api(err) => match err.revert_reason() {
+                Some(reason) => Self::QueryReverted { reason },
+                None => Self::Api(err.to_string()),
+            },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, ethereum_types::H160};
+
+    #[test]
+    fn swap_deadline_from_window() {
+        let now = Duration::from_secs(1_700_000_000);
+
+        assert_eq!(
+            swap_deadline(Some(Duration::from_secs(60)), now),
+            U256::from(1_700_000_060)
+        );
+        assert_eq!(swap_deadline(None, now), U256::one() << 255);
+        assert_eq!(
+            query_deadline(Some(Duration::from_secs(60)), now),
+            1_700_000_060
+        );
+        assert_eq!(query_deadline(None, now), 1_700_000_120);
+    }
+
+    #[test]
+    fn gas_by_pool_specialization() {
+        let v2 = |specialization: u8| {
+            let mut id = [0x01; 32];
+            id[20..22].copy_from_slice(&[0, specialization]);
+            PoolId::V2(ethereum_types::H256(id))
+        };
+        let quote = |pools: Vec<PoolId>| dto::Quote {
+            swaps: pools
+                .into_iter()
+                .map(|pool_id| dto::Swap {
+                    pool_id,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let gas = GasPerSwap {
+            general: 100,
+            minimal_swap_info: 10,
+            two_token: 1,
+            v3: 1_000,
+        };
+
+        assert_eq!(gas.route(&quote(vec![v2(0), v2(1), v2(2)])), 111);
+        assert_eq!(
+            gas.route(&quote(vec![PoolId::V3(Default::default()), v2(2)])),
+            1_001
+        );
+        assert_eq!(
+            GasPerSwap::default().route(&quote(vec![v2(0), v2(2)])),
+            2 * GasPerSwap::DEFAULT
+        );
+    }
+
+    fn config() -> Config {
+        Config {
+            block_stream: None,
+            follow_redirects: false,
+            rate_limit: None,
+            connect_timeout: crate::infra::dex::DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: crate::infra::dex::DEFAULT_REQUEST_TIMEOUT,
+            shared_client: None,
+            retry: None,
+            endpoint: "https://api.balancer.fi/graphql".parse().unwrap(),
+            vault: eth::ContractAddress(H160([0x01; 20])),
+            v3_batch_router: eth::ContractAddress(H160([0x02; 20])),
+            permit2: eth::ContractAddress(H160([0x03; 20])),
+            settlement: eth::ContractAddress(H160([0x04; 20])),
+            chain_id: eth::ChainId::Mainnet,
+            query_batch_swap: false,
+            swap_deadline: None,
+            deprecated_pools: Default::default(),
+            reject_deprecated_pools: false,
+            gas_per_swap: Default::default(),
+            protocol_version: None,
+            use_internal_balance_in: false,
+            use_internal_balance_out: false,
+            recipient: None,
+            decimals: None,
+        }
+    }
+
+    #[test]
+    fn recipient_defaults_to_settlement() {
+        assert_eq!(Sor::new(config()).unwrap().recipient, H160([0x04; 20]));
+        assert_eq!(
+            Sor::new(Config {
+                recipient: Some(H160([0x42; 20])),
+                ..config()
+            })
+            .unwrap()
+            .recipient,
+            H160([0x42; 20])
+        );
+        assert!(matches!(
+            Sor::new(Config {
+                recipient: Some(H160::zero()),
+                ..config()
+            }),
+            Err(Error::InvalidRecipient)
+        ));
+    }
+
+    #[test]
+    fn passes_internal_balance_flags_to_batch_swap() {
+        let encode = |use_internal_balance_in, use_internal_balance_out| {
+            let sor = Sor::new(Config {
+                use_internal_balance_in,
+                use_internal_balance_out,
+                ..config()
+            })
+            .unwrap();
+            let order = dex::Order {
+                sell: eth::TokenAddress(H160([0x05; 20])),
+                buy: eth::TokenAddress(H160([0x06; 20])),
+                side: order::Side::Sell,
+                amount: dex::Amount::new(U256::exp10(18)),
+                owner: H160([0x07; 20]),
+                partially_fillable: false,
+                fill_ratio: None,
+                slippage: None,
+            };
+            let quote = dto::Quote {
+                swaps: vec![dto::Swap {
+                    pool_id: PoolId::V2(ethereum_types::H256([0x08; 32])),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+            let calls = sor
+                .encode_v2_swap(&order, &quote, U256::exp10(18), U256::one(), U256::one())
+                .unwrap();
+
+            // The `FundManagement` tuple is encoded in place after the
+            // selector and the swap kind, swaps and assets head words. The
+            // boolean flags are its second and fourth words.
+            let flag = |word: usize| calls[0].calldata[4 + 32 * (3 + word) + 31] != 0;
+            (flag(1), flag(3))
+        };
+
+        assert_eq!(encode(false, false), (false, false));
+        assert_eq!(encode(true, false), (true, false));
+        assert_eq!(encode(false, true), (false, true));
+        assert_eq!(encode(true, true), (true, true));
+    }
+
+    #[test]
+    fn flags_deprecated_pools() {
+        let deprecated = PoolId::V2(ethereum_types::H256([0x42; 32]));
+        let quote = dto::Quote {
+            swaps: vec![
+                dto::Swap {
+                    pool_id: PoolId::V2(ethereum_types::H256([0x01; 32])),
+                    ..Default::default()
+                },
+                dto::Swap {
+                    pool_id: PoolId::V2(ethereum_types::H256([0x42; 32])),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            deprecated_pools(&quote, &[deprecated]),
+            vec![format!("0x{}", "42".repeat(32))],
+        );
+        assert!(deprecated_pools(&quote, &[]).is_empty());
+    }
+}
@@ -50,11 +50,11 @@ impl Vault {
         assets: Vec<H160>,
         funds: Funds,
         limits: Vec<I256>,
+        deadline: U256,
     ) -> Vec<dex::Call> {
-        vec![dex::Call {
-            to: self.address(),
-            calldata: self
-                .0
+        vec![dex::Call::swap(
+            self.address(),
+            self.0
                 .methods()
                 .batch_swap(
                     kind as _,
@@ -78,14 +78,12 @@ impl Vault {
                         funds.to_internal_balance,
                     ),
                     limits,
-                    // `deadline`: Sufficiently large value with as many 0's as possible for some
-                    // small gas savings.
-                    U256::one() << 255,
+                    deadline,
                 )
                 .tx
                 .data
                 .expect("calldata")
                 .0,
-        }]
+        )]
     }
 }
@@ -44,7 +44,11 @@ impl Permit2 {
         let mut calldata = call.tx.data.unwrap().0;
         calldata.extend_from_slice(&[0u8; 24]);
 
-        dex::Call { to, calldata }
+        dex::Call {
+            to,
+            calldata,
+            purpose: dex::Purpose::Approval,
+        }
     }
 }
 
@@ -80,23 +84,21 @@ impl Router {
         permit2: &Permit2,
         token_in: H160,
         max_input: U256,
+        deadline: U256,
     ) -> Vec<dex::Call> {
         let permit2_approval_call =
             permit2.create_approval_call(self.address().0, token_in, max_input);
 
         let swap_call = self.0.swap_exact_in(
             Self::encode_paths(paths),
-            Self::deadline(),
+            deadline,
             Self::weth_is_eth(),
             Self::user_data(),
         );
 
         vec![
             permit2_approval_call,
-            dex::Call {
-                to: self.address(),
-                calldata: swap_call.tx.data.unwrap().0,
-            },
+            dex::Call::swap(self.address(), swap_call.tx.data.unwrap().0),
         ]
     }
 
@@ -106,23 +108,21 @@ impl Router {
         permit2: &Permit2,
         token_in: H160,
         max_input: U256,
+        deadline: U256,
     ) -> Vec<dex::Call> {
         let permit2_approval_call =
             permit2.create_approval_call(self.address().0, token_in, max_input);
 
         let swap_call = self.0.swap_exact_out(
             Self::encode_paths(paths),
-            Self::deadline(),
+            deadline,
             Self::weth_is_eth(),
             Self::user_data(),
         );
 
         vec![
             permit2_approval_call,
-            dex::Call {
-                to: self.address(),
-                calldata: swap_call.tx.data.unwrap().0,
-            },
+            dex::Call::swap(self.address(), swap_call.tx.data.unwrap().0),
         ]
     }
 
@@ -146,12 +146,6 @@ impl Router {
             .collect()
     }
 
-    /// Returns a `deadline` value that is sufficiently large with as many 0's
-    /// as possible for some small gas savings (i.e. b1000...0000).
-    fn deadline() -> U256 {
-        U256::one() << 255
-    }
-
     /// Returns value for the `wethIsEth` argument. If that is true, incoming
     /// ETH will be wrapped to WETH and outgoing WETH will be unwrapped to
     /// ETH. Since the settlement contract only works with WETH we don't
@@ -0,0 +1,178 @@
+use {
+    crate::infra,
+    std::{
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Stops sending requests to a DEX API that keeps failing, so that a downed
+/// upstream does not slow down the whole auction with piled up timeouts.
+///
+/// The breaker opens after a number of consecutive failures within a window
+/// and rejects all requests until a cooldown elapses. Afterwards, a single
+/// probe request is let through: if it succeeds the breaker closes again,
+/// otherwise it stays open for another cooldown.
+pub struct CircuitBreaker {
+    name: &'static str,
+    config: Config,
+    state: Mutex<State>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    /// The number of consecutive failures that opens the breaker.
+    pub failures: u32,
+    /// The time within which the consecutive failures have to occur.
+    pub window: Duration,
+    /// How long the breaker stays open before probing the DEX API again.
+    pub cooldown: Duration,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed {
+        failures: u32,
+        /// When the first of the consecutive failures occurred.
+        since: Option<Instant>,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A probe request is in flight. Should it never complete (e.g. because
+    /// its future got dropped), another probe is allowed after a cooldown.
+    Probing {
+        until: Instant,
+    },
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, config: Config) -> Self {
+        assert!(config.failures > 0, "circuit breaker must allow failures");
+        Self {
+            name,
+            config,
+            state: Mutex::new(State::Closed {
+                failures: 0,
+                since: None,
+            }),
+        }
+    }
+
+    /// Returns whether a request may be sent to the DEX API. The outcome of
+    /// permitted requests must be reported with [`Self::record`].
+    pub fn permit(&self) -> bool {
+        self.permit_at(Instant::now())
+    }
+
+    /// Reports the outcome of a permitted request.
+    pub fn record(&self, success: bool) {
+        self.record_at(success, Instant::now())
+    }
+
+    fn permit_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } | State::Probing { until } if now >= until => {
+                tracing::debug!(dex = self.name, "probing DEX API");
+                *state = State::Probing {
+                    until: now + self.config.cooldown,
+                };
+                true
+            }
+            State::Open { .. } | State::Probing { .. } => false,
+        }
+    }
+
+    fn record_at(&self, success: bool, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        match (&*state, success) {
+            (State::Closed { .. }, true) => {
+                *state = State::Closed {
+                    failures: 0,
+                    since: None,
+                };
+            }
+            (State::Closed { failures, since }, false) => {
+                let (failures, since) = match since {
+                    Some(since) if now.saturating_duration_since(*since) <= self.config.window => {
+                        (failures + 1, *since)
+                    }
+                    _ => (1, now),
+                };
+                if failures >= self.config.failures {
+                    tracing::warn!(dex = self.name, failures, "circuit breaker opened");
+                    infra::metrics::circuit_breaker(self.name, true);
+                    *state = State::Open {
+                        until: now + self.config.cooldown,
+                    };
+                } else {
+                    *state = State::Closed {
+                        failures,
+                        since: Some(since),
+                    };
+                }
+            }
+            (State::Probing { .. }, true) => {
+                tracing::info!(dex = self.name, "circuit breaker closed");
+                infra::metrics::circuit_breaker(self.name, false);
+                *state = State::Closed {
+                    failures: 0,
+                    since: None,
+                };
+            }
+            (State::Probing { .. }, false) => {
+                tracing::debug!(dex = self.name, "probe failed; circuit breaker stays open");
+                *state = State::Open {
+                    until: now + self.config.cooldown,
+                };
+            }
+            // Outcomes of requests that were sent before the breaker opened.
+            (State::Open { .. }, _) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures_and_probes_after_cooldown() {
+        let breaker = CircuitBreaker::new(
+            "test",
+            Config {
+                failures: 2,
+                window: Duration::from_secs(10),
+                cooldown: Duration::from_secs(30),
+            },
+        );
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // Failures outside of the window are not consecutive.
+        breaker.record_at(false, at(0));
+        breaker.record_at(false, at(11));
+        assert!(breaker.permit_at(at(11)));
+        // A success resets the failures.
+        breaker.record_at(true, at(12));
+        breaker.record_at(false, at(13));
+        assert!(breaker.permit_at(at(13)));
+
+        breaker.record_at(false, at(14));
+        assert!(!breaker.permit_at(at(15)));
+        assert!(!breaker.permit_at(at(43)));
+
+        // Only a single probe is let through after the cooldown.
+        assert!(breaker.permit_at(at(44)));
+        assert!(!breaker.permit_at(at(44)));
+        breaker.record_at(false, at(45));
+        assert!(!breaker.permit_at(at(46)));
+
+        assert!(breaker.permit_at(at(75)));
+        breaker.record_at(true, at(76));
+        assert!(breaker.permit_at(at(76)));
+        assert!(breaker.permit_at(at(76)));
+    }
+}
@@ -0,0 +1,58 @@
+use {
+    crate::{
+        domain::eth,
+        infra::multicall::{self, Multicall},
+    },
+    moka::future::Cache,
+    std::{sync::Arc, time::Duration},
+};
+
+/// Fetches the decimals of tokens that are missing from the auction on-chain,
+/// caching them to cut down on RPC requests. Concurrent lookups (e.g. for the
+/// orders of an auction) are batched into a single RPC request.
+pub struct Decimals {
+    multicall: Multicall,
+    cache: Cache<eth::TokenAddress, u8>,
+}
+
+pub struct Config {
+    /// The batched calls to fetch token decimals with.
+    pub multicall: Multicall,
+    /// How long fetched decimals are cached for.
+    pub ttl: Duration,
+    /// The maximum number of tokens to cache decimals for.
+    pub max_size: u64,
+}
+
+impl Decimals {
+    pub fn new(config: Config) -> Self {
+        Self {
+            multicall: config.multicall,
+            cache: Cache::builder()
+                .max_capacity(config.max_size)
+                .time_to_live(config.ttl)
+                .build(),
+        }
+    }
+
+    /// Returns the decimals of the token, or `None` if they can't be fetched
+    /// (e.g. because the token does not implement the optional `decimals`
+    /// method). Failed lookups are not cached.
+    pub async fn get(&self, token: eth::TokenAddress) -> Option<u8> {
+        self.cache
+            .try_get_with(token, async {
+                let data = self
+                    .multicall
+                    .call(multicall::Call::decimals(token), None)
+                    .await?;
+                multicall::decode_uint(&data)
+                    .and_then(|decimals| u8::try_from(decimals).ok())
+                    .ok_or(multicall::Error::InvalidReturnData)
+            })
+            .await
+            .inspect_err(|err: &Arc<multicall::Error>| {
+                tracing::debug!(?token, ?err, "failed to fetch token decimals");
+            })
+            .ok()
+    }
+}
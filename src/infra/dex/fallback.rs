@@ -0,0 +1,189 @@
+//! A meta DEX that tries several DEX APIs in a fixed order of priority and
+//! uses the first one that provides a swap.
+
+use {
+    super::{backend, multi, Backend, Dex, Error},
+    crate::{
+        domain::{auction, dex},
+        infra,
+    },
+    std::time::Duration,
+};
+
+/// Tries its backends one after the other for every order, and returns the
+/// swap of the first backend that succeeds. Later backends are only asked if
+/// all earlier ones failed to quote the order, see [`advances`].
+pub struct FallbackDex {
+    backends: Vec<Backend>,
+    request_timeout: Duration,
+}
+
+impl FallbackDex {
+    /// Creates a new fallback chain, in order of priority. Backends that take
+    /// longer than the request timeout to quote an order, or whose swap fails
+    /// the checks of the backend config, are skipped for that order.
+    pub fn new(backends: Vec<Dex>, request_timeout: Duration, backend: backend::Config) -> Self {
+        Self {
+            backends: backends
+                .into_iter()
+                .map(|dex| Backend::new(dex, backend.clone()))
+                .collect(),
+            request_timeout,
+        }
+    }
+
+    pub async fn swap(
+        &self,
+        order: &dex::Order,
+        slippage: &dex::Slippage,
+        tokens: &auction::Tokens,
+        gas_price: auction::GasPrice,
+    ) -> Result<dex::Swap, Error> {
+        let mut errors = Vec::new();
+        for backend in &self.backends {
+            let swap = backend.swap(order, slippage, tokens, gas_price);
+            match tokio::time::timeout(self.request_timeout, swap)
+                .await
+                .unwrap_or(Err(Error::Timeout))
+            {
+                Ok(swap) => {
+                    tracing::debug!(backend = backend.name(), "served swap");
+                    infra::metrics::fallback_served(backend.name());
+                    return Ok(swap);
+                }
+                Err(err) if advances(&err) => {
+                    tracing::debug!(backend = backend.name(), ?err, "falling back");
+                    errors.push(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(multi::combine(errors))
+    }
+}
+
+/// Whether the next backend should be tried after an error. This is the case
+/// if the backend can't quote the order (or only with an unusable swap) or is
+/// failing, but not if the order itself is at fault, since no other backend
+/// would do any better.
+fn advances(err: &Error) -> bool {
+    match err {
+        Error::OrderNotSupported
+        | Error::NotFound
+        | Error::InsufficientLiquidity
+        | Error::CalldataTooLarge(_)
+        | Error::PriceDeviationTooHigh
+        | Error::RateLimited { .. }
+        | Error::Timeout
+        | Error::Unavailable
+        | Error::Transient(_)
+        | Error::Other(_) => true,
+        Error::InvalidOrder(_)
+        | Error::InvalidToken(_)
+        | Error::OrderTooLarge
+        | Error::SlippageTooHigh
+        | Error::UnavailableForLegalReasons => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{domain::eth, infra::dex::replay},
+        ethereum_types::U256,
+        std::fs,
+    };
+
+    #[test]
+    fn advances_unless_order_is_at_fault() {
+        assert!(advances(&Error::NotFound));
+        assert!(advances(&Error::RateLimited { retry_after: None }));
+        assert!(advances(&Error::OrderNotSupported));
+        assert!(!advances(&Error::InvalidToken(eth::TokenAddress(
+            eth::H160::zero()
+        ))));
+        assert!(!advances(&Error::OrderTooLarge));
+    }
+
+    /// A backend replaying a swap with the given calldata, or no swap at all.
+    fn replay(calldata: Option<&str>) -> Dex {
+        let fixtures = tempfile::tempdir().unwrap();
+        if let Some(calldata) = calldata {
+            fs::write(
+                fixtures.path().join("swap.json"),
+                format!(
+                    r#"{{
+                        "order": {{
+                            "sellToken": "0x0101010101010101010101010101010101010101",
+                            "buyToken": "0x0202020202020202020202020202020202020202",
+                            "side": "sell",
+                            "amount": "1000"
+                        }},
+                        "swap": {{
+                            "calls": [{{ "to": "0x0303030303030303030303030303030303030303", "calldata": "{calldata}" }}],
+                            "inputAmount": "1000",
+                            "outputAmount": "2000",
+                            "spender": "0x0303030303030303030303030303030303030303",
+                            "allowance": "1000",
+                            "gas": "100000"
+                        }}
+                    }}"#
+                ),
+            )
+            .unwrap();
+        }
+        Dex::Replay(
+            replay::Replay::new(replay::Config {
+                fixtures: fixtures.path().to_owned(),
+            })
+            .unwrap(),
+        )
+    }
+
+    fn fallback(backends: Vec<Dex>, max_calldata_bytes: usize) -> FallbackDex {
+        FallbackDex::new(
+            backends,
+            Duration::from_secs(1),
+            backend::Config {
+                max_calldata_bytes,
+                circuit_breaker: None,
+            },
+        )
+    }
+
+    async fn swap(fallback: &FallbackDex) -> Result<dex::Swap, Error> {
+        fallback
+            .swap(
+                &dex::testing::order(1_000),
+                &dex::Slippage::one_percent(),
+                &auction::Tokens(Default::default()),
+                auction::GasPrice(eth::Ether(U256::one())),
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn tries_backends_in_order() {
+        let first = fallback(
+            vec![replay(None), replay(Some("0x0102")), replay(Some("0x0304"))],
+            usize::MAX,
+        );
+        assert_eq!(swap(&first).await.unwrap().calls[0].calldata, [0x01, 0x02]);
+
+        let none = fallback(vec![replay(None), replay(None)], usize::MAX);
+        assert!(matches!(swap(&none).await, Err(Error::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_swaps_with_too_much_calldata() {
+        let fallback = fallback(
+            vec![replay(Some("0x0102030405")), replay(Some("0x0102"))],
+            4,
+        );
+        assert_eq!(
+            swap(&fallback).await.unwrap().calls[0].calldata,
+            [0x01, 0x02]
+        );
+    }
+}
@@ -1,25 +1,67 @@
 use {
-    crate::domain::{auction, dex},
+    crate::{
+        domain::{auction, dex, eth},
+        infra,
+    },
     ethrpc::block_stream::CurrentBlockWatcher,
     reqwest::RequestBuilder,
+    std::{
+        collections::HashSet,
+        sync::{
+            atomic::{self, AtomicUsize},
+            Arc,
+        },
+        time::Duration,
+    },
 };
 
+mod allowances;
+pub mod backend;
 pub mod balancer;
+pub mod breaker;
+pub mod decimals;
+pub mod fallback;
+pub mod multi;
 pub mod okx;
 pub mod oneinch;
 pub mod paraswap;
+mod quote_cache;
+pub mod replay;
 pub mod simulator;
+mod single_flight;
+pub mod sources;
+mod throttle;
+pub mod token_code;
+pub mod weth;
 pub mod zeroex;
 
-pub use self::simulator::Simulator;
+pub use self::{
+    allowances::Allowances,
+    backend::Backend,
+    decimals::Decimals,
+    quote_cache::QuoteCache,
+    simulator::Simulator,
+    single_flight::SingleFlight,
+    throttle::RateLimit,
+    token_code::TokenCode,
+    weth::Weth,
+};
 
 /// A supported external DEX/DEX aggregator API.
+///
+/// This is the uniform interface over all backends: it hides their differing
+/// `swap` signatures and maps their errors into the common [`Error`], so that
+/// configured backends can be stored and iterated over together. The concrete
+/// clients remain usable on their own.
 pub enum Dex {
     Balancer(balancer::Sor),
     OneInch(oneinch::OneInch),
     ZeroEx(zeroex::ZeroEx),
     ParaSwap(paraswap::ParaSwap),
     Okx(okx::Okx),
+    Replay(replay::Replay),
+    Multi(multi::MultiDex),
+    Fallback(fallback::FallbackDex),
 }
 
 impl Dex {
@@ -32,55 +74,277 @@ impl Dex {
         order: &dex::Order,
         slippage: &dex::Slippage,
         tokens: &auction::Tokens,
+        gas_price: auction::GasPrice,
     ) -> Result<dex::Swap, Error> {
-        let swap = match self {
-            Dex::Balancer(balancer) => balancer.swap(order, slippage, tokens).await?,
-            Dex::OneInch(oneinch) => oneinch.swap(order, slippage).await?,
-            Dex::ZeroEx(zeroex) => zeroex.swap(order, slippage).await?,
-            Dex::ParaSwap(paraswap) => paraswap.swap(order, slippage, tokens).await?,
-            Dex::Okx(okx) => okx.swap(order, slippage).await?,
-        };
+        validate(order)?;
+        // The meta DEXs only fan out to their backends, which count themselves.
+        let _in_flight = (!self.is_meta()).then(InFlight::start);
+        let start = std::time::Instant::now();
+        let swap: Result<dex::Swap, Error> = async {
+            Ok(match self {
+                Dex::Balancer(balancer) => balancer.swap(order, slippage, tokens).await?,
+                Dex::OneInch(oneinch) => oneinch.swap(order, slippage, tokens, gas_price).await?,
+                Dex::ZeroEx(zeroex) => zeroex.swap(order, slippage, gas_price).await?,
+                Dex::ParaSwap(paraswap) => paraswap.swap(order, slippage, tokens).await?,
+                Dex::Okx(okx) => okx.swap(order, slippage).await?,
+                Dex::Replay(replay) => replay.swap(order)?,
+                // Boxed, since the meta DEXs in turn swap with their backends.
+                Dex::Multi(multi) => {
+                    Box::pin(multi.swap(order, slippage, tokens, gas_price)).await?
+                }
+                Dex::Fallback(fallback) => {
+                    Box::pin(fallback.swap(order, slippage, tokens, gas_price)).await?
+                }
+            })
+        }
+        .await;
+        let elapsed = start.elapsed();
+        infra::metrics::swap(
+            self.name(),
+            order.side,
+            elapsed,
+            match &swap {
+                Ok(_) => "Success",
+                Err(err) => err.format_variant(),
+            },
+        );
+        let mut swap = swap?;
+        swap.metadata.upstream_latency_ms =
+            Some(elapsed.as_millis().try_into().unwrap_or(u64::MAX));
         Ok(swap)
     }
+
+    /// A short name of the DEX API for logging.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dex::Balancer(_) => "balancer",
+            Dex::OneInch(_) => "oneinch",
+            Dex::ZeroEx(_) => "zeroex",
+            Dex::ParaSwap(_) => "paraswap",
+            Dex::Okx(_) => "okx",
+            Dex::Replay(_) => "replay",
+            Dex::Multi(_) => "multi",
+            Dex::Fallback(_) => "fallback",
+        }
+    }
+
+    /// Whether this is a meta DEX that requests swaps from other DEX APIs
+    /// instead of a DEX API of its own.
+    fn is_meta(&self) -> bool {
+        matches!(self, Dex::Multi(_) | Dex::Fallback(_))
+    }
+}
+
+/// The number of DEX API requests that are currently in flight.
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// Tracks a DEX API request as in flight for as long as it is alive, even if
+/// the request future gets dropped early.
+struct InFlight;
+
+impl InFlight {
+    fn start() -> Self {
+        let in_flight = IN_FLIGHT.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+        infra::metrics::request_started(in_flight);
+        Self
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, atomic::Ordering::Relaxed);
+    }
+}
+
+/// Returns the first call target or allowance spender of a swap that is not
+/// in the allowlist, as a defense against faulty or compromised DEX API
+/// responses. All targets are allowed without an allowlist.
+fn disallowed_target(
+    swap: &dex::Swap,
+    allowed_targets: Option<&HashSet<eth::ContractAddress>>,
+) -> Option<eth::ContractAddress> {
+    swap.calls
+        .iter()
+        .inspect(|call| tracing::debug!(to = ?call.to.0, "swap call target"))
+        .map(|call| call.to)
+        .chain([swap.allowance.spender])
+        .find(|to| allowed_targets.is_some_and(|allowed| !allowed.contains(to)))
+}
+
+/// Rejects orders that no DEX can sensibly quote, before wasting an API
+/// request on them.
+fn validate(order: &dex::Order) -> Result<(), Error> {
+    if order.sell == order.buy {
+        return Err(Error::InvalidOrder("sell and buy tokens are the same"));
+    }
+    if order.amount.get().is_zero() {
+        return Err(Error::InvalidOrder("amount is zero"));
+    }
+    Ok(())
 }
 
 /// A categorized error that occurred building a swap with an external DEX/DEX
 /// aggregator.
-#[derive(Debug, thiserror::Error)]
+#[derive(Clone, Debug, thiserror::Error)]
 pub enum Error {
     #[error("order type is not supported")]
     OrderNotSupported,
+    #[error("invalid order: {0}")]
+    InvalidOrder(&'static str),
     #[error("no valid swap interaction could be found")]
     NotFound,
+    #[error("not enough liquidity to swap the order amount")]
+    InsufficientLiquidity,
+    /// The DEX API is rate limiting requests, and may have specified how long
+    /// to wait before sending the next one in a `Retry-After` header.
     #[error("rate limited")]
-    RateLimited,
+    RateLimited { retry_after: Option<Duration> },
     #[error("unavailable for legal reasons, banned tokens or similar")]
     UnavailableForLegalReasons,
+    #[error("token {0:?} has no contract code")]
+    InvalidToken(eth::TokenAddress),
+    #[error("order value exceeds the maximum the solver handles")]
+    OrderTooLarge,
+    #[error("swap calldata of {0} bytes exceeds the maximum the solver handles")]
+    CalldataTooLarge(usize),
+    #[error("slippage tolerance exceeds the configured maximum")]
+    SlippageTooHigh,
+    /// The swap price deviates implausibly from the reference prices, which
+    /// points to a faulty DEX API response.
+    #[error("swap price deviates too far from the reference prices")]
+    PriceDeviationTooHigh,
+    #[error("request timed out")]
+    Timeout,
+    #[error("DEX API is unavailable after repeated failures")]
+    Unavailable,
+    /// The DEX API failed in a way that it expects to recover from, so the
+    /// request is worth retrying.
+    #[error("transient DEX API error: {0}")]
+    Transient(Arc<dyn std::error::Error + Send + Sync>),
     #[error(transparent)]
-    Other(Box<dyn std::error::Error + Send + Sync>),
+    Other(Arc<dyn std::error::Error + Send + Sync>),
+}
+
+/// How long DEX API clients wait for a connection to be established, unless
+/// configured otherwise.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long DEX API clients wait for a response, unless configured otherwise.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Builds an HTTP client to share among the clients of several DEX APIs
+/// (see [`Client::shared`]), so that they use a single connection pool and
+/// DNS cache.
+pub fn shared_client(
+    follow_redirects: bool,
+    connect_timeout: Duration,
+) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .redirect(redirect_policy(follow_redirects))
+        .connect_timeout(connect_timeout)
+        .build()
+}
+
+/// Returns the redirect policy for DEX API clients.
+fn redirect_policy(follow_redirects: bool) -> reqwest::redirect::Policy {
+    if follow_redirects {
+        reqwest::redirect::Policy::default()
+    } else {
+        reqwest::redirect::Policy::none()
+    }
 }
 
 /// A wrapper around [`reqwest::Client`] to pre-set commonly used headers
 /// and other properties on each request.
+#[derive(Clone)]
 struct Client {
     /// Client to send requests.
     client: reqwest::Client,
 
+    /// Headers to send with every request, e.g. API credentials.
+    headers: reqwest::header::HeaderMap,
+
+    /// Timeout to set on every request, for shared clients that don't have
+    /// the timeout of this DEX API set already.
+    timeout: Option<Duration>,
+
     /// Block stream to read the current block.
     block_stream: Option<CurrentBlockWatcher>,
+
+    /// Delays requests to stay within the request quota of the DEX API.
+    throttle: Option<Arc<throttle::Throttle>>,
 }
 
 impl Client {
-    pub fn new(client: reqwest::Client, block_stream: Option<CurrentBlockWatcher>) -> Self {
+    /// Builds the client with the specified timeouts, so that a stalled DEX
+    /// API can't hold up a request indefinitely.
+    pub fn new(
+        client: reqwest::ClientBuilder,
+        connect_timeout: Duration,
+        request_timeout: Duration,
+        block_stream: Option<CurrentBlockWatcher>,
+    ) -> reqwest::Result<Self> {
+        Ok(Self {
+            client: client
+                .connect_timeout(connect_timeout)
+                .timeout(request_timeout)
+                .build()?,
+            headers: Default::default(),
+            timeout: None,
+            block_stream,
+            throttle: None,
+        })
+    }
+
+    /// Sends requests with an existing client, sharing its connection pool
+    /// with the other DEX APIs that use it. Its redirect policy and connect
+    /// timeout apply instead of the ones of this DEX API.
+    pub fn shared(
+        client: reqwest::Client,
+        request_timeout: Duration,
+        block_stream: Option<CurrentBlockWatcher>,
+    ) -> Self {
         Self {
             client,
+            headers: Default::default(),
+            timeout: Some(request_timeout),
             block_stream,
+            throttle: None,
+        }
+    }
+
+    /// Sets headers to send with every request.
+    pub fn with_headers(mut self, headers: reqwest::header::HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Limits the rate of requests sent by the client and all its clones.
+    pub fn with_rate_limit(mut self, limit: Option<RateLimit>) -> Self {
+        self.throttle = limit.map(|limit| Arc::new(throttle::Throttle::new(limit)));
+        self
+    }
+
+    /// Prepares a request builder which already has additional headers set,
+    /// once the request may be sent within the rate limit.
+    pub async fn request(&self, method: reqwest::Method, url: reqwest::Url) -> RequestBuilder {
+        if let Some(throttle) = &self.throttle {
+            throttle.acquire().await;
         }
+        self.prepare(method, url)
     }
 
-    /// Prepares a request builder which already has additional headers set.
-    pub fn request(&self, method: reqwest::Method, url: reqwest::Url) -> RequestBuilder {
-        let request = self.client.request(method, url);
+    /// Prepares a request builder like [`Client::request`], but without
+    /// waiting for the rate limit. It is meant for requests that are only
+    /// inspected and never sent, e.g. to sign them.
+    pub fn prepare(&self, method: reqwest::Method, url: reqwest::Url) -> RequestBuilder {
+        let mut request = self
+            .client
+            .request(method, url)
+            .headers(self.headers.clone());
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
         if let Some(stream) = &self.block_stream {
             // Set this header to easily support caching in an egress proxy.
             request.header("X-CURRENT-BLOCK-HASH", stream.borrow().hash.to_string())
@@ -91,13 +355,43 @@ impl Client {
 }
 
 impl Error {
+    /// Whether the same request may succeed when retried after backing off.
+    pub fn retryable(&self) -> bool {
+        matches!(self, Self::RateLimited { .. } | Self::Transient(_))
+    }
+
+    /// How long the DEX API asked us to wait before sending more requests.
+    /// Without it, the configured back-off applies.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether the error indicates that the DEX API itself is failing, as
+    /// opposed to it not being able to provide a swap for the order.
+    pub fn is_upstream_failure(&self) -> bool {
+        matches!(self, Self::Timeout | Self::Transient(_) | Self::Other(_))
+    }
+
     /// for instrumentization purposes
     pub fn format_variant(&self) -> &'static str {
         match self {
             Self::OrderNotSupported => "OrderNotSupported",
+            Self::InvalidOrder(_) => "InvalidOrder",
             Self::NotFound => "NotFound",
-            Self::RateLimited => "RateLimited",
+            Self::InsufficientLiquidity => "InsufficientLiquidity",
+            Self::RateLimited { .. } => "RateLimited",
             Self::UnavailableForLegalReasons => "UnavailableForLegalReasons",
+            Self::InvalidToken(_) => "InvalidToken",
+            Self::OrderTooLarge => "OrderTooLarge",
+            Self::CalldataTooLarge(_) => "CalldataTooLarge",
+            Self::SlippageTooHigh => "SlippageTooHigh",
+            Self::PriceDeviationTooHigh => "PriceDeviationTooHigh",
+            Self::Timeout => "Timeout",
+            Self::Unavailable => "Unavailable",
+            Self::Transient(_) => "Transient",
             Self::Other(_) => "Other",
         }
     }
@@ -106,9 +400,9 @@ impl Error {
 impl From<balancer::Error> for Error {
     fn from(err: balancer::Error) -> Self {
         match err {
-            balancer::Error::NotFound => Self::NotFound,
-            balancer::Error::RateLimited => Self::RateLimited,
-            _ => Self::Other(Box::new(err)),
+            balancer::Error::NotFound | balancer::Error::DeprecatedPool(_) => Self::NotFound,
+            balancer::Error::RateLimited => Self::RateLimited { retry_after: None },
+            _ => Self::Other(Arc::new(err)),
         }
     }
 }
@@ -118,8 +412,9 @@ impl From<oneinch::Error> for Error {
         match err {
             oneinch::Error::OrderNotSupported => Self::OrderNotSupported,
             oneinch::Error::NotFound => Self::NotFound,
-            oneinch::Error::RateLimited => Self::RateLimited,
-            _ => Self::Other(Box::new(err)),
+            oneinch::Error::InsufficientLiquidity => Self::InsufficientLiquidity,
+            oneinch::Error::RateLimited { retry_after } => Self::RateLimited { retry_after },
+            _ => Self::Other(Arc::new(err)),
         }
     }
 }
@@ -128,10 +423,11 @@ impl From<zeroex::Error> for Error {
     fn from(err: zeroex::Error) -> Self {
         match err {
             zeroex::Error::NotFound => Self::NotFound,
-            zeroex::Error::RateLimited => Self::RateLimited,
+            zeroex::Error::RateLimited { retry_after } => Self::RateLimited { retry_after },
+            zeroex::Error::Transient { .. } => Self::Transient(Arc::new(err)),
             zeroex::Error::UnavailableForLegalReasons => Self::UnavailableForLegalReasons,
             zeroex::Error::OrderNotSupported => Self::OrderNotSupported,
-            _ => Self::Other(Box::new(err)),
+            _ => Self::Other(Arc::new(err)),
         }
     }
 }
@@ -140,8 +436,8 @@ impl From<paraswap::Error> for Error {
     fn from(err: paraswap::Error) -> Self {
         match err {
             paraswap::Error::NotFound | paraswap::Error::MissingDecimals => Self::NotFound,
-            paraswap::Error::RateLimited => Self::RateLimited,
-            _ => Self::Other(Box::new(err)),
+            paraswap::Error::RateLimited => Self::RateLimited { retry_after: None },
+            _ => Self::Other(Arc::new(err)),
         }
     }
 }
@@ -151,8 +447,156 @@ impl From<okx::Error> for Error {
         match err {
             okx::Error::OrderNotSupported => Self::OrderNotSupported,
             okx::Error::NotFound => Self::NotFound,
-            okx::Error::RateLimited => Self::RateLimited,
-            _ => Self::Other(Box::new(err)),
+            okx::Error::RateLimited => Self::RateLimited { retry_after: None },
+            _ => Self::Other(Arc::new(err)),
+        }
+    }
+}
+
+impl From<replay::Error> for Error {
+    fn from(err: replay::Error) -> Self {
+        match err {
+            replay::Error::NotFound => Self::NotFound,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::domain::dex::testing::{asset, order, swap, token},
+    };
+
+    #[test]
+    fn rejects_invalid_orders() {
+        let order = |buy, amount: u64| dex::Order {
+            buy,
+            ..order(amount)
+        };
+
+        assert!(validate(&order(token(2), 1)).is_ok());
+        assert!(matches!(
+            validate(&order(token(1), 1)),
+            Err(Error::InvalidOrder("sell and buy tokens are the same"))
+        ));
+        assert!(matches!(
+            validate(&order(token(2), 0)),
+            Err(Error::InvalidOrder("amount is zero"))
+        ));
+    }
+
+    #[test]
+    fn rejects_disallowed_call_targets() {
+        let target = |byte| eth::ContractAddress(eth::H160::repeat_byte(byte));
+        let swap = |calls: &[u8], spender| {
+            let mut swap = swap(asset(token(4), 0), asset(token(5), 0));
+            swap.calls = calls
+                .iter()
+                .map(|&to| dex::Call::swap(target(to), Default::default()))
+                .collect();
+            swap.allowance.spender = target(spender);
+            swap
+        };
+
+        assert_eq!(disallowed_target(&swap(&[1, 2], 3), None), None);
+        assert_eq!(
+            disallowed_target(&swap(&[1, 2], 1), Some(&[target(1), target(2)].into())),
+            None
+        );
+        assert_eq!(
+            disallowed_target(&swap(&[1, 2], 1), Some(&[target(1)].into())),
+            Some(target(2))
+        );
+        assert_eq!(
+            disallowed_target(&swap(&[1], 3), Some(&[target(1)].into())),
+            Some(target(3))
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_wait_for_the_rate_limit() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url: reqwest::Url = format!("http://{}/", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let unavailable = axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async { axum::http::StatusCode::SERVICE_UNAVAILABLE }),
+        );
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(unavailable.into_make_service()),
+        );
+
+        let client = Client::new(
+            reqwest::ClientBuilder::new(),
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+            None,
+        )
+        .unwrap()
+        .with_rate_limit(Some(RateLimit {
+            requests_per_second: 10.0,
+            burst: 1,
+        }));
+        let retry = crate::util::http::RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            max_elapsed: Duration::from_secs(10),
+        };
+
+        let start = std::time::Instant::now();
+        let result = crate::util::http::roundtrip_retry!(
+            <serde_json::Value, serde_json::Value>;
+            &retry,
+            || client.request(reqwest::Method::GET, url.clone())
+        )
+        .await;
+        assert!(result.is_err_and(|err| err.is_transient()));
+        // The first attempt takes the only token of the burst, so that both
+        // retries have to wait for a token to be refilled.
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn retries_rate_limited_and_transient_errors_only() {
+        assert!(Error::RateLimited { retry_after: None }.retryable());
+        let transient = Error::from(zeroex::Error::Transient {
+            code: 103,
+            reason: "try again".to_owned(),
+        });
+        assert!(matches!(transient, Error::Transient(_)));
+        assert!(transient.retryable());
+        assert_eq!(transient.retry_after(), None);
+        assert!(!Error::NotFound.retryable());
+        assert!(!Error::InsufficientLiquidity.retryable());
+        assert!(!Error::Other(Arc::new(std::fmt::Error)).retryable());
+    }
+
+    #[test]
+    fn reports_retry_after_of_rate_limited_errors() {
+        let delay = Duration::from_secs(2);
+        assert_eq!(
+            Error::from(zeroex::Error::RateLimited {
+                retry_after: Some(delay)
+            })
+            .retry_after(),
+            Some(delay)
+        );
+        assert_eq!(
+            Error::from(oneinch::Error::RateLimited {
+                retry_after: Some(delay)
+            })
+            .retry_after(),
+            Some(delay)
+        );
+        assert_eq!(
+            Error::from(paraswap::Error::RateLimited).retry_after(),
+            None
+        );
+        assert_eq!(Error::NotFound.retry_after(), None);
+    }
+}
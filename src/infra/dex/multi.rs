@@ -0,0 +1,369 @@
+//! A meta DEX that quotes several DEX APIs for the same order and picks the
+//! best swap among them.
+
+use {
+    super::{backend, Backend, Dex, Error},
+    crate::domain::{auction, dex, eth, order},
+    bigdecimal::BigDecimal,
+    futures::{stream::FuturesUnordered, StreamExt},
+    std::time::Duration,
+};
+
+/// Quotes all of its backends concurrently for every order and picks the swap
+/// with the best net output, see [`dex::Swap::net_output`].
+///
+/// The number of orders that are solved concurrently is capped by the solver,
+/// so each backend receives at most that many concurrent requests.
+///
+/// Orders matching one of the configured [`Route`]s are only quoted by the
+/// backend of the first matching route instead.
+///
+/// With [`EarlyReturn`] configured, a good enough swap is picked without
+/// waiting for the remaining backends.
+///
+/// With a MEV penalty configured, the ranking is MEV-aware: the net output of
+/// a swap is reduced by the penalty (a fraction of its net output) for every
+/// public AMM that it is routed through, since swaps through public AMMs can
+/// be sandwiched. The exposure is derived from the sources that the backend
+/// reports (see [`dex::Source::is_public_amm`]). Swaps without reported
+/// sources count as one public AMM, unless they are routed through private
+/// market maker liquidity. The penalty only decides between swaps that are
+/// close in price.
+pub struct MultiDex {
+    backends: Vec<Backend>,
+    request_timeout: Duration,
+    routes: Vec<Route>,
+    early_return: Option<EarlyReturn>,
+    mev_penalty: Option<BigDecimal>,
+}
+
+impl MultiDex {
+    /// Creates a new meta DEX. Backends that take longer than the request
+    /// timeout to quote an order, or whose swap fails the checks of the
+    /// backend config, are skipped for that order.
+    pub fn new(
+        backends: Vec<Dex>,
+        request_timeout: Duration,
+        routes: Vec<Route>,
+        early_return: Option<EarlyReturn>,
+        mev_penalty: Option<BigDecimal>,
+        backend: backend::Config,
+    ) -> Self {
+        Self {
+            backends: backends
+                .into_iter()
+                .map(|dex| Backend::new(dex, backend.clone()))
+                .collect(),
+            request_timeout,
+            routes,
+            early_return,
+            mev_penalty,
+        }
+    }
+
+    pub async fn swap(
+        &self,
+        order: &dex::Order,
+        slippage: &dex::Slippage,
+        tokens: &auction::Tokens,
+        gas_price: auction::GasPrice,
+    ) -> Result<dex::Swap, Error> {
+        let routed = self
+            .routes
+            .iter()
+            .find(|route| route.matches(order))
+            .and_then(|route| self.backends.get(route.backend));
+        if let Some(backend) = routed {
+            tracing::debug!(backend = backend.name(), "routed order");
+            let swap = backend.swap(order, slippage, tokens, gas_price);
+            return tokio::time::timeout(self.request_timeout, swap)
+                .await
+                .unwrap_or(Err(Error::Timeout));
+        }
+
+        let mut pending = self
+            .backends
+            .iter()
+            .map(|backend| async move {
+                let swap = backend.swap(order, slippage, tokens, gas_price);
+                (
+                    backend.name(),
+                    tokio::time::timeout(self.request_timeout, swap)
+                        .await
+                        .unwrap_or(Err(Error::Timeout)),
+                )
+            })
+            .collect::<FuturesUnordered<_>>();
+        let mut outstanding = self.backends.iter().map(Backend::name).collect::<Vec<_>>();
+        let min_wait = tokio::time::sleep(
+            self.early_return
+                .as_ref()
+                .map(|early_return| early_return.min_wait)
+                .unwrap_or_default(),
+        );
+        tokio::pin!(min_wait);
+        let mut waited = false;
+
+        let mut best = None;
+        let mut errors = Vec::new();
+        let mut rejected = Vec::new();
+        loop {
+            tokio::select! {
+                result = pending.next() => {
+                    let Some((backend, result)) = result else {
+                        break;
+                    };
+                    outstanding.retain(|name| *name != backend);
+                    match result {
+                        Ok(swap) => {
+                            let net_output = self.rank(&swap, order.side, gas_price, tokens);
+                            tracing::debug!(backend, %net_output, "quoted swap");
+                            let outranked =
+                                if best.as_ref().is_none_or(|(_, _, best)| net_output > *best) {
+                                    best.replace((backend, swap, net_output))
+                                } else {
+                                    Some((backend, swap, net_output))
+                                };
+                            if let Some((backend, swap, _)) = outranked {
+                                rejected.push(dex::Rejected {
+                                    backend,
+                                    amounts: Some((swap.input.amount, swap.output.amount)),
+                                    reason: dex::Rejection::Outranked,
+                                });
+                            }
+                        }
+                        Err(err) => {
+                            tracing::debug!(backend, ?err, "backend failed to quote");
+                            rejected.push(dex::Rejected {
+                                backend,
+                                amounts: None,
+                                reason: dex::Rejection::Failed(err.format_variant()),
+                            });
+                            errors.push(err);
+                        }
+                    }
+                }
+                () = &mut min_wait, if !waited => waited = true,
+            }
+
+            let good_enough = self.early_return.as_ref().is_some_and(|early_return| {
+                best.as_ref()
+                    .is_some_and(|(_, swap, _)| early_return.accepts(swap, tokens))
+            });
+            if waited && good_enough {
+                // Dropping the pending requests cancels them.
+                tracing::debug!(pending = pending.len(), "returning early");
+                rejected.extend(outstanding.into_iter().map(|backend| dex::Rejected {
+                    backend,
+                    amounts: None,
+                    reason: dex::Rejection::Cancelled,
+                }));
+                break;
+            }
+        }
+
+        let (backend, mut swap, _) = best.ok_or_else(|| combine(errors))?;
+        tracing::debug!(backend, "picked swap");
+        swap.metadata.rejected = rejected;
+        Ok(swap)
+    }
+
+    /// The net output of the swap (see [`dex::Swap::net_output`]), reduced by
+    /// the MEV penalty if the ranking is MEV-aware.
+    fn rank(
+        &self,
+        swap: &dex::Swap,
+        side: order::Side,
+        gas_price: auction::GasPrice,
+        tokens: &auction::Tokens,
+    ) -> BigDecimal {
+        let net_output = swap.net_output(side, gas_price, tokens);
+        match &self.mev_penalty {
+            Some(penalty) => {
+                let penalty = net_output.abs() * penalty * BigDecimal::from(mev_exposure(swap));
+                net_output - penalty
+            }
+            None => net_output,
+        }
+    }
+}
+
+/// The number of public AMMs that the swap is routed through, see
+/// [`MultiDex`] for the heuristic.
+fn mev_exposure(swap: &dex::Swap) -> u32 {
+    let metadata = &swap.metadata;
+    if metadata.sources.is_empty() {
+        return (!metadata.private_liquidity).into();
+    }
+    let amms = metadata
+        .sources
+        .iter()
+        .filter(|source| source.is_public_amm())
+        .count();
+    amms.try_into().unwrap_or(u32::MAX)
+}
+
+/// Stops waiting for the remaining backends of a [`MultiDex`] once a good
+/// enough swap was quoted. This trades a potentially slightly better swap from
+/// a slow backend for latency.
+#[derive(Clone, Debug)]
+pub struct EarlyReturn {
+    /// How long to wait for the backends in any case.
+    pub min_wait: Duration,
+    /// The maximum price impact (see [`dex::Swap::price_impact`]) of a swap
+    /// that is good enough to be returned early.
+    pub max_price_impact: BigDecimal,
+}
+
+impl EarlyReturn {
+    /// Whether the swap is good enough to be returned early. Swaps without
+    /// reference prices for their tokens never are.
+    fn accepts(&self, swap: &dex::Swap, tokens: &auction::Tokens) -> bool {
+        swap.price_impact(tokens)
+            .is_some_and(|price_impact| price_impact <= self.max_price_impact)
+    }
+}
+
+/// Pins the orders of matching token pairs to a single backend, e.g. because
+/// it is known to always provide the best swap for them. This saves the
+/// requests to all other backends.
+#[derive(Clone, Debug)]
+pub struct Route {
+    /// The sell token to match, or any token if `None`.
+    pub sell: Option<eth::TokenAddress>,
+    /// The buy token to match, or any token if `None`.
+    pub buy: Option<eth::TokenAddress>,
+    /// The backend to quote the matching orders with, by its index in the
+    /// backends of the [`MultiDex`]. The configuration resolves it from the
+    /// identifier of the backend.
+    pub backend: usize,
+}
+
+impl Route {
+    fn matches(&self, order: &dex::Order) -> bool {
+        self.sell.is_none_or(|sell| sell == order.sell)
+            && self.buy.is_none_or(|buy| buy == order.buy)
+    }
+}
+
+/// Picks the error to report when all backends failed. A retryable error is
+/// preferred, so that the solver backs off from rate limited backends.
+/// Otherwise, the error of the first backend is reported.
+pub(super) fn combine(errors: Vec<Error>) -> Error {
+    match errors.iter().position(Error::retryable) {
+        Some(i) => errors.into_iter().nth(i),
+        None => errors.into_iter().next(),
+    }
+    .unwrap_or(Error::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::domain::dex::testing::{asset, ether, order, swap, token, token_info},
+    };
+
+    #[test]
+    fn prefers_retryable_errors() {
+        assert!(matches!(
+            combine(vec![
+                Error::NotFound,
+                Error::RateLimited { retry_after: None }
+            ]),
+            Error::RateLimited { .. }
+        ));
+        assert!(matches!(
+            combine(vec![Error::OrderNotSupported, Error::NotFound]),
+            Error::OrderNotSupported
+        ));
+        assert!(matches!(combine(vec![]), Error::NotFound));
+    }
+
+    #[test]
+    fn matches_routes_with_wildcards() {
+        let order = order(1);
+        let route = |sell, buy| Route {
+            sell,
+            buy,
+            backend: 0,
+        };
+
+        assert!(route(Some(token(1)), Some(token(2))).matches(&order));
+        assert!(route(Some(token(1)), None).matches(&order));
+        assert!(route(None, Some(token(2))).matches(&order));
+        assert!(route(None, None).matches(&order));
+        assert!(!route(Some(token(2)), Some(token(1))).matches(&order));
+        assert!(!route(None, Some(token(3))).matches(&order));
+    }
+
+    #[test]
+    fn returns_early_below_price_impact_threshold() {
+        let early_return = EarlyReturn {
+            min_wait: Duration::ZERO,
+            max_price_impact: "0.01".parse().unwrap(),
+        };
+        let swap = |output: &str| {
+            swap(
+                asset(token(1), ether("1.0").0),
+                asset(token(2), ether(output).0),
+            )
+        };
+        let tokens = |price: Option<&str>| {
+            auction::Tokens(
+                [
+                    (token(1), token_info(None, Some("1.0"))),
+                    (token(2), token_info(None, price)),
+                ]
+                .into_iter()
+                .collect(),
+            )
+        };
+
+        assert!(early_return.accepts(&swap("0.995"), &tokens(Some("1.0"))));
+        assert!(early_return.accepts(&swap("1.1"), &tokens(Some("1.0"))));
+        assert!(!early_return.accepts(&swap("0.98"), &tokens(Some("1.0"))));
+        assert!(!early_return.accepts(&swap("1.0"), &tokens(None)));
+    }
+
+    #[test]
+    fn penalizes_public_amm_routes_when_mev_aware() {
+        let amm = {
+            let mut swap = swap(asset(token(1), 1000), asset(token(2), 1000));
+            swap.metadata.sources = vec![dex::Source::UniswapV3, dex::Source::Curve];
+            swap
+        };
+        let rfq = {
+            let mut swap = swap(asset(token(1), 1000), asset(token(2), 999));
+            swap.metadata.private_liquidity = true;
+            swap
+        };
+        assert_eq!(mev_exposure(&amm), 2);
+        assert_eq!(mev_exposure(&rfq), 0);
+        assert_eq!(
+            mev_exposure(&swap(asset(token(1), 1), asset(token(2), 1))),
+            1
+        );
+
+        let mut multi = MultiDex {
+            backends: Default::default(),
+            request_timeout: Duration::ZERO,
+            routes: Default::default(),
+            early_return: None,
+            mev_penalty: None,
+        };
+        let rank = |multi: &MultiDex, swap| {
+            multi.rank(
+                swap,
+                order::Side::Sell,
+                auction::GasPrice(eth::Ether(Default::default())),
+                &auction::Tokens(Default::default()),
+            )
+        };
+
+        assert!(rank(&multi, &amm) > rank(&multi, &rfq));
+        multi.mev_penalty = Some("0.001".parse().unwrap());
+        assert_eq!(rank(&multi, &amm), "998".parse().unwrap());
+        assert!(rank(&multi, &amm) < rank(&multi, &rfq));
+    }
+}
@@ -132,6 +132,10 @@ pub struct SwapResponseTx {
     #[serde_as(as = "serialize::U256")]
     pub gas: U256,
 
+    /// The gas price used to compute the swap.
+    #[serde_as(as = "Option<serialize::U256>")]
+    pub gas_price: Option<U256>,
+
     /// The contract address of OKX DEX router.
     pub to: H160,
 
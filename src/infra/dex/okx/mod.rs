@@ -8,11 +8,14 @@ use {
     ethrpc::block_stream::CurrentBlockWatcher,
     futures::TryFutureExt,
     hmac::{Hmac, Mac},
-    hyper::{header::HeaderValue, StatusCode},
+    hyper::header::HeaderValue,
     moka::future::Cache,
     serde::{de::DeserializeOwned, Serialize},
     sha2::Sha256,
-    std::sync::atomic::{self, AtomicU64},
+    std::{
+        sync::atomic::{self, AtomicU64},
+        time::Duration,
+    },
     tracing::Instrument,
 };
 
@@ -26,6 +29,7 @@ pub struct Okx {
     endpoint: reqwest::Url,
     api_secret_key: String,
     defaults: dto::SwapRequest,
+    retry: util::http::RetryPolicy,
     /// Cache which stores a map of Token Address to contract address of
     /// OKX DEX approve contract.
     dex_approved_addresses: Cache<eth::TokenAddress, eth::ContractAddress>,
@@ -44,6 +48,27 @@ pub struct Config {
 
     /// The stream that yields every new block.
     pub block_stream: Option<CurrentBlockWatcher>,
+
+    /// Whether to follow HTTP redirects from the API.
+    pub follow_redirects: bool,
+
+    /// Optional request quota of the API, shared by all requests made by
+    /// this solver.
+    pub rate_limit: Option<super::RateLimit>,
+
+    /// How long to wait for a connection to the API to be established.
+    pub connect_timeout: Duration,
+
+    /// How long to wait for a response from the API, including connecting.
+    pub request_timeout: Duration,
+
+    /// An HTTP client shared with other DEX APIs to send requests with,
+    /// instead of building a dedicated one.
+    pub shared_client: Option<reqwest::Client>,
+
+    /// How failed requests are retried. Requests are only sent once if not
+    /// specified.
+    pub retry: Option<util::http::RetryPolicy>,
 }
 
 pub struct OkxCredentialsConfig {
@@ -78,10 +103,20 @@ impl Okx {
             headers.insert("OK-ACCESS-KEY", api_key);
             headers.insert("OK-ACCESS-PASSPHRASE", api_passphrase);
 
-            let client = reqwest::Client::builder()
-                .default_headers(headers)
-                .build()?;
-            super::Client::new(client, config.block_stream)
+            match config.shared_client {
+                Some(client) => {
+                    super::Client::shared(client, config.request_timeout, config.block_stream)
+                }
+                None => super::Client::new(
+                    reqwest::Client::builder()
+                        .redirect(super::redirect_policy(config.follow_redirects)),
+                    config.connect_timeout,
+                    config.request_timeout,
+                    config.block_stream,
+                )?,
+            }
+            .with_headers(headers)
+            .with_rate_limit(config.rate_limit)
         };
 
         let defaults = dto::SwapRequest {
@@ -98,6 +133,7 @@ impl Okx {
             endpoint: config.endpoint,
             api_secret_key: config.okx_credentials.api_secret_key,
             defaults,
+            retry: config.retry.unwrap_or_else(util::http::RetryPolicy::none),
             dex_approved_addresses: Cache::new(DEFAULT_DEX_APPROVED_ADDRESSES_CACHE_SIZE),
         })
     }
@@ -125,12 +161,16 @@ impl Okx {
             .gas
             .checked_add(swap_response.tx.gas / 2)
             .ok_or(Error::GasCalculationFailed)?;
+        let gas_cost_wei = swap_response
+            .tx
+            .gas_price
+            .and_then(|gas_price| gas.checked_mul(gas_price));
 
         Ok(dex::Swap {
-            calls: vec![dex::Call {
-                to: eth::ContractAddress(swap_response.tx.to),
-                calldata: swap_response.tx.data.clone(),
-            }],
+            calls: vec![dex::Call::swap(
+                eth::ContractAddress(swap_response.tx.to),
+                swap_response.tx.data.clone(),
+            )],
             input: eth::Asset {
                 token: swap_response
                     .router_result
@@ -152,6 +192,11 @@ impl Okx {
                 amount: dex::Amount::new(swap_response.router_result.from_token_amount),
             },
             gas: eth::Gas(gas),
+            metadata: dex::Metadata {
+                gas_cost_wei,
+                gas_confidence: dex::GasConfidence::Measured,
+                ..Default::default()
+            },
         })
     }
 
@@ -235,19 +280,14 @@ impl Okx {
         T: Serialize,
         U: DeserializeOwned + Clone,
     {
-        let mut request_builder = self
+        let url = self
+            .endpoint
+            .join(endpoint)
+            .map_err(|_| Error::RequestBuildFailed)?;
+        let request = self
             .client
-            .request(
-                reqwest::Method::GET,
-                self.endpoint
-                    .join(endpoint)
-                    .map_err(|_| Error::RequestBuildFailed)?,
-            )
-            .query(query);
-
-        let request = request_builder
-            .try_clone()
-            .ok_or(Error::RequestBuildFailed)?
+            .prepare(reqwest::Method::GET, url.clone())
+            .query(query)
             .build()
             .map_err(|_| Error::RequestBuildFailed)?;
 
@@ -256,19 +296,23 @@ impl Okx {
             .to_string();
         let signature = self.generate_signature(&request, timestamp)?;
 
-        request_builder = request_builder.header(
-            "OK-ACCESS-TIMESTAMP",
-            reqwest::header::HeaderValue::from_str(timestamp)
-                .map_err(|_| Error::RequestBuildFailed)?,
-        );
-        request_builder = request_builder.header(
-            "OK-ACCESS-SIGN",
-            HeaderValue::from_str(&signature).map_err(|_| Error::RequestBuildFailed)?,
-        );
-
-        let response = util::http::roundtrip!(
+        let timestamp = &reqwest::header::HeaderValue::from_str(timestamp)
+            .map_err(|_| Error::RequestBuildFailed)?;
+        let signature =
+            &HeaderValue::from_str(&signature).map_err(|_| Error::RequestBuildFailed)?;
+        let url = &url;
+
+        let response = util::http::roundtrip_retry!(
             <dto::Response<U>, dto::Error>;
-            request_builder
+            &self.retry,
+            || async move {
+                self.client
+                    .request(reqwest::Method::GET, url.clone())
+                    .await
+                    .query(query)
+                    .header("OK-ACCESS-TIMESTAMP", timestamp.clone())
+                    .header("OK-ACCESS-SIGN", signature.clone())
+            }
         )
         .await?;
 
@@ -313,16 +357,10 @@ impl From<util::http::RoundtripError<dto::Error>> for Error {
     // dedicated function: handle_api_error().
     fn from(err: util::http::RoundtripError<dto::Error>) -> Self {
         match err {
-            util::http::RoundtripError::Http(err) => {
-                if let util::http::Error::Status(code, _) = err {
-                    match code {
-                        StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
-                        _ => Self::Http(err),
-                    }
-                } else {
-                    Self::Http(err)
-                }
-            }
+            util::http::RoundtripError::Http(err) => match err {
+                util::http::Error::TooManyRequests { .. } => Self::RateLimited,
+                _ => Self::Http(err),
+            },
             util::http::RoundtripError::Api(err) => match err.code {
                 429 => Self::RateLimited,
                 _ => Self::Api {
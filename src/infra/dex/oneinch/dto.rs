@@ -67,6 +67,10 @@ pub struct Query {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub referrer_address: Option<H160>,
 
+    /// The partner fee that gets sent to the referrer address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<Fee>,
+
     /// Disable 1Inch swap estimates. Normally, the 1Inch API will simulate and
     /// verify the swap. However, this requires upfront balances and approvals
     /// which are not always available (and in the case of the CoW Protocol
@@ -84,6 +88,11 @@ pub struct Query {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub complexity_level: Option<u32>,
 
+    /// Allow returning a route that only swaps part of the amount if there
+    /// is not enough liquidity for the full amount.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_partial_fill: Option<bool>,
+
     /// The target gas price for the swap transaction.
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde_as(as = "Option<serialize::U256>")]
@@ -138,6 +147,30 @@ impl Slippage {
     }
 }
 
+/// A 1Inch partner fee, in percent of the sell amount.
+///
+/// The fee is deducted from the sell amount before swapping, so the quoted
+/// `toTokenAmount` already accounts for it while `fromTokenAmount` is the full
+/// amount that gets transferred in.
+#[derive(Clone, Debug, Serialize)]
+pub struct Fee(BigDecimal);
+
+impl Fee {
+    /// Returns a 1Inch fee for the specified amount of basis points.
+    pub fn from_bps(bps: u32) -> Self {
+        Self(BigDecimal::new(bps.into(), 2).normalized())
+    }
+
+    /// Returns a 1Inch fee for the specified amount of basis points, or an
+    /// error if the API does not accept such a high fee.
+    pub fn try_from_bps(bps: u32) -> Result<Self, super::Error> {
+        if bps > super::MAX_FEE_BPS {
+            return Err(super::Error::InvalidFee(bps));
+        }
+        Ok(Self::from_bps(bps))
+    }
+}
+
 /// A 1Inch API swap response.
 #[serde_as]
 #[derive(Deserialize)]
@@ -153,6 +186,29 @@ pub struct Swap {
 
     /// The corresponding transaction for the swap.
     pub tx: Tx,
+
+    /// The liquidity sources the swap is routed through, grouped by route
+    /// and hop.
+    #[serde(default)]
+    pub protocols: Vec<Vec<Vec<RouteProtocol>>>,
+}
+
+impl Swap {
+    /// Whether any part of the route uses private market maker liquidity.
+    /// 1Inch names these liquidity sources `PMM1`, `PMM2`, etc.
+    pub fn uses_private_liquidity(&self) -> bool {
+        self.protocols
+            .iter()
+            .flatten()
+            .flatten()
+            .any(|protocol| protocol.name.starts_with("PMM"))
+    }
+}
+
+/// A liquidity source used by a part of a 1Inch swap route.
+#[derive(Deserialize)]
+pub struct RouteProtocol {
+    pub name: String,
 }
 
 /// 1Inch swap transaction data.
@@ -169,6 +225,10 @@ pub struct Tx {
 
     /// The estimated gas limit required for executing the transaction.
     pub gas: u64,
+
+    /// The gas price used to compute the swap.
+    #[serde_as(as = "Option<serialize::U256>")]
+    pub gas_price: Option<U256>,
 }
 
 #[derive(Deserialize)]
@@ -194,3 +254,201 @@ pub struct Error {
     pub status_code: i32,
     pub description: String,
 }
+
+/// DTOs for the v6 1Inch swap API. The query parameters and swap response
+/// fields were renamed compared to v5, but otherwise carry the same data.
+pub mod v6 {
+    use {super::*, crate::util::serialize};
+
+    /// A v6 1Inch API swap query parameters.
+    #[serde_as]
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Query<'a> {
+        pub src: H160,
+        pub dst: H160,
+        #[serde_as(as = "serialize::U256")]
+        pub amount: U256,
+        pub from: H160,
+        pub origin: H160,
+        pub slippage: &'a Slippage,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde_as(as = "Option<serialize::CommaSeparated>")]
+        pub protocols: Option<Vec<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub referrer: Option<H160>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub fee: Option<&'a Fee>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub disable_estimate: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub main_route_parts: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub connector_tokens: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub complexity_level: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub allow_partial_fill: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde_as(as = "Option<serialize::U256>")]
+        pub gas_price: Option<U256>,
+        /// v6 only includes the route in the response when asked to.
+        pub include_protocols: bool,
+    }
+
+    impl<'a> From<&'a super::Query> for Query<'a> {
+        fn from(query: &'a super::Query) -> Self {
+            Self {
+                src: query.from_token_address,
+                dst: query.to_token_address,
+                amount: query.amount,
+                from: query.from_address,
+                origin: query.origin,
+                slippage: &query.slippage,
+                protocols: query.protocols.clone(),
+                referrer: query.referrer_address,
+                fee: query.fee.as_ref(),
+                disable_estimate: query.disable_estimate,
+                main_route_parts: query.main_route_parts,
+                connector_tokens: query.connector_tokens,
+                complexity_level: query.complexity_level,
+                allow_partial_fill: query.allow_partial_fill,
+                gas_price: query.gas_price,
+                include_protocols: true,
+            }
+        }
+    }
+
+    /// A v6 1Inch API swap response.
+    #[serde_as]
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Swap {
+        /// Expected amount of destination token.
+        #[serde_as(as = "serialize::U256")]
+        pub dst_amount: U256,
+
+        /// The corresponding transaction for the swap.
+        pub tx: Tx,
+
+        /// The liquidity sources the swap is routed through, grouped by
+        /// route and hop.
+        #[serde(default)]
+        pub protocols: Vec<Vec<Vec<RouteProtocol>>>,
+    }
+
+    impl Swap {
+        /// Converts the v6 response into the v5 shape. v6 no longer reports
+        /// the source token amount, so the queried amount is used instead.
+        pub fn into_v5(self, amount: U256) -> super::Swap {
+            super::Swap {
+                from_token_amount: amount,
+                to_token_amount: self.dst_amount,
+                tx: self.tx,
+                protocols: self.protocols,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_from_bps() {
+        for (bps, percent) in [(1, "0.01"), (25, "0.25"), (100, "1"), (300, "3")] {
+            assert_eq!(Fee::from_bps(bps).0, percent.parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_fee() {
+        assert!(Fee::try_from_bps(300).is_ok());
+        assert!(matches!(
+            Fee::try_from_bps(301),
+            Err(super::super::Error::InvalidFee(301))
+        ));
+    }
+
+    #[test]
+    fn serializes_fee_as_percent() {
+        let query = Query {
+            fee: Some(Fee::from_bps(25)),
+            ..Default::default()
+        };
+        let request = reqwest::Client::new()
+            .get("http://localhost/swap")
+            .query(&query)
+            .build()
+            .unwrap();
+
+        assert!(request.url().query().unwrap().contains("&fee=0.25"));
+    }
+
+    #[test]
+    fn serializes_allow_partial_fill() {
+        let query = Query {
+            allow_partial_fill: Some(true),
+            ..Default::default()
+        };
+        let request = reqwest::Client::new()
+            .get("http://localhost/swap")
+            .query(&query)
+            .build()
+            .unwrap();
+
+        assert!(request
+            .url()
+            .query()
+            .unwrap()
+            .contains("&allowPartialFill=true"));
+    }
+
+    #[test]
+    fn serializes_v6_query() {
+        let query = Query {
+            from_token_address: H160([1; 20]),
+            to_token_address: H160([2; 20]),
+            amount: 1000.into(),
+            referrer_address: Some(H160([3; 20])),
+            ..Default::default()
+        };
+        let request = reqwest::Client::new()
+            .get("http://localhost/swap")
+            .query(&v6::Query::from(&query))
+            .build()
+            .unwrap();
+        let query = request.url().query().unwrap();
+
+        assert!(query.starts_with(
+            "src=0x0101010101010101010101010101010101010101&\
+             dst=0x0202020202020202020202020202020202020202&amount=1000"
+        ));
+        assert!(query.contains("&referrer=0x0303030303030303030303030303030303030303"));
+        assert!(query.ends_with("&includeProtocols=true"));
+    }
+
+    #[test]
+    fn deserializes_v6_swap() {
+        let swap = serde_json::from_value::<v6::Swap>(serde_json::json!({
+            "dstAmount": "2000",
+            "tx": {
+                "from": "0x9008d19f58aabd9ed0d60971565aa8510560ab41",
+                "to": "0x111111125421ca6dc452d289314280a0f8842a65",
+                "data": "0x12aa3caf",
+                "value": "0",
+                "gas": 150000,
+                "gasPrice": "20000000000"
+            },
+            "protocols": [[[{ "name": "UNISWAP_V3", "part": 100 }]]]
+        }))
+        .unwrap()
+        .into_v5(1000.into());
+
+        assert_eq!(swap.from_token_amount, U256::from(1000));
+        assert_eq!(swap.to_token_amount, U256::from(2000));
+        assert_eq!(swap.tx.gas, 150_000);
+        assert_eq!(swap.protocols[0][0][0].name, "UNISWAP_V3");
+    }
+}
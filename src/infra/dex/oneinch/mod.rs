@@ -1,12 +1,21 @@
 use {
     crate::{
-        domain::{dex, eth},
+        domain::{auction, dex, eth},
         util,
     },
-    ethereum_types::H160,
+    ethereum_types::{H160, U256},
     ethrpc::block_stream::CurrentBlockWatcher,
     std::{
-        sync::atomic::{self, AtomicU64},
+        collections::{BTreeMap, HashSet},
+        fs,
+        io,
+        path::PathBuf,
+        sync::{
+            atomic::{self, AtomicU64},
+            Arc,
+            RwLock,
+            Weak,
+        },
         time::{Duration, Instant},
     },
     tracing::Instrument,
@@ -17,16 +26,35 @@ mod dto;
 /// Bindings to the 1Inch swap API.
 pub struct OneInch {
     client: super::Client,
+    version: Version,
     endpoint: reqwest::Url,
     defaults: dto::Query,
+    /// The liquidity sources to route through. These can change over time
+    /// when they are refreshed in the background.
+    protocols: Arc<RwLock<Option<Vec<String>>>>,
     spender: eth::ContractAddress,
+    not_found_status_codes: Vec<u16>,
+    retry: util::http::RetryPolicy,
+    gas_price: Option<GasPrice>,
+    allowed_targets: Option<HashSet<eth::ContractAddress>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    /// The base URL for the 1Inch swap API.
+    /// The chain ID identifying the network to request quotes for.
+    pub chain_id: eth::ChainId,
+
+    /// The base URL for the 1Inch swap API. Defaults to the public API
+    /// endpoint of the configured version and chain.
     pub endpoint: Option<reqwest::Url>,
 
+    /// The version of the 1Inch swap API to use.
+    pub version: Version,
+
+    /// The key to authenticate with as bearer token, which the public v6 API
+    /// requires.
+    pub api_key: Option<String>,
+
     /// The address of the Settlement contract.
     pub settlement: eth::ContractAddress,
 
@@ -37,6 +65,11 @@ pub struct Config {
     /// the positive slippage that 1Inch collects.
     pub referrer: Option<H160>,
 
+    /// The partner fee in basis points of the sell amount. It is sent to the
+    /// referrer address (or the settlement contract if no referrer is
+    /// configured). At most [`MAX_FEE_BPS`].
+    pub fee_bps: Option<u32>,
+
     // The following configuration options tweak the complexity of the 1Inch
     // route that the API returns. Unfortunately, the exact definition (and
     // what each field actually controls) is fairly opaque and not well
@@ -45,94 +78,435 @@ pub struct Config {
     pub connector_tokens: Option<u32>,
     pub complexity_level: Option<u32>,
 
+    /// The gas price 1Inch should assume when deciding whether a more complex
+    /// route is worth its gas cost. By default, 1Inch uses its own estimate.
+    pub gas_price: Option<GasPrice>,
+
+    /// Whether 1Inch may return a route that only swaps part of the sell
+    /// amount when there is not enough liquidity for all of it. Such swaps
+    /// are only used for partially fillable orders.
+    pub allow_partial_fill: bool,
+
+    /// API error status codes that indicate that no swap could be found for
+    /// an order.
+    pub not_found_status_codes: Vec<u16>,
+
+    /// How rate limited or otherwise transiently failing quote requests are
+    /// retried.
+    pub retry: util::http::RetryPolicy,
+
+    /// How often the list of available liquidity sources is refreshed when
+    /// excluding liquidity sources. `None` disables the refresh.
+    pub liquidity_refresh_interval: Option<Duration>,
+
+    /// How long to try initializing the solver before giving up. Defaults to
+    /// [`DEFAULT_INIT_TIMEOUT`].
+    pub init_timeout: Option<Duration>,
+
+    /// How long to wait before trying to initialize the solver again. Defaults
+    /// to [`DEFAULT_INIT_RETRY_DELAY`].
+    pub init_retry_delay: Option<Duration>,
+
+    /// A file for caching the spender address. The cached address is used if
+    /// fetching it from the API fails during initialization.
+    pub spender_cache_path: Option<PathBuf>,
+
+    /// How the requests for bootstrapping the solver (fetching the spender
+    /// and liquidity sources) are retried.
+    pub bootstrap_retry: util::http::RetryPolicy,
+
     /// Stream that yields every new block.
     pub block_stream: Option<CurrentBlockWatcher>,
+
+    /// Whether to follow HTTP redirects from the API.
+    pub follow_redirects: bool,
+
+    /// Optional request quota of the API, shared by all requests made by
+    /// this solver.
+    pub rate_limit: Option<super::RateLimit>,
+
+    /// The only contracts that swaps may call, if restricted.
+    pub allowed_targets: Option<HashSet<eth::ContractAddress>>,
+
+    /// How long to wait for a connection to the API to be established.
+    pub connect_timeout: Duration,
+
+    /// How long to wait for a response from the API, including connecting.
+    pub request_timeout: Duration,
+
+    /// An HTTP client shared with other DEX APIs to send requests with,
+    /// instead of building a dedicated one.
+    pub shared_client: Option<reqwest::Client>,
 }
 
-#[derive(Debug, Clone)]
-pub enum Liquidity {
-    Any,
-    Only(Vec<String>),
-    Exclude(Vec<String>),
+/// How long we try to initialize the solver before panicking by default.
+pub const DEFAULT_INIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait before trying to initialize the solver again by default.
+pub const DEFAULT_INIT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The maximum partner fee the 1Inch API accepts, in basis points (3%).
+pub const MAX_FEE_BPS: u32 = 300;
+
+/// A supported 1Inch swap API version.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    /// The deprecated v5.0 API. Kept around in order to be able to roll back.
+    V5,
+    #[default]
+    V6,
+}
+
+impl Version {
+    /// The public API endpoint for this version and the specified chain. All
+    /// paths (`swap`, `approve/spender`, ...) are relative to it.
+    pub fn default_endpoint(self, chain_id: eth::ChainId) -> reqwest::Url {
+        let chain_id = chain_id.network_id();
+        match self {
+            Self::V5 => format!("https://api.1inch.io/v5.0/{chain_id}/"),
+            Self::V6 => format!("https://api.1inch.dev/swap/v6.0/{chain_id}/"),
+        }
+        .parse()
+        .unwrap()
+    }
+}
+
+/// The gas price to include in quote requests.
+#[derive(Debug, Clone, Copy)]
+pub enum GasPrice {
+    /// Use the effective gas price of the auction being solved.
+    Auction,
+    /// Use a fixed gas price in wei.
+    Fixed(U256),
+}
+
+/// The 1Inch liquidity sources to route through.
+#[derive(Debug, Clone, Default)]
+pub struct Liquidity {
+    /// Only route through these sources. All sources supported by 1Inch are
+    /// considered if unset.
+    pub include: Option<Vec<String>>,
+    /// Never route through these sources.
+    pub exclude: Vec<String>,
+}
+
+/// Returns the API endpoint to use for the specified chain.
+///
+/// Custom endpoints are expected to follow the layout of the public API,
+/// where the last path segment is the chain ID. An endpoint for a different
+/// chain than the configured one is rejected, since it would silently return
+/// quotes for the wrong network.
+fn endpoint(
+    custom: Option<reqwest::Url>,
+    version: Version,
+    chain_id: eth::ChainId,
+) -> Result<reqwest::Url, Error> {
+    let Some(endpoint) = custom else {
+        return Ok(version.default_endpoint(chain_id));
+    };
+    let endpoint_chain_id = endpoint
+        .path_segments()
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .and_then(|segment| segment.parse::<u64>().ok());
+    match endpoint_chain_id {
+        Some(id) if id != chain_id.value().as_u64() => {
+            Err(Error::ChainMismatch { endpoint, chain_id })
+        }
+        _ => Ok(endpoint),
+    }
+}
+
+/// A file that caches the 1Inch spender address per chain, since it almost
+/// never changes.
+struct SpenderCache {
+    path: PathBuf,
+    chain_id: eth::ChainId,
 }
 
-pub const DEFAULT_URL: &str = "https://api.1inch.io/v5.0/1/";
+impl SpenderCache {
+    fn new(path: PathBuf, chain_id: eth::ChainId) -> Self {
+        Self { path, chain_id }
+    }
+
+    /// Reads the cached addresses of all chains.
+    fn read(&self) -> io::Result<BTreeMap<String, H160>> {
+        let contents = fs::read(&self.path)?;
+        serde_json::from_slice(&contents).map_err(io::Error::from)
+    }
+
+    fn load(&self) -> Option<H160> {
+        self.read().ok()?.get(self.chain_id.network_id()).copied()
+    }
+
+    fn store(&self, spender: H160) -> io::Result<()> {
+        let mut spenders = match self.read() {
+            Ok(spenders) => spenders,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Default::default(),
+            Err(err) => return Err(err),
+        };
+        if spenders.get(self.chain_id.network_id()) == Some(&spender) {
+            return Ok(());
+        }
+        spenders.insert(self.chain_id.network_id().to_owned(), spender);
+        fs::write(&self.path, serde_json::to_vec_pretty(&spenders)?)
+    }
+}
+
+/// Fetches the liquidity sources supported by 1Inch without the excluded ones.
+async fn fetch_protocols(
+    client: &super::Client,
+    endpoint: &reqwest::Url,
+    excluded: &[String],
+    retry: util::http::RetryPolicy,
+    not_found_status_codes: &[u16],
+) -> Result<Vec<String>, Error> {
+    let liquidity = retry
+        .retry(
+            || async {
+                util::http::roundtrip!(
+                    <dto::Liquidity, dto::Error>;
+                    client.request(reqwest::Method::GET, util::url::join(endpoint, "liquidity-sources")).await
+                )
+                .await
+                .map_err(|err| Error::from_roundtrip(err, not_found_status_codes))
+            },
+            Error::is_bootstrap_transient,
+            Error::retry_after,
+        )
+        .await?;
+
+    without_excluded(
+        liquidity.protocols.into_iter().map(|protocol| protocol.id),
+        excluded,
+    )
+}
+
+/// Removes the excluded liquidity sources, making sure that at least one
+/// source remains. Otherwise 1Inch would silently consider all sources.
+fn without_excluded(
+    protocols: impl IntoIterator<Item = String>,
+    excluded: &[String],
+) -> Result<Vec<String>, Error> {
+    let protocols = protocols
+        .into_iter()
+        .filter(|protocol| !excluded.contains(protocol))
+        .collect::<Vec<_>>();
+    if protocols.is_empty() {
+        return Err(Error::NoLiquidity);
+    }
+    Ok(protocols)
+}
+
+/// Periodically refreshes the liquidity sources, since 1Inch adds and removes
+/// sources over time. When a block stream is configured, refreshes happen on
+/// the first new block after the interval elapsed. The current sources are
+/// kept if refreshing fails. The task stops once the solver gets dropped.
+async fn refresh_protocols(
+    client: super::Client,
+    endpoint: reqwest::Url,
+    excluded: Vec<String>,
+    interval: Duration,
+    retry: util::http::RetryPolicy,
+    not_found_status_codes: Vec<u16>,
+    protocols: Weak<RwLock<Option<Vec<String>>>>,
+) {
+    let mut blocks = client.block_stream.clone();
+    loop {
+        tokio::time::sleep(interval).await;
+        if let Some(blocks) = &mut blocks {
+            if blocks.changed().await.is_err() {
+                return;
+            }
+        }
+        if protocols.strong_count() == 0 {
+            return;
+        }
+
+        let refreshed = match fetch_protocols(
+            &client,
+            &endpoint,
+            &excluded,
+            retry,
+            &not_found_status_codes,
+        )
+        .await
+        {
+            Ok(refreshed) => refreshed,
+            Err(err) => {
+                tracing::warn!(?err, "failed to refresh oneinch liquidity sources");
+                continue;
+            }
+        };
+        let Some(protocols) = protocols.upgrade() else {
+            return;
+        };
+        let mut protocols = protocols.write().unwrap();
+        let current = protocols.clone().unwrap_or_default();
+        let added = refreshed
+            .iter()
+            .filter(|protocol| !current.contains(protocol))
+            .collect::<Vec<_>>();
+        let removed = current
+            .iter()
+            .filter(|protocol| !refreshed.contains(protocol))
+            .collect::<Vec<_>>();
+        if !added.is_empty() || !removed.is_empty() {
+            tracing::info!(?added, ?removed, "oneinch liquidity sources changed");
+        }
+        *protocols = Some(refreshed);
+    }
+}
 
 impl OneInch {
-    /// Initializes a new solver instance. Panics if it doesn't succeed after a
-    /// short period of time.
+    /// Initializes a new solver instance. Panics if it doesn't succeed within
+    /// the configured initialization timeout.
     pub async fn new(config: Config) -> Self {
-        /// How long we try to initialize the solver before panicking.
-        const INIT_TIMEOUT: Duration = Duration::from_secs(10);
-        /// How long to wait before trying to initialize the solver again.
-        const RETRY_DELAY: Duration = Duration::from_secs(1);
+        let init_timeout = config.init_timeout.unwrap_or(DEFAULT_INIT_TIMEOUT);
+        let init_retry_delay = config.init_retry_delay.unwrap_or(DEFAULT_INIT_RETRY_DELAY);
 
         let start = Instant::now();
+        let mut attempts = 0;
         loop {
+            attempts += 1;
             let error = match Self::try_new(config.clone()).await {
                 Ok(solver) => return solver,
+                Err(
+                    err @ (Error::ChainMismatch { .. }
+                    | Error::InvalidFee(_)
+                    | Error::InvalidApiKey
+                    | Error::NoLiquidity),
+                ) => {
+                    panic!("invalid oneinch solver configuration: {err}")
+                }
                 Err(err) => err,
             };
 
-            if start.elapsed() > INIT_TIMEOUT {
-                panic!("could not initialize oneinch solver in time");
+            if start.elapsed() > init_timeout {
+                panic!(
+                    "could not initialize oneinch solver in time after {attempts} attempts: \
+                     {error}"
+                );
             } else {
                 tracing::warn!(?error, "failed to initialize oneinch solver; trying again");
-                tokio::time::sleep(RETRY_DELAY).await;
+                tokio::time::sleep(init_retry_delay).await;
             }
         }
     }
 
     async fn try_new(config: Config) -> Result<Self, Error> {
-        let client = super::Client::new(Default::default(), config.block_stream);
-        let endpoint = config
-            .endpoint
-            .unwrap_or_else(|| DEFAULT_URL.parse().unwrap());
-
-        let protocols = match config.liquidity {
-            Liquidity::Any => None,
-            Liquidity::Only(protocols) => Some(protocols),
-            Liquidity::Exclude(excluded) => {
-                let liquidity = util::http::roundtrip!(
-                    <dto::Liquidity, dto::Error>;
-                    client.request(reqwest::Method::GET, util::url::join(&endpoint, "liquidity-sources"))
-                )
-                .await?;
-
-                let protocols = liquidity
-                    .protocols
-                    .into_iter()
-                    .filter(|protocol| !excluded.contains(&protocol.id))
-                    .map(|protocol| protocol.id)
-                    .collect();
-                Some(protocols)
+        let client = match config.shared_client {
+            Some(client) => {
+                super::Client::shared(client, config.request_timeout, config.block_stream)
             }
+            None => super::Client::new(
+                reqwest::Client::builder()
+                    .redirect(super::redirect_policy(config.follow_redirects)),
+                config.connect_timeout,
+                config.request_timeout,
+                config.block_stream,
+            )
+            .map_err(|err| Error::Http(err.into()))?,
+        }
+        .with_headers(headers(config.api_key.as_deref())?)
+        .with_rate_limit(config.rate_limit);
+        let endpoint = endpoint(config.endpoint, config.version, config.chain_id)?;
+        let fee = config.fee_bps.map(dto::Fee::try_from_bps).transpose()?;
+
+        let backend = |sources: &[String]| -> Vec<String> {
+            sources
+                .iter()
+                .map(|source| super::sources::ONEINCH.backend(source))
+                .collect()
+        };
+        let excluded = backend(&config.liquidity.exclude);
+        let protocols = match &config.liquidity.include {
+            Some(included) => Some(without_excluded(backend(included), &excluded)?),
+            None if excluded.is_empty() => None,
+            None => Some(
+                fetch_protocols(
+                    &client,
+                    &endpoint,
+                    &excluded,
+                    config.bootstrap_retry,
+                    &config.not_found_status_codes,
+                )
+                .await?,
+            ),
         };
+        let refresh = protocols.is_some() && config.liquidity.include.is_none();
+        let protocols = Arc::new(RwLock::new(protocols));
+        if let (true, Some(interval)) = (refresh, config.liquidity_refresh_interval) {
+            tokio::spawn(refresh_protocols(
+                client.clone(),
+                endpoint.clone(),
+                excluded,
+                interval,
+                config.bootstrap_retry,
+                config.not_found_status_codes.clone(),
+                Arc::downgrade(&protocols),
+            ));
+        }
+
         let defaults = dto::Query {
             from_address: config.settlement.0,
-            protocols,
             referrer_address: Some(config.referrer.unwrap_or(config.settlement.0)),
+            fee,
             disable_estimate: Some(true),
             main_route_parts: config.main_route_parts,
             connector_tokens: config.connector_tokens,
             complexity_level: config.complexity_level,
+            allow_partial_fill: config.allow_partial_fill.then_some(true),
             ..Default::default()
         };
 
-        let spender = eth::ContractAddress(
-            util::http::roundtrip!(
-                <dto::Spender, dto::Error>;
-                client.request(reqwest::Method::GET, util::url::join(&endpoint, "approve/spender"))
+        let spender = config
+            .bootstrap_retry
+            .retry(
+                || async {
+                    util::http::roundtrip!(
+                        <dto::Spender, dto::Error>;
+                        client.request(reqwest::Method::GET, util::url::join(&endpoint, "approve/spender")).await
+                    )
+                    .await
+                    .map_err(|err| Error::from_roundtrip(err, &config.not_found_status_codes))
+                },
+                Error::is_bootstrap_transient,
+                Error::retry_after,
             )
-            .await?
-            .address,
-        );
+            .await
+            .map(|spender| spender.address);
+        let cache = config
+            .spender_cache_path
+            .map(|path| SpenderCache::new(path, config.chain_id));
+        let spender = eth::ContractAddress(match (spender, &cache) {
+            (Ok(spender), Some(cache)) => {
+                if let Err(err) = cache.store(spender) {
+                    tracing::warn!(?err, "failed to cache oneinch spender address");
+                }
+                spender
+            }
+            (Ok(spender), None) => spender,
+            (Err(err), Some(cache)) => match cache.load() {
+                Some(spender) => {
+                    tracing::warn!(?err, ?spender, "using cached oneinch spender address");
+                    spender
+                }
+                None => return Err(err),
+            },
+            (Err(err), None) => return Err(err),
+        });
 
         Ok(Self {
             client,
+            version: config.version,
             endpoint,
             defaults,
+            protocols,
             spender,
+            not_found_status_codes: config.not_found_status_codes,
+            retry: config.retry,
+            gas_price: config.gas_price,
+            allowed_targets: config.allowed_targets,
         })
     }
 
@@ -140,8 +514,18 @@ impl OneInch {
         &self,
         order: &dex::Order,
         slippage: &dex::Slippage,
+        tokens: &auction::Tokens,
+        gas_price: auction::GasPrice,
     ) -> Result<dex::Swap, Error> {
-        let query = self.defaults.clone().try_with_domain(order, slippage)?;
+        let query = dto::Query {
+            protocols: self.protocols.read().unwrap().clone(),
+            gas_price: self.gas_price.map(|price| match price {
+                GasPrice::Auction => gas_price.0 .0,
+                GasPrice::Fixed(price) => price,
+            }),
+            ..self.defaults.clone()
+        }
+        .try_with_domain(order, slippage)?;
         let swap = {
             // Set up a tracing span to make debugging of API requests easier.
             // Historically, debugging API requests to external DEXs was a bit
@@ -152,12 +536,27 @@ impl OneInch {
                 .instrument(tracing::trace_span!("quote", id = %id))
                 .await?
         };
+        let private_liquidity = swap.uses_private_liquidity();
+        let filled_amount =
+            (swap.from_token_amount < order.amount.get()).then_some(swap.from_token_amount);
+        let sources = super::sources::ONEINCH.route(
+            swap.protocols
+                .iter()
+                .flatten()
+                .flatten()
+                .map(|protocol| protocol.name.as_str()),
+        );
+        let gas = U256::from(swap.tx.gas);
+        let gas_cost_wei = swap
+            .tx
+            .gas_price
+            .and_then(|gas_price| gas.checked_mul(gas_price));
 
-        Ok(dex::Swap {
-            calls: vec![dex::Call {
-                to: eth::ContractAddress(swap.tx.to),
-                calldata: swap.tx.data,
-            }],
+        let mut swap = dex::Swap {
+            calls: vec![dex::Call::swap(
+                eth::ContractAddress(swap.tx.to),
+                swap.tx.data,
+            )],
             input: eth::Asset {
                 token: order.sell,
                 amount: swap.from_token_amount,
@@ -170,18 +569,53 @@ impl OneInch {
                 spender: self.spender,
                 amount: dex::Amount::new(swap.from_token_amount),
             },
-            gas: eth::Gas(swap.tx.gas.into()),
-        })
+            gas: eth::Gas(gas),
+            metadata: dex::Metadata {
+                private_liquidity,
+                gas_cost_wei,
+                sources,
+                gas_confidence: dex::GasConfidence::Measured,
+                filled_amount,
+                ..Default::default()
+            },
+        };
+        if let Some(target) = super::disallowed_target(&swap, self.allowed_targets.as_ref()) {
+            return Err(Error::DisallowedTarget(target));
+        }
+        swap.metadata.price_impact = swap.price_impact(tokens);
+        Ok(swap)
     }
 
+    /// Requests a quote, retrying rate limited and transient errors according
+    /// to the retry policy.
     async fn quote(&self, query: &dto::Query) -> Result<dto::Swap, Error> {
-        let swap = util::http::roundtrip!(
-            <dto::Swap, dto::Error>;
-            self.client
-                .request(reqwest::Method::GET, util::url::join(&self.endpoint, "swap"))
-                .query(query)
-        )
-        .await?;
+        let request = || {
+            self.client.request(
+                reqwest::Method::GET,
+                util::url::join(&self.endpoint, "swap"),
+            )
+        };
+        let swap = match self.version {
+            Version::V5 => {
+                util::http::roundtrip_retry!(
+                    <dto::Swap, dto::Error>;
+                    &self.retry,
+                    || async move { request().await.query(query) }
+                )
+                .await
+            }
+            Version::V6 => {
+                let v6 = &dto::v6::Query::from(query);
+                util::http::roundtrip_retry!(
+                    <dto::v6::Swap, dto::Error>;
+                    &self.retry,
+                    || async move { request().await.query(v6) }
+                )
+                .await
+                .map(|swap| swap.into_v5(query.amount))
+            }
+        }
+        .map_err(|err| Error::from_roundtrip(err, &self.not_found_status_codes))?;
 
         Ok(swap)
     }
@@ -193,37 +627,249 @@ pub enum Error {
     OrderNotSupported,
     #[error("no valid swap could be found")]
     NotFound,
+    #[error("insufficient liquidity for the swap")]
+    InsufficientLiquidity,
     #[error("rate limited")]
-    RateLimited,
+    RateLimited { retry_after: Option<Duration> },
     #[error("api error {code}: {description}")]
     Api { code: i32, description: String },
+    #[error("no liquidity sources left after applying the liquidity filters")]
+    NoLiquidity,
+    #[error("partner fee of {0} bps exceeds the maximum of {MAX_FEE_BPS} bps")]
+    InvalidFee(u32),
+    #[error("endpoint {endpoint} does not serve chain {chain_id:?}")]
+    ChainMismatch {
+        endpoint: reqwest::Url,
+        chain_id: eth::ChainId,
+    },
+    #[error("swap calls disallowed target {0:?}")]
+    DisallowedTarget(eth::ContractAddress),
+    #[error("API key is not a valid header value")]
+    InvalidApiKey,
     #[error(transparent)]
     Http(util::http::Error),
 }
 
-impl From<util::http::RoundtripError<dto::Error>> for Error {
-    fn from(err: util::http::RoundtripError<dto::Error>) -> Self {
+/// The headers to send with every request, authenticating with the API key as
+/// bearer token if configured.
+fn headers(api_key: Option<&str>) -> Result<reqwest::header::HeaderMap, Error> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(api_key) = api_key {
+        let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {api_key}"))
+            .map_err(|_| Error::InvalidApiKey)?;
+        value.set_sensitive(true);
+        headers.insert(reqwest::header::AUTHORIZATION, value);
+    }
+    Ok(headers)
+}
+
+impl Error {
+    /// Whether a request for bootstrapping the solver may succeed when
+    /// retried. Unlike quotes, these are also retried on malformed responses,
+    /// since the solver can't start without them.
+    fn is_bootstrap_transient(&self) -> bool {
+        matches!(self, Self::Http(_) | Self::RateLimited { .. })
+    }
+
+    /// How long the API asked us to wait before retrying.
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Categorizes a roundtrip error, treating API errors with any of the
+    /// specified status codes as [`Error::NotFound`].
+    fn from_roundtrip(
+        err: util::http::RoundtripError<dto::Error>,
+        not_found_status_codes: &[u16],
+    ) -> Self {
         match err {
             util::http::RoundtripError::Http(http_err) => match http_err {
-                util::http::Error::Status(status_code, _) if status_code.as_u16() == 429 => {
-                    Self::RateLimited
+                util::http::Error::TooManyRequests { retry_after, .. } => {
+                    Self::RateLimited { retry_after }
                 }
                 other_err => Self::Http(other_err),
             },
             util::http::RoundtripError::Api(err) => {
-                // Unfortunately, AFAIK these codes aren't documented anywhere. These
-                // based on empirical observations of what the API has returned in the
-                // past.
-                match err.status_code {
-                    // 403 is returned when the 1inch quote API is forbidden due to legal reason for
-                    // a specific address or an artificial address was used in the request.
-                    400 | 403 => Self::NotFound,
-                    _ => Self::Api {
+                if err.status_code == 400
+                    && err
+                        .description
+                        .to_lowercase()
+                        .contains("insufficient liquidity")
+                {
+                    return Self::InsufficientLiquidity;
+                }
+                let not_found = u16::try_from(err.status_code)
+                    .is_ok_and(|code| not_found_status_codes.contains(&code));
+                if not_found {
+                    Self::NotFound
+                } else {
+                    Self::Api {
                         code: err.status_code,
                         description: err.description,
-                    },
+                    }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_error(status_code: i32) -> util::http::RoundtripError<dto::Error> {
+        api_error_with_description(status_code, "error")
+    }
+
+    fn api_error_with_description(
+        status_code: i32,
+        description: &str,
+    ) -> util::http::RoundtripError<dto::Error> {
+        util::http::RoundtripError::Api(dto::Error {
+            status_code,
+            description: description.to_owned(),
+        })
+    }
+
+    #[test]
+    fn distinguishes_insufficient_liquidity() {
+        let not_found_status_codes = [400, 403];
+
+        assert!(matches!(
+            Error::from_roundtrip(
+                api_error_with_description(400, "insufficient liquidity"),
+                &not_found_status_codes
+            ),
+            Error::InsufficientLiquidity
+        ));
+        assert!(matches!(
+            Error::from_roundtrip(
+                api_error_with_description(400, "cannot estimate"),
+                &not_found_status_codes
+            ),
+            Error::NotFound
+        ));
+        assert!(matches!(
+            Error::from_roundtrip(
+                api_error_with_description(403, "Insufficient liquidity"),
+                &not_found_status_codes
+            ),
+            Error::NotFound
+        ));
+    }
+
+    #[test]
+    fn custom_not_found_status_codes() {
+        let not_found_status_codes = [404, 422];
+
+        assert!(matches!(
+            Error::from_roundtrip(api_error(404), &not_found_status_codes),
+            Error::NotFound
+        ));
+        assert!(matches!(
+            Error::from_roundtrip(api_error(422), &not_found_status_codes),
+            Error::NotFound
+        ));
+        assert!(matches!(
+            Error::from_roundtrip(api_error(400), &not_found_status_codes),
+            Error::Api { code: 400, .. }
+        ));
+    }
+
+    #[test]
+    fn caches_spender_per_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spender.json");
+        let mainnet = SpenderCache::new(path.clone(), eth::ChainId::Mainnet);
+        let gnosis = SpenderCache::new(path, eth::ChainId::Gnosis);
+
+        assert_eq!(mainnet.load(), None);
+        mainnet.store(H160([1; 20])).unwrap();
+        gnosis.store(H160([2; 20])).unwrap();
+        assert_eq!(mainnet.load(), Some(H160([1; 20])));
+        assert_eq!(gnosis.load(), Some(H160([2; 20])));
+    }
+
+    #[test]
+    fn excludes_from_included_liquidity() {
+        let sources = |sources: &[&str]| {
+            sources
+                .iter()
+                .map(|source| source.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            without_excluded(
+                sources(&["UNISWAP_V3", "CURVE", "PMM1"]),
+                &sources(&["PMM1"])
+            )
+            .unwrap(),
+            sources(&["UNISWAP_V3", "CURVE"])
+        );
+        assert!(matches!(
+            without_excluded(sources(&["PMM1"]), &sources(&["PMM1"])),
+            Err(Error::NoLiquidity)
+        ));
+    }
+
+    #[test]
+    fn chain_aware_endpoint() {
+        for (chain_id, url) in [
+            (eth::ChainId::Mainnet, "https://api.1inch.dev/swap/v6.0/1/"),
+            (eth::ChainId::Gnosis, "https://api.1inch.dev/swap/v6.0/100/"),
+            (
+                eth::ChainId::ArbitrumOne,
+                "https://api.1inch.dev/swap/v6.0/42161/",
+            ),
+        ] {
+            assert_eq!(endpoint(None, Version::V6, chain_id).unwrap().as_str(), url);
+        }
+        assert_eq!(
+            endpoint(None, Version::V5, eth::ChainId::Gnosis)
+                .unwrap()
+                .as_str(),
+            "https://api.1inch.io/v5.0/100/"
+        );
+
+        let custom = |url: &str| Some(url.parse().unwrap());
+        assert!(matches!(
+            endpoint(
+                custom("https://proxy.io/v6.0/1/"),
+                Version::V6,
+                eth::ChainId::Gnosis
+            ),
+            Err(Error::ChainMismatch { .. })
+        ));
+        assert!(endpoint(
+            custom("https://proxy.io/v6.0/100/"),
+            Version::V6,
+            eth::ChainId::Gnosis
+        )
+        .is_ok());
+        assert!(endpoint(
+            custom("https://proxy.io/"),
+            Version::V6,
+            eth::ChainId::Gnosis
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn authenticates_with_bearer_token() {
+        assert!(headers(None).unwrap().is_empty());
+
+        let headers = headers(Some("secret")).unwrap();
+        let authorization = &headers[reqwest::header::AUTHORIZATION];
+        assert_eq!(authorization, "Bearer secret");
+        assert!(authorization.is_sensitive());
+
+        assert!(matches!(
+            super::headers(Some("invalid\nkey")),
+            Err(Error::InvalidApiKey)
+        ));
+    }
+}
@@ -5,6 +5,7 @@ use {
     },
     ethereum_types::Address,
     ethrpc::block_stream::CurrentBlockWatcher,
+    std::time::Duration,
 };
 
 mod dto;
@@ -14,6 +15,7 @@ pub const DEFAULT_URL: &str = "https://apiv5.paraswap.io";
 /// Bindings to the ParaSwap API.
 pub struct ParaSwap {
     client: super::Client,
+    retry: util::http::RetryPolicy,
     config: Config,
 }
 
@@ -43,6 +45,27 @@ pub struct Config {
 
     /// A stream that yields every new block.
     pub block_stream: Option<CurrentBlockWatcher>,
+
+    /// Whether to follow HTTP redirects from the API.
+    pub follow_redirects: bool,
+
+    /// Optional request quota of the API, shared by all requests made by
+    /// this solver.
+    pub rate_limit: Option<super::RateLimit>,
+
+    /// How long to wait for a connection to the API to be established.
+    pub connect_timeout: Duration,
+
+    /// How long to wait for a response from the API, including connecting.
+    pub request_timeout: Duration,
+
+    /// An HTTP client shared with other DEX APIs to send requests with,
+    /// instead of building a dedicated one.
+    pub shared_client: Option<reqwest::Client>,
+
+    /// How failed requests are retried. Requests are only sent once if not
+    /// specified.
+    pub retry: Option<util::http::RetryPolicy>,
 }
 
 impl ParaSwap {
@@ -54,13 +77,25 @@ impl ParaSwap {
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert("x-api-key", key);
 
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .unwrap();
+        let client = match config.shared_client.clone() {
+            Some(client) => {
+                super::Client::shared(client, config.request_timeout, config.block_stream.clone())
+            }
+            None => super::Client::new(
+                reqwest::Client::builder()
+                    .redirect(super::redirect_policy(config.follow_redirects)),
+                config.connect_timeout,
+                config.request_timeout,
+                config.block_stream.clone(),
+            )
+            .unwrap(),
+        };
 
         Self {
-            client: super::Client::new(client, config.block_stream.clone()),
+            client: client
+                .with_headers(headers)
+                .with_rate_limit(config.rate_limit),
+            retry: config.retry.unwrap_or_else(util::http::RetryPolicy::none),
             config,
         }
     }
@@ -72,18 +107,23 @@ impl ParaSwap {
         slippage: &dex::Slippage,
         tokens: &auction::Tokens,
     ) -> Result<dex::Swap, Error> {
-        let query = dto::SwapQuery::new(&self.config, order, tokens, slippage)?;
-        let swap = util::http::roundtrip!(
+        let query = &dto::SwapQuery::new(&self.config, order, tokens, slippage)?;
+        let swap = util::http::roundtrip_retry!(
             <dto::Swap, dto::Error>;
-            self.client.request(reqwest::Method::GET, util::url::join(&self.config.endpoint, "swap"))
-                .query(&query)
+            &self.retry,
+            || async move {
+                self.client
+                    .request(reqwest::Method::GET, util::url::join(&self.config.endpoint, "swap"))
+                    .await
+                    .query(query)
+            }
         )
         .await?;
         Ok(dex::Swap {
-            calls: vec![dex::Call {
-                to: eth::ContractAddress(swap.tx_params.to),
-                calldata: swap.tx_params.data,
-            }],
+            calls: vec![dex::Call::swap(
+                eth::ContractAddress(swap.tx_params.to),
+                swap.tx_params.data,
+            )],
             input: eth::Asset {
                 token: order.sell,
                 amount: swap.price_route.src_amount,
@@ -97,6 +137,10 @@ impl ParaSwap {
                 amount: dex::Amount::new(swap.price_route.src_amount),
             },
             gas: eth::Gas(swap.price_route.gas_cost),
+            metadata: dex::Metadata {
+                gas_confidence: dex::GasConfidence::Measured,
+                ..Default::default()
+            },
         })
     }
 }
@@ -121,9 +165,7 @@ impl From<util::http::RoundtripError<dto::Error>> for Error {
     fn from(err: util::http::RoundtripError<dto::Error>) -> Self {
         match err {
             util::http::RoundtripError::Http(http_err) => match http_err {
-                util::http::Error::Status(status_code, _) if status_code.as_u16() == 429 => {
-                    Self::RateLimited
-                }
+                util::http::Error::TooManyRequests { .. } => Self::RateLimited,
                 other_err => Self::Http(other_err),
             },
             util::http::RoundtripError::Api(err) => match err.error.as_str() {
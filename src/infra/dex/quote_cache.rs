@@ -0,0 +1,125 @@
+use {
+    crate::domain::{auction, dex, eth},
+    ethrpc::block_stream::CurrentBlockWatcher,
+    moka::future::Cache,
+};
+
+/// Caches the swaps quoted by a DEX API for the current block. Quotes are
+/// only valid for roughly one block, so all entries are dropped as soon as a
+/// new block arrives.
+pub struct QuoteCache {
+    blocks: CurrentBlockWatcher,
+    cache: Cache<Key, dex::Swap>,
+}
+
+/// Quotes are keyed on the block they were requested in, the order and the
+/// gas price, since some DEX APIs take the latter into account for routing.
+type Key = (u64, dex::Order, eth::Ether);
+
+impl QuoteCache {
+    pub fn new(max_size: u64, blocks: CurrentBlockWatcher) -> Self {
+        let cache = Cache::new(max_size);
+        tokio::spawn(invalidate_on_new_block(blocks.clone(), cache.clone()));
+        Self { blocks, cache }
+    }
+
+    /// The current block. It should be captured before requesting a quote,
+    /// so that the quote is cached for the block it was requested in, even if
+    /// a new block arrives while the request is in flight.
+    pub fn block(&self) -> u64 {
+        self.blocks.borrow().number
+    }
+
+    /// Returns the swap quoted for the order and gas price in the block, if
+    /// any.
+    pub async fn get(
+        &self,
+        block: u64,
+        order: &dex::Order,
+        gas_price: auction::GasPrice,
+    ) -> Option<dex::Swap> {
+        self.cache.get(&(block, order.clone(), gas_price.0)).await
+    }
+
+    /// Caches the swap quoted for the order and gas price in the block.
+    pub async fn insert(
+        &self,
+        block: u64,
+        order: &dex::Order,
+        gas_price: auction::GasPrice,
+        swap: &dex::Swap,
+    ) {
+        self.cache
+            .insert((block, order.clone(), gas_price.0), swap.clone())
+            .await;
+    }
+}
+
+/// Drops all cached quotes whenever a new block arrives. Entries are keyed on
+/// the block number as well, so a late invalidation never serves stale
+/// quotes, but only keeps them around for a little longer.
+async fn invalidate_on_new_block(mut blocks: CurrentBlockWatcher, cache: Cache<Key, dex::Swap>) {
+    while blocks.changed().await.is_ok() {
+        cache.invalidate_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::domain::dex::testing::{asset, order, swap},
+        ethereum_types::U256,
+        ethrpc::block_stream::BlockInfo,
+    };
+
+    fn quoted() -> dex::Swap {
+        swap(
+            asset(order(1_000).sell, 1_000),
+            asset(order(1_000).buy, 2_000),
+        )
+    }
+
+    fn gas_price(wei: u64) -> auction::GasPrice {
+        auction::GasPrice(eth::Ether(U256::from(wei)))
+    }
+
+    #[tokio::test]
+    async fn keys_quotes_on_gas_price() {
+        let (_sender, blocks) = tokio::sync::watch::channel(BlockInfo {
+            number: 1,
+            ..Default::default()
+        });
+        let cache = QuoteCache::new(10, blocks);
+
+        cache
+            .insert(1, &order(1_000), gas_price(10), &quoted())
+            .await;
+        assert!(cache.get(1, &order(1_000), gas_price(10)).await.is_some());
+        assert!(cache.get(1, &order(1_000), gas_price(20)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn caches_quotes_for_the_block_they_were_requested_in() {
+        let (sender, blocks) = tokio::sync::watch::channel(BlockInfo {
+            number: 1,
+            ..Default::default()
+        });
+        let cache = QuoteCache::new(10, blocks);
+
+        // A new block arrives while the quote requested in block 1 is in
+        // flight.
+        let block = cache.block();
+        sender.send_modify(|block| block.number = 2);
+        tokio::task::yield_now().await;
+        cache
+            .insert(block, &order(1_000), gas_price(10), &quoted())
+            .await;
+
+        assert_eq!(cache.block(), 2);
+        assert!(cache
+            .get(cache.block(), &order(1_000), gas_price(10))
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,128 @@
+//! DTOs for recorded swap fixtures. Each fixture file contains a single JSON
+//! object with the quoted order and the swap that was returned for it.
+
+use {
+    crate::{
+        domain::{dex, eth, order},
+        util::serialize,
+    },
+    ethereum_types::{H160, U256},
+    serde::Deserialize,
+    serde_with::serde_as,
+};
+
+/// A recorded request→response pair.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Fixture {
+    pub order: Order,
+    pub swap: Swap,
+}
+
+/// The order that was quoted.
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Order {
+    pub sell_token: H160,
+    pub buy_token: H160,
+    pub side: Side,
+    /// The sell amount for sell orders or the buy amount for buy orders.
+    #[serde_as(as = "serialize::U256")]
+    pub amount: U256,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// The recorded swap.
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Swap {
+    pub calls: Vec<Call>,
+    #[serde_as(as = "serialize::U256")]
+    pub input_amount: U256,
+    #[serde_as(as = "serialize::U256")]
+    pub output_amount: U256,
+    pub spender: H160,
+    #[serde_as(as = "serialize::U256")]
+    pub allowance: U256,
+    #[serde_as(as = "serialize::U256")]
+    pub gas: U256,
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Call {
+    pub to: H160,
+    #[serde_as(as = "serialize::Hex")]
+    pub calldata: Vec<u8>,
+}
+
+impl Order {
+    /// The normalized lookup key for the recorded order.
+    pub fn key(&self) -> Key {
+        Key {
+            sell: self.sell_token,
+            buy: self.buy_token,
+            side: match self.side {
+                Side::Buy => order::Side::Buy,
+                Side::Sell => order::Side::Sell,
+            },
+            amount: self.amount,
+        }
+    }
+}
+
+/// The normalized query that fixtures are matched on.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Key {
+    sell: H160,
+    buy: H160,
+    side: order::Side,
+    amount: U256,
+}
+
+impl Key {
+    pub fn from_domain(order: &dex::Order) -> Self {
+        Self {
+            sell: order.sell.0,
+            buy: order.buy.0,
+            side: order.side,
+            amount: order.amount.get(),
+        }
+    }
+}
+
+impl Swap {
+    /// Converts the recorded swap into a domain swap for the specified order.
+    pub fn to_domain(&self, order: &dex::Order) -> dex::Swap {
+        dex::Swap {
+            calls: self
+                .calls
+                .iter()
+                .map(|call| dex::Call::swap(eth::ContractAddress(call.to), call.calldata.clone()))
+                .collect(),
+            input: eth::Asset {
+                token: order.sell,
+                amount: self.input_amount,
+            },
+            output: eth::Asset {
+                token: order.buy,
+                amount: self.output_amount,
+            },
+            allowance: dex::Allowance {
+                spender: eth::ContractAddress(self.spender),
+                amount: dex::Amount::new(self.allowance),
+            },
+            gas: eth::Gas(self.gas),
+            metadata: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use {
+    crate::domain::dex,
+    std::{collections::HashMap, fs, io, path::PathBuf},
+};
+
+mod dto;
+
+/// An offline DEX that answers swaps from a directory of recorded fixtures
+/// instead of querying an external API. This allows running the full solver
+/// engine deterministically and without any network access.
+pub struct Replay {
+    fixtures: HashMap<dto::Key, dto::Swap>,
+}
+
+pub struct Config {
+    /// The directory containing the recorded fixtures, one JSON file per
+    /// quoted order.
+    pub fixtures: PathBuf,
+}
+
+impl Replay {
+    pub fn new(config: Config) -> Result<Self, CreationError> {
+        let mut fixtures = HashMap::new();
+        for entry in fs::read_dir(&config.fixtures)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let fixture = serde_json::from_slice::<dto::Fixture>(&fs::read(&path)?)
+                .map_err(|err| CreationError::Fixture(path.clone(), err))?;
+            if fixtures.insert(fixture.order.key(), fixture.swap).is_some() {
+                return Err(CreationError::Duplicate(path));
+            }
+        }
+        Ok(Self { fixtures })
+    }
+
+    pub fn swap(&self, order: &dex::Order) -> Result<dex::Swap, Error> {
+        self.fixtures
+            .get(&dto::Key::from_domain(order))
+            .map(|swap| swap.to_domain(order))
+            .ok_or(Error::NotFound)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CreationError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("invalid fixture {0:?}: {1}")]
+    Fixture(PathBuf, serde_json::Error),
+    #[error("fixture {0:?} duplicates the order of another fixture")]
+    Duplicate(PathBuf),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no fixture recorded for the order")]
+    NotFound,
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::domain::eth, ethereum_types::U256};
+
+    #[test]
+    fn replays_recorded_swaps() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("weth-cow.json"),
+            r#"{
+                "order": {
+                    "sellToken": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                    "buyToken": "0xdef1ca1fb7fbcdc777520aa7f396b4e015f497ab",
+                    "side": "sell",
+                    "amount": "1000000000000000000"
+                },
+                "swap": {
+                    "calls": [{ "to": "0x1111111254eeb25477b68fb85ed929f73a960582", "calldata": "0x12aa3caf" }],
+                    "inputAmount": "1000000000000000000",
+                    "outputAmount": "5000000000000000000000",
+                    "spender": "0x1111111254eeb25477b68fb85ed929f73a960582",
+                    "allowance": "1000000000000000000",
+                    "gas": "200000"
+                }
+            }"#,
+        )
+        .unwrap();
+        let replay = Replay::new(Config {
+            fixtures: dir.path().to_owned(),
+        })
+        .unwrap();
+
+        let order = |amount: u64| dex::Order {
+            sell: eth::TokenAddress("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".parse().unwrap()),
+            buy: eth::TokenAddress("def1ca1fb7fbcdc777520aa7f396b4e015f497ab".parse().unwrap()),
+            ..dex::testing::order(U256::from(amount) * U256::exp10(18))
+        };
+
+        let swap = replay.swap(&order(1)).unwrap();
+        assert_eq!(swap.output.amount, U256::from(5_000) * U256::exp10(18));
+        assert_eq!(swap.calls[0].calldata, [0x12, 0xaa, 0x3c, 0xaf]);
+        assert!(matches!(replay.swap(&order(2)), Err(Error::NotFound)));
+    }
+}
@@ -5,7 +5,7 @@ use {
     },
     contracts::ethcontract::{self, web3},
     ethereum_types::{Address, U256},
-    ethrpc::extensions::EthExt,
+    ethrpc::{block_stream::CurrentBlockWatcher, extensions::EthExt},
     std::collections::HashMap,
 };
 
@@ -15,19 +15,24 @@ pub struct Simulator {
     web3: ethrpc::Web3,
     settlement: eth::ContractAddress,
     authenticator: eth::ContractAddress,
+    block_stream: Option<CurrentBlockWatcher>,
 }
 
 impl Simulator {
-    /// Create a new simulator for computing DEX swap gas usage.
+    /// Create a new simulator for computing DEX swap gas usage. Simulations
+    /// run at the current block of the block stream if there is one, and at
+    /// the latest block otherwise.
     pub fn new(
         url: &reqwest::Url,
         settlement: eth::ContractAddress,
         authenticator: eth::ContractAddress,
+        block_stream: Option<CurrentBlockWatcher>,
     ) -> Self {
         Self {
             web3: blockchain::rpc(url),
             settlement,
             authenticator,
+            block_stream,
         }
     }
 
@@ -35,6 +40,41 @@ impl Simulator {
     ///
     /// This will return a `None` if the gas simulation is unavailable.
     pub async fn gas(&self, owner: Address, swap: &dex::Swap) -> Result<eth::Gas, Error> {
+        let gas = self.simulate(owner, swap).await?;
+
+        // `gas == 0` means that the simulation is not possible. See
+        // `Swapper.sol` contract for more details. In this case, use the
+        // heuristic gas amount from the swap.
+        Ok(if gas.is_zero() {
+            tracing::info!(
+                gas = ?swap.gas,
+                "could not simulate dex swap to get gas used; fall back to gas estimate provided \
+                 by dex API"
+            );
+            swap.gas
+        } else {
+            eth::Gas(gas)
+        })
+    }
+
+    /// Simulates the calls of a single order DEX swap and returns whether
+    /// they execute without reverting. Swaps that cannot be simulated (see
+    /// [`Self::gas`]) are assumed to succeed, while node failures are
+    /// returned as errors.
+    pub async fn succeeds(&self, owner: Address, swap: &dex::Swap) -> Result<bool, Error> {
+        match self.simulate(owner, swap).await {
+            Ok(_) => Ok(true),
+            Err(Error::Web3(err)) if is_revert(&err) => {
+                tracing::debug!(?err, "dex swap simulation reverted");
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Executes the swap through the `Swapper` contract and returns the gas
+    /// it used, or 0 if the simulation is not possible.
+    async fn simulate(&self, owner: Address, swap: &dex::Swap) -> Result<U256, Error> {
         if owner == self.settlement.0 {
             // we can't have both the settlement and swapper contracts at the same address
             return Err(Error::SettlementContractIsOwner);
@@ -95,34 +135,21 @@ impl Simulator {
             ),
         ]);
 
+        let block = match &self.block_stream {
+            Some(stream) => web3::types::BlockNumber::Number(stream.borrow().number.into()),
+            None => web3::types::BlockNumber::Latest,
+        };
         let return_data = self
             .web3
             .eth()
-            .call_with_state_overrides(call, web3::types::BlockNumber::Latest.into(), overrides)
+            .call_with_state_overrides(call, block.into(), overrides)
             .await?
             .0;
 
-        let gas = {
-            if return_data.len() != 32 {
-                return Err(Error::InvalidReturnData);
-            }
-
-            U256::from_big_endian(&return_data)
-        };
-
-        // `gas == 0` means that the simulation is not possible. See
-        // `Swapper.sol` contract for more details. In this case, use the
-        // heuristic gas amount from the swap.
-        Ok(if gas.is_zero() {
-            tracing::info!(
-                gas = ?swap.gas,
-                "could not simulate dex swap to get gas used; fall back to gas estimate provided \
-                 by dex API"
-            );
-            swap.gas
-        } else {
-            eth::Gas(gas)
-        })
+        if return_data.len() != 32 {
+            return Err(Error::InvalidReturnData);
+        }
+        Ok(U256::from_big_endian(&return_data))
     }
 }
 
@@ -130,6 +157,19 @@ impl Simulator {
 #[error("error initializing simulator: {0}")]
 pub struct InitializationError(#[from] ethcontract::errors::MethodError);
 
+/// Whether the node reports that the call reverted, as opposed to failing to
+/// execute it at all (e.g. because it is rate limited or timed out). Nodes
+/// report reverts with error code 3 when there is revert data, and with an
+/// "execution reverted" message otherwise.
+fn is_revert(err: &web3::error::Error) -> bool {
+    match err {
+        web3::error::Error::Rpc(err) => {
+            err.code.code() == 3 || err.message.to_lowercase().contains("execution reverted")
+        }
+        _ => false,
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("web3 error: {0:?}")]
@@ -141,3 +181,30 @@ pub enum Error {
     #[error("can't simulate gas for an order for which the settlement contract is the owner")]
     SettlementContractIsOwner,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_treats_reverts_as_failed_simulations() {
+        let rpc = |error: serde_json::Value| {
+            web3::error::Error::Rpc(serde_json::from_value(error).unwrap())
+        };
+
+        assert!(is_revert(&rpc(serde_json::json!({
+            "code": 3,
+            "message": "execution reverted: STF",
+            "data": "0x08c379a0",
+        }))));
+        assert!(is_revert(&rpc(serde_json::json!({
+            "code": -32000,
+            "message": "execution reverted",
+        }))));
+        assert!(!is_revert(&rpc(serde_json::json!({
+            "code": -32005,
+            "message": "rate limit exceeded",
+        }))));
+        assert!(!is_revert(&web3::error::Error::Unreachable));
+    }
+}
@@ -0,0 +1,122 @@
+use {
+    crate::infra,
+    std::{collections::HashMap, future::Future, hash::Hash, sync::Mutex},
+    tokio::sync::watch,
+};
+
+/// Deduplicates concurrent identical DEX API requests: while a request for a
+/// key is in flight, further requests for the same key wait for its result
+/// instead of being sent themselves.
+pub struct SingleFlight<K, V> {
+    calls: Mutex<HashMap<K, watch::Receiver<Option<V>>>>,
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            calls: Default::default(),
+        }
+    }
+
+    /// Runs the request for the key, unless an identical one is already in
+    /// flight, in which case its result (including errors) is shared. Should
+    /// the request in flight get cancelled, the waiters run their own.
+    pub async fn run(&self, key: K, request: impl Future<Output = V>) -> V {
+        let leader = {
+            let mut calls = self.calls.lock().unwrap();
+            match calls.get(&key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    calls.insert(key.clone(), receiver);
+                    Ok(sender)
+                }
+            }
+        };
+
+        match leader {
+            Ok(sender) => {
+                let _call = Call { flight: self, key };
+                let result = request.await;
+                let _ = sender.send(Some(result.clone()));
+                result
+            }
+            Err(mut receiver) => {
+                let shared = receiver
+                    .wait_for(Option::is_some)
+                    .await
+                    .ok()
+                    .and_then(|result| result.clone());
+                match shared {
+                    Some(result) => {
+                        infra::metrics::request_deduplicated();
+                        result
+                    }
+                    None => request.await,
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> Default for SingleFlight<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Removes a request from the ones in flight once it completes or gets
+/// cancelled.
+struct Call<'a, K: Eq + Hash, V> {
+    flight: &'a SingleFlight<K, V>,
+    key: K,
+}
+
+impl<K: Eq + Hash, V> Drop for Call<'_, K, V> {
+    fn drop(&mut self) {
+        self.flight.calls.lock().unwrap().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[tokio::test]
+    async fn shares_result_of_request_in_flight() {
+        let flight = SingleFlight::<u32, Result<u32, String>>::new();
+        let sent = AtomicUsize::new(0);
+        let request = |result: Result<u32, String>| {
+            let sent = &sent;
+            async move {
+                sent.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                result
+            }
+        };
+
+        let (first, second, other) = tokio::join!(
+            flight.run(1, request(Err("boom".to_owned()))),
+            flight.run(1, request(Ok(1))),
+            flight.run(2, request(Ok(2))),
+        );
+
+        assert_eq!(first, Err("boom".to_owned()));
+        assert_eq!(second, Err("boom".to_owned()));
+        assert_eq!(other, Ok(2));
+        assert_eq!(sent.load(Ordering::SeqCst), 2);
+        // Completed requests are no longer shared.
+        assert_eq!(flight.run(1, request(Ok(1))).await, Ok(1));
+    }
+}
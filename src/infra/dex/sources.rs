@@ -0,0 +1,89 @@
+//! Mappings between the liquidity source names used by individual DEX
+//! aggregators and the canonical [`Source`] taxonomy.
+
+use crate::domain::dex::Source;
+
+/// A table mapping canonical liquidity sources to the names a specific DEX
+/// aggregator uses for them.
+pub struct Mapping(&'static [(Source, &'static str)]);
+
+/// 0x liquidity source names.
+pub const ZEROEX: Mapping = Mapping(&[
+    (Source::UniswapV2, "Uniswap_V2"),
+    (Source::UniswapV3, "Uniswap_V3"),
+    (Source::SushiSwap, "SushiSwap"),
+    (Source::Curve, "Curve"),
+    (Source::BalancerV1, "Balancer_V1"),
+    (Source::BalancerV2, "Balancer_V2"),
+    (Source::Bancor, "Bancor"),
+    (Source::DodoV2, "DODO_V2"),
+    (Source::Lido, "Lido"),
+    (Source::ShibaSwap, "ShibaSwap"),
+]);
+
+/// 1Inch liquidity source (protocol) IDs.
+pub const ONEINCH: Mapping = Mapping(&[
+    (Source::UniswapV2, "UNISWAP_V2"),
+    (Source::UniswapV3, "UNISWAP_V3"),
+    (Source::SushiSwap, "SUSHI"),
+    (Source::Curve, "CURVE"),
+    (Source::BalancerV1, "BALANCER"),
+    (Source::BalancerV2, "BALANCER_V2"),
+    (Source::Bancor, "BANCOR"),
+    (Source::DodoV2, "DODO_V2"),
+    (Source::Lido, "ST_ETH"),
+    (Source::ShibaSwap, "SHIBASWAP"),
+]);
+
+impl Mapping {
+    /// Maps a liquidity source name reported by the aggregator to its
+    /// canonical source.
+    pub fn canonical(&self, name: &str) -> Source {
+        self.0
+            .iter()
+            .find(|(_, backend)| *backend == name)
+            .map(|(source, _)| source.clone())
+            .unwrap_or_else(|| Source::Other(name.to_owned()))
+    }
+
+    /// Maps a configured liquidity source name to the name the aggregator
+    /// uses. Configured names can either be canonical or aggregator specific,
+    /// the latter are passed through unchanged.
+    pub fn backend(&self, name: &str) -> String {
+        let source = Source::from_name(name);
+        self.0
+            .iter()
+            .find(|(known, _)| *known == source)
+            .map(|(_, backend)| (*backend).to_owned())
+            .unwrap_or_else(|| name.to_owned())
+    }
+
+    /// Maps the liquidity sources of a route to their canonical sources,
+    /// removing duplicates while preserving the route order.
+    pub fn route<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Vec<Source> {
+        let mut sources = Vec::new();
+        for source in names.into_iter().map(|name| self.canonical(name)) {
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        sources
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_between_backends() {
+        assert_eq!(ZEROEX.canonical("SushiSwap"), Source::SushiSwap);
+        assert_eq!(ONEINCH.backend(Source::SushiSwap.name()), "SUSHI");
+        assert_eq!(ONEINCH.backend("PMM1"), "PMM1");
+        assert_eq!(ZEROEX.backend("Balancer_V2"), "Balancer_V2");
+        assert_eq!(
+            ZEROEX.route(["Uniswap_V3", "0x_RFQ", "Uniswap_V3"]),
+            vec![Source::UniswapV3, Source::Other("0x_RFQ".to_owned())],
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use {
+    serde::Deserialize,
+    std::{
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// The request quota of a DEX API.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RateLimit {
+    /// The sustained number of requests per second.
+    pub requests_per_second: f64,
+    /// The number of requests that can be sent at once after a quiet period.
+    pub burst: u32,
+}
+
+/// A token bucket that delays requests to stay within a [`RateLimit`]. It is
+/// shared by all requests to the same DEX API, across all orders.
+pub struct Throttle {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// The available tokens, negative if requests are already waiting for
+    /// tokens.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Throttle {
+    pub fn new(limit: RateLimit) -> Self {
+        assert!(
+            limit.requests_per_second > 0.0 && limit.burst > 0,
+            "rate limit must allow requests"
+        );
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens: limit.burst.into(),
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until the next request may be sent.
+    pub async fn acquire(&self) {
+        let delay = self.reserve(Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Takes a token from the bucket at the specified time and returns how
+    /// long to wait before it becomes available. Tokens are handed out in
+    /// order, so a request may take a token that is only refilled later.
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let refilled = now.saturating_duration_since(bucket.updated).as_secs_f64()
+            * self.limit.requests_per_second;
+        bucket.tokens = (bucket.tokens + refilled).min(self.limit.burst.into()) - 1.0;
+        bucket.updated = bucket.updated.max(now);
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.limit.requests_per_second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_requests_beyond_burst() {
+        let throttle = Throttle::new(RateLimit {
+            requests_per_second: 10.0,
+            burst: 2,
+        });
+        let start = throttle.bucket.lock().unwrap().updated;
+        let at = |millis| start + Duration::from_millis(millis);
+        let round = |delay: Duration| delay.as_millis();
+
+        assert_eq!(round(throttle.reserve(at(0))), 0);
+        assert_eq!(round(throttle.reserve(at(0))), 0);
+        assert_eq!(round(throttle.reserve(at(0))), 100);
+        assert_eq!(round(throttle.reserve(at(0))), 200);
+        // After a quiet period, the bucket is full again, but not more.
+        assert_eq!(round(throttle.reserve(at(10_000))), 0);
+        assert_eq!(round(throttle.reserve(at(10_000))), 0);
+        assert_eq!(round(throttle.reserve(at(10_000))), 100);
+    }
+}
@@ -0,0 +1,50 @@
+use {
+    crate::{
+        domain::{dex, eth},
+        infra::blockchain,
+    },
+    contracts::ethcontract::web3,
+    moka::future::Cache,
+    std::sync::Arc,
+};
+
+const DEFAULT_CACHE_SIZE: u64 = 10_000;
+
+/// Sanity check that the tokens of an order are actually contracts. This
+/// catches typos, externally owned accounts and self-destructed contracts
+/// before we spend any upstream API quota on them.
+pub struct TokenCode {
+    web3: ethrpc::Web3,
+    /// Whether or not a token address has deployed bytecode.
+    has_code: Cache<eth::TokenAddress, bool>,
+}
+
+impl TokenCode {
+    pub fn new(url: &reqwest::Url) -> Self {
+        Self {
+            web3: blockchain::rpc(url),
+            has_code: Cache::new(DEFAULT_CACHE_SIZE),
+        }
+    }
+
+    /// Verifies that both the sell and buy tokens of the order have bytecode.
+    pub async fn validate(&self, order: &dex::Order) -> Result<(), super::Error> {
+        let (sell, buy) = tokio::try_join!(self.has_code(order.sell), self.has_code(order.buy))?;
+        for (token, has_code) in [(order.sell, sell), (order.buy, buy)] {
+            if !has_code {
+                return Err(super::Error::InvalidToken(token));
+            }
+        }
+        Ok(())
+    }
+
+    async fn has_code(&self, token: eth::TokenAddress) -> Result<bool, super::Error> {
+        self.has_code
+            .try_get_with(token, async {
+                let code = self.web3.eth().code(token.0, None).await?;
+                Ok(!code.0.is_empty())
+            })
+            .await
+            .map_err(|err: Arc<web3::Error>| super::Error::Other(err))
+    }
+}
@@ -0,0 +1,78 @@
+//! Support for orders that buy the native token. DEX APIs only swap ERC20
+//! tokens, so these orders are quoted for WETH, which the swap then unwraps.
+
+use {
+    crate::domain::{dex, eth},
+    contracts::WETH9,
+};
+
+pub struct Weth(WETH9);
+
+impl Weth {
+    /// The approximate gas used for unwrapping WETH.
+    pub const UNWRAP_GAS: u64 = 30_000;
+
+    pub fn new(address: eth::WethAddress) -> Self {
+        Self(contracts::dummy_contract!(WETH9, address.0))
+    }
+
+    pub fn address(&self) -> eth::WethAddress {
+        eth::WethAddress(self.0.address())
+    }
+
+    /// Turns a swap that was quoted for a WETH buy order into a swap for the
+    /// native token, by unwrapping the expected output amount after the swap.
+    pub fn unwrap(&self, mut swap: dex::Swap) -> dex::Swap {
+        swap.calls.push(dex::Call {
+            to: eth::ContractAddress(self.address().0),
+            calldata: self
+                .0
+                .methods()
+                .withdraw(swap.output.amount)
+                .tx
+                .data
+                .expect("calldata")
+                .0,
+            purpose: dex::Purpose::Wrap,
+        });
+        swap.output.token = eth::TokenAddress::NATIVE;
+        swap.gas.0 += Self::UNWRAP_GAS.into();
+        swap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{domain::dex::testing, infra},
+        ethereum_types::U256,
+    };
+
+    #[test]
+    fn unwraps_native_buy_swaps_on_mainnet() {
+        let weth = Weth::new(infra::contracts::Contracts::for_chain(eth::ChainId::Mainnet).weth);
+        let usdc = eth::TokenAddress(eth::H160([0x01; 20]));
+        let swap = weth.unwrap(dex::Swap {
+            calls: vec![dex::Call::swap(
+                eth::ContractAddress(eth::H160([0x02; 20])),
+                vec![0xca, 0xfe],
+            )],
+            gas: eth::Gas(100_000.into()),
+            ..testing::swap(
+                testing::asset(usdc, U256::exp10(9)),
+                testing::asset(eth::TokenAddress(weth.address().0), U256::exp10(18)),
+            )
+        });
+
+        assert_eq!(swap.input.token, usdc);
+        assert_eq!(swap.output.token, eth::TokenAddress::NATIVE);
+        assert_eq!(swap.output.amount, U256::exp10(18));
+        assert_eq!(swap.gas.0, (100_000 + Weth::UNWRAP_GAS).into());
+        assert_eq!(swap.calls.len(), 2);
+        assert_eq!(swap.calls[1].to.0, weth.address().0);
+        assert_eq!(swap.calls[1].purpose, dex::Purpose::Wrap);
+        // `withdraw(uint256)`
+        assert_eq!(swap.calls[1].selector(), Some([0x2e, 0x1a, 0x7d, 0x4d]));
+    }
+}
@@ -49,6 +49,11 @@ pub struct Query {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde_as(as = "serialize::CommaSeparated")]
     pub excluded_sources: Vec<String>,
+
+    /// List of sources to restrict the quote to.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde_as(as = "serialize::CommaSeparated")]
+    pub included_sources: Vec<String>,
 }
 
 /// A 0x slippage amount.
@@ -60,8 +65,10 @@ impl Query {
         self,
         order: &dex::Order,
         slippage: &dex::Slippage,
+        gas_price: Option<U256>,
     ) -> Result<Self, super::Error> {
-        // Buy orders are not supported on 0x
+        // Buy orders are not supported on 0x: the v2 allowance holder API only
+        // quotes exact sell amounts and has no `buyAmount` parameter.
         if order.side == order::Side::Buy {
             return Err(super::Error::OrderNotSupported);
         };
@@ -71,6 +78,7 @@ impl Query {
             buy_token: order.buy.0,
             sell_amount: order.amount.get(),
             slippage_bps: slippage.as_bps().map(Slippage),
+            gas_price,
             ..self
         })
     }
@@ -91,6 +99,8 @@ pub struct Quote {
     pub buy_amount: Option<U256>,
     pub transaction: Option<QuoteTransaction>,
     pub issues: Option<Issues>,
+    /// The liquidity sources that the swap is routed through.
+    pub route: Option<Route>,
 }
 
 /// A valid quote response, with liquidity available.
@@ -101,6 +111,7 @@ pub struct ValidQuote {
     pub buy_amount: U256,
     pub transaction: QuoteTransaction,
     pub issues: Issues,
+    pub route: Option<Route>,
 }
 
 impl From<Quote> for Option<ValidQuote> {
@@ -114,6 +125,7 @@ impl From<Quote> for Option<ValidQuote> {
             buy_amount: raw.buy_amount?,
             transaction: raw.transaction?,
             issues: raw.issues?,
+            route: raw.route,
         })
     }
 }
@@ -133,6 +145,10 @@ pub struct QuoteTransaction {
     /// transaction.
     #[serde_as(as = "Option<serialize::U256>")]
     pub gas: Option<U256>,
+
+    /// The gas price used to compute the quote.
+    #[serde_as(as = "Option<serialize::U256>")]
+    pub gas_price: Option<U256>,
 }
 
 #[serde_as]
@@ -154,6 +170,26 @@ pub struct Allowance {
     pub spender: H160,
 }
 
+#[derive(Deserialize)]
+pub struct Route {
+    pub fills: Vec<Fill>,
+}
+
+#[derive(Deserialize)]
+pub struct Fill {
+    /// The name of the liquidity source.
+    pub source: String,
+}
+
+impl Route {
+    /// Whether any part of the route is filled by 0x RFQ market makers.
+    pub fn uses_private_liquidity(&self) -> bool {
+        self.fills
+            .iter()
+            .any(|fill| fill.source.to_ascii_lowercase().contains("rfq"))
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(untagged)]
 #[allow(clippy::large_enum_variant)]
@@ -202,6 +238,19 @@ mod tests {
         assert!(quote.liquidity_available);
     }
 
+    #[test]
+    fn test_quote_route_private_liquidity() {
+        let json = r#"{
+            "fills": [
+                { "from": "0x1", "to": "0x2", "source": "Uniswap_V3", "proportionBps": "5000" },
+                { "from": "0x1", "to": "0x2", "source": "0x_RFQ", "proportionBps": "5000" }
+            ]
+        }"#;
+
+        let route: super::Route = serde_json::from_str(json).unwrap();
+        assert!(route.uses_private_liquidity());
+    }
+
     #[test]
     fn test_quote_no_liquidity_deserialization() {
         let json = r#"{
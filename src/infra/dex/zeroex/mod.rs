@@ -1,13 +1,15 @@
 use {
     crate::{
-        domain::{dex, eth},
+        domain::{auction, dex, eth},
         util,
     },
     ethrpc::block_stream::CurrentBlockWatcher,
     hyper::StatusCode,
     std::{
+        collections::{HashMap, HashSet},
         str::FromStr,
         sync::atomic::{self, AtomicU64},
+        time::Duration,
     },
     tracing::Instrument,
 };
@@ -19,6 +21,9 @@ pub struct ZeroEx {
     client: super::Client,
     endpoint: reqwest::Url,
     defaults: dto::Query,
+    retriable_error_codes: HashMap<i64, bool>,
+    retry: util::http::RetryPolicy,
+    allowed_targets: Option<HashSet<eth::ContractAddress>>,
 }
 
 /// https://0x.org/docs/introduction/0x-cheat-sheet#0x-contracts
@@ -36,17 +41,61 @@ pub struct Config {
     pub api_key: String,
 
     /// The list of excluded liquidity sources. Liquidity from these sources
-    /// will not be considered when solving.
+    /// will not be considered when solving. Sources can be specified either
+    /// by their canonical or their 0x name.
     pub excluded_sources: Vec<String>,
+
+    /// The list of included liquidity sources. When non-empty, only liquidity
+    /// from these sources will be considered when solving. Sources are named
+    /// like the excluded ones.
+    ///
+    /// The two lists are mutually exclusive rather than one taking precedence
+    /// over the other, which keeps the routing intent unambiguous. Creating
+    /// the solver with both lists set fails with
+    /// [`CreationError::ConflictingSources`].
+    pub included_sources: Vec<String>,
+
+    /// Whether 0x API error codes are retriable (i.e. transient) or terminal.
+    /// Codes that are not part of the map are considered terminal.
+    pub retriable_error_codes: HashMap<i64, bool>,
+
     /// The address of the settlement contract.
     pub settlement: eth::ContractAddress,
 
     /// The stream that yields every new block.
     pub block_stream: Option<CurrentBlockWatcher>,
+
+    /// Whether to follow HTTP redirects from the API.
+    pub follow_redirects: bool,
+
+    /// Optional request quota of the API, shared by all requests made by
+    /// this solver.
+    pub rate_limit: Option<super::RateLimit>,
+
+    /// The only contracts that swaps may call, if restricted.
+    pub allowed_targets: Option<HashSet<eth::ContractAddress>>,
+
+    /// How long to wait for a connection to the API to be established.
+    pub connect_timeout: Duration,
+
+    /// How long to wait for a response from the API, including connecting.
+    pub request_timeout: Duration,
+
+    /// An HTTP client shared with other DEX APIs to send requests with,
+    /// instead of building a dedicated one.
+    pub shared_client: Option<reqwest::Client>,
+
+    /// How failed requests are retried. Requests are only sent once if not
+    /// specified.
+    pub retry: Option<util::http::RetryPolicy>,
 }
 
 impl ZeroEx {
     pub fn new(config: Config) -> Result<Self, CreationError> {
+        if !config.included_sources.is_empty() && !config.excluded_sources.is_empty() {
+            return Err(CreationError::ConflictingSources);
+        }
+
         let client = {
             let mut key = reqwest::header::HeaderValue::from_str(&config.api_key)?;
             key.set_sensitive(true);
@@ -58,14 +107,33 @@ impl ZeroEx {
                 reqwest::header::HeaderValue::from_static("v2"),
             );
 
-            let client = reqwest::Client::builder()
-                .default_headers(headers)
-                .build()?;
-            super::Client::new(client, config.block_stream)
+            match config.shared_client {
+                Some(client) => {
+                    super::Client::shared(client, config.request_timeout, config.block_stream)
+                }
+                None => super::Client::new(
+                    reqwest::Client::builder()
+                        .redirect(super::redirect_policy(config.follow_redirects)),
+                    config.connect_timeout,
+                    config.request_timeout,
+                    config.block_stream,
+                )?,
+            }
+            .with_headers(headers)
+            .with_rate_limit(config.rate_limit)
         };
         let defaults = dto::Query {
             taker: config.settlement.0,
-            excluded_sources: config.excluded_sources,
+            excluded_sources: config
+                .excluded_sources
+                .iter()
+                .map(|source| super::sources::ZEROEX.backend(source))
+                .collect(),
+            included_sources: config
+                .included_sources
+                .iter()
+                .map(|source| super::sources::ZEROEX.backend(source))
+                .collect(),
             chain_id: config.chain_id.value().as_u64(),
             ..Default::default()
         };
@@ -74,6 +142,9 @@ impl ZeroEx {
             client,
             endpoint: config.endpoint,
             defaults,
+            retriable_error_codes: config.retriable_error_codes,
+            retry: config.retry.unwrap_or_else(util::http::RetryPolicy::none),
+            allowed_targets: config.allowed_targets,
         })
     }
 
@@ -81,8 +152,15 @@ impl ZeroEx {
         &self,
         order: &dex::Order,
         slippage: &dex::Slippage,
+        gas_price: auction::GasPrice,
     ) -> Result<dex::Swap, Error> {
-        let query = self.defaults.clone().try_with_domain(order, slippage)?;
+        // A zero gas price means that the auction did not provide one, in which
+        // case 0x uses its own gas price assumption.
+        let gas_price = Some(gas_price.0 .0).filter(|gas_price| !gas_price.is_zero());
+        let query = self
+            .defaults
+            .clone()
+            .try_with_domain(order, slippage, gas_price)?;
         let quote = {
             // Set up a tracing span to make debugging of API requests easier.
             // Historically, debugging API requests to external DEXs was a bit
@@ -94,11 +172,24 @@ impl ZeroEx {
                 .await?
         };
 
-        Ok(dex::Swap {
-            calls: vec![dex::Call {
-                to: eth::ContractAddress(quote.transaction.to),
-                calldata: quote.transaction.data,
-            }],
+        let gas = quote.transaction.gas.ok_or(Error::MissingGasEstimate)?;
+        let (private_liquidity, sources) = match &quote.route {
+            Some(route) => (
+                route.uses_private_liquidity(),
+                super::sources::ZEROEX.route(route.fills.iter().map(|fill| fill.source.as_str())),
+            ),
+            None => Default::default(),
+        };
+        let gas_cost_wei = quote
+            .transaction
+            .gas_price
+            .and_then(|gas_price| gas.checked_mul(gas_price));
+
+        let swap = dex::Swap {
+            calls: vec![dex::Call::swap(
+                eth::ContractAddress(quote.transaction.to),
+                quote.transaction.data,
+            )],
             input: eth::Asset {
                 token: order.sell,
                 amount: quote.sell_amount,
@@ -117,19 +208,35 @@ impl ZeroEx {
                     )),
                 amount: dex::Amount::new(quote.sell_amount),
             },
-            gas: eth::Gas(quote.transaction.gas.ok_or(Error::MissingGasEstimate)?),
-        })
+            gas: eth::Gas(gas),
+            metadata: dex::Metadata {
+                private_liquidity,
+                gas_cost_wei,
+                sources,
+                gas_confidence: dex::GasConfidence::Measured,
+                ..Default::default()
+            },
+        };
+        if let Some(target) = super::disallowed_target(&swap, self.allowed_targets.as_ref()) {
+            return Err(Error::DisallowedTarget(target));
+        }
+        Ok(swap)
     }
 
     async fn quote(&self, query: &dto::Query) -> Result<dto::ValidQuote, Error> {
         let quote = Into::<Option<dto::ValidQuote>>::into(
-            util::http::roundtrip!(
+            util::http::roundtrip_retry!(
                 <dto::Quote, dto::Error>;
-                self.client
-                    .request(reqwest::Method::GET, util::url::join(&self.endpoint, "quote"))
-                    .query(query)
+                &self.retry,
+                || async move {
+                    self.client
+                        .request(reqwest::Method::GET, util::url::join(&self.endpoint, "quote"))
+                        .await
+                        .query(query)
+                }
             )
-            .await?,
+            .await
+            .map_err(|err| Error::from_roundtrip(err, &self.retriable_error_codes))?,
         );
 
         quote.ok_or(Error::NotFound)
@@ -142,6 +249,8 @@ pub enum CreationError {
     Header(#[from] reqwest::header::InvalidHeaderValue),
     #[error(transparent)]
     Client(#[from] reqwest::Error),
+    #[error("included and excluded liquidity sources are mutually exclusive")]
+    ConflictingSources,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -153,22 +262,32 @@ pub enum Error {
     #[error("unable to find a quote")]
     NotFound,
     #[error("rate limited")]
-    RateLimited,
+    RateLimited { retry_after: Option<Duration> },
     #[error("sell token or buy token are banned from trading")]
     UnavailableForLegalReasons,
+    #[error("transient api error code {code}: {reason}")]
+    Transient { code: i64, reason: String },
     #[error("api error code {code}: {reason}")]
     Api { code: i64, reason: String },
+    #[error("swap calls disallowed target {0:?}")]
+    DisallowedTarget(eth::ContractAddress),
     #[error(transparent)]
     Http(util::http::Error),
 }
 
-impl From<util::http::RoundtripError<dto::Error>> for Error {
-    fn from(err: util::http::RoundtripError<dto::Error>) -> Self {
+impl Error {
+    /// Categorizes a roundtrip error, using the specified mapping to decide
+    /// which API error codes are retriable.
+    fn from_roundtrip(
+        err: util::http::RoundtripError<dto::Error>,
+        retriable_error_codes: &HashMap<i64, bool>,
+    ) -> Self {
         match err {
             util::http::RoundtripError::Http(err) => {
-                if let util::http::Error::Status(code, ref body) = err {
+                if let util::http::Error::TooManyRequests { retry_after, .. } = err {
+                    Self::RateLimited { retry_after }
+                } else if let util::http::Error::Status(code, ref body) = err {
                     match code {
-                        StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
                         StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => {
                             Self::UnavailableForLegalReasons
                         }
@@ -186,12 +305,17 @@ impl From<util::http::RoundtripError<dto::Error>> for Error {
                 }
             }
             util::http::RoundtripError::Api(err) => {
-                // Unfortunately, AFAIK these codes aren't documented anywhere. These
-                // based on empirical observations of what the API has returned in the
-                // past.
+                let retriable = retriable_error_codes
+                    .get(&err.code)
+                    .copied()
+                    .unwrap_or_default();
                 match err.code {
+                    429 if retriable => Self::RateLimited { retry_after: None },
+                    _ if retriable => Self::Transient {
+                        code: err.code,
+                        reason: err.reason,
+                    },
                     100 => Self::NotFound,
-                    429 => Self::RateLimited,
                     422 => Self::UnavailableForLegalReasons,
                     451 => Self::UnavailableForLegalReasons,
                     _ => Self::Api {
@@ -203,3 +327,72 @@ impl From<util::http::RoundtripError<dto::Error>> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_retriable_error_codes() {
+        let codes = HashMap::from([(100, false), (103, true), (429, false)]);
+        let classify = |code| {
+            Error::from_roundtrip(
+                util::http::RoundtripError::Api(dto::Error {
+                    code,
+                    reason: "reason".to_owned(),
+                }),
+                &codes,
+            )
+        };
+
+        assert!(matches!(classify(100), Error::NotFound));
+        assert!(matches!(classify(103), Error::Transient { code: 103, .. }));
+        assert!(matches!(classify(429), Error::Api { code: 429, .. }));
+        assert!(matches!(classify(451), Error::UnavailableForLegalReasons));
+    }
+
+    fn config(included_sources: &[&str], excluded_sources: &[&str]) -> Config {
+        let sources = |sources: &[&str]| sources.iter().map(|s| s.to_string()).collect();
+        Config {
+            chain_id: eth::ChainId::Mainnet,
+            endpoint: "https://api.0x.org/swap/allowance-holder/".parse().unwrap(),
+            api_key: "key".to_owned(),
+            excluded_sources: sources(excluded_sources),
+            included_sources: sources(included_sources),
+            retriable_error_codes: Default::default(),
+            settlement: eth::ContractAddress(Default::default()),
+            block_stream: None,
+            follow_redirects: true,
+            rate_limit: None,
+            connect_timeout: crate::infra::dex::DEFAULT_CONNECT_TIMEOUT,
+            request_timeout: crate::infra::dex::DEFAULT_REQUEST_TIMEOUT,
+            shared_client: None,
+            allowed_targets: None,
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn rejects_included_and_excluded_sources() {
+        assert!(matches!(
+            ZeroEx::new(config(&["curve"], &["Balancer_V2"])),
+            Err(CreationError::ConflictingSources)
+        ));
+        assert!(ZeroEx::new(config(&["curve"], &[])).is_ok());
+        assert!(ZeroEx::new(config(&[], &["Balancer_V2"])).is_ok());
+    }
+
+    #[test]
+    fn serializes_included_sources() {
+        let zeroex = ZeroEx::new(config(&["uniswap-v3", "Curve"], &[])).unwrap();
+        let request = reqwest::Client::new()
+            .get(zeroex.endpoint.clone())
+            .query(&zeroex.defaults)
+            .build()
+            .unwrap();
+        let query = request.url().query().unwrap();
+
+        assert!(query.contains("includedSources=Uniswap_V3%2CCurve"));
+        assert!(!query.contains("excludedSources"));
+    }
+}
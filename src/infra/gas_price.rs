@@ -0,0 +1,41 @@
+use {
+    crate::{domain::eth, infra::blockchain},
+    contracts::ethcontract::web3,
+    ethrpc::block_stream::CurrentBlockWatcher,
+};
+
+/// The solver engine's view of the current gas price, so that drivers can
+/// align their gas assumptions with the ones the solver uses.
+pub struct GasPrice {
+    block_stream: Option<CurrentBlockWatcher>,
+    web3: ethrpc::Web3,
+    /// The priority fee that gets added to the current base fee.
+    tip: eth::Ether,
+}
+
+impl GasPrice {
+    pub fn new(
+        node_url: &reqwest::Url,
+        block_stream: Option<CurrentBlockWatcher>,
+        tip: eth::Ether,
+    ) -> Self {
+        Self {
+            block_stream,
+            web3: blockchain::rpc(node_url),
+            tip,
+        }
+    }
+
+    /// Returns the current gas price estimate. This is the gas price of the
+    /// latest block plus the configured tip, or the node's gas price
+    /// suggestion when there is no block stream.
+    pub async fn current(&self) -> Result<eth::Ether, web3::Error> {
+        match &self.block_stream {
+            Some(stream) => {
+                let gas_price = stream.borrow().gas_price;
+                Ok(eth::Ether(gas_price.saturating_add(self.tip.0)))
+            }
+            None => Ok(eth::Ether(self.web3.eth().gas_price().await?)),
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::domain::{auction, solution};
+use {
+    crate::domain::{auction, order},
+    std::time::Duration,
+};
 
 /// Metrics for the solver engine.
 #[derive(Debug, Clone, prometheus_metric_storage::MetricStorage)]
@@ -21,6 +24,49 @@ struct Metrics {
 
     /// The number of solutions that were found.
     solutions: prometheus::IntCounter,
+
+    /// The latest remaining request budget reported by each DEX API in its
+    /// rate limit response headers, by the host of the API.
+    #[metric(labels("host"))]
+    rate_limit_remaining: prometheus::IntGaugeVec,
+
+    /// The number of DEX API requests in flight whenever a new one is sent,
+    /// i.e. the achieved request parallelism.
+    #[metric(buckets(1, 2, 4, 8, 16, 32, 64))]
+    concurrent_requests: prometheus::Histogram,
+
+    /// The duration of swap requests to the DEX API in seconds.
+    #[metric(labels("dex", "side"), buckets(0.05, 0.1, 0.25, 0.5, 1, 2.5, 5, 10))]
+    swap_duration: prometheus::HistogramVec,
+
+    /// The outcomes of swap requests to the DEX API, `Success` or the error
+    /// variant.
+    #[metric(labels("dex", "result"))]
+    swap_results: prometheus::IntCounterVec,
+
+    /// The calldata size of the swaps returned by the DEX API in bytes.
+    #[metric(
+        labels("dex"),
+        buckets(1_000, 2_000, 4_000, 8_000, 16_000, 32_000, 64_000, 128_000)
+    )]
+    swap_calldata_bytes: prometheus::HistogramVec,
+
+    /// The number of DEX API requests that were retried, by the host of the
+    /// API.
+    #[metric(labels("host"))]
+    request_retries: prometheus::IntCounterVec,
+
+    /// The number of DEX API requests that were not sent, because they shared
+    /// the result of an identical request in flight.
+    deduplicated_requests: prometheus::IntCounter,
+
+    /// The number of orders served by each backend of the fallback solver.
+    #[metric(labels("backend"))]
+    fallback_served: prometheus::IntCounterVec,
+
+    /// Whether the circuit breaker of a DEX API is open.
+    #[metric(labels("solver"))]
+    circuit_breaker_open: prometheus::IntGaugeVec,
 }
 
 /// Setup the metrics registry.
@@ -38,11 +84,11 @@ pub fn solve(auction: &auction::Auction) {
     );
 }
 
-pub fn solved(deadline: &auction::Deadline, solutions: &[solution::Solution]) {
+pub fn solved(deadline: &auction::Deadline, solutions: usize) {
     get()
         .remaining_time
         .observe(deadline.remaining().unwrap_or_default().as_secs_f64());
-    get().solutions.inc_by(solutions.len() as u64);
+    get().solutions.inc_by(solutions as u64);
 }
 
 pub fn solve_error(reason: &str) {
@@ -53,6 +99,55 @@ pub fn request_sent() {
     get().solve_requests.inc();
 }
 
+pub fn request_started(in_flight: usize) {
+    get().concurrent_requests.observe(in_flight as f64);
+}
+
+pub fn rate_limit_remaining(host: &str, remaining: i64) {
+    get()
+        .rate_limit_remaining
+        .with_label_values(&[host])
+        .set(remaining);
+}
+
+pub fn swap(dex: &str, side: order::Side, duration: Duration, result: &str) {
+    let side = match side {
+        order::Side::Buy => "buy",
+        order::Side::Sell => "sell",
+    };
+    get()
+        .swap_duration
+        .with_label_values(&[dex, side])
+        .observe(duration.as_secs_f64());
+    get().swap_results.with_label_values(&[dex, result]).inc();
+}
+
+pub fn swap_calldata(dex: &str, bytes: usize) {
+    get()
+        .swap_calldata_bytes
+        .with_label_values(&[dex])
+        .observe(bytes as f64);
+}
+
+pub fn request_retried(host: &str) {
+    get().request_retries.with_label_values(&[host]).inc();
+}
+
+pub fn request_deduplicated() {
+    get().deduplicated_requests.inc();
+}
+
+pub fn fallback_served(backend: &str) {
+    get().fallback_served.with_label_values(&[backend]).inc();
+}
+
+pub fn circuit_breaker(solver: &str, open: bool) {
+    get()
+        .circuit_breaker_open
+        .with_label_values(&[solver])
+        .set(open.into());
+}
+
 /// Get the metrics instance.
 fn get() -> &'static Metrics {
     Metrics::instance(observe::metrics::get_storage_registry())
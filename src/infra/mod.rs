@@ -3,4 +3,6 @@ pub mod cli;
 pub mod config;
 pub mod contracts;
 pub mod dex;
+pub mod gas_price;
 pub mod metrics;
+pub mod multicall;
@@ -0,0 +1,318 @@
+//! Batching of contract calls through the Multicall3 contract.
+
+use {
+    crate::{
+        domain::eth,
+        infra::{blockchain, contracts},
+    },
+    futures::future,
+    std::{collections::HashMap, sync::Arc, time::Duration},
+    tokio::sync::{mpsc, oneshot},
+    web3::{
+        ethabi::{self, ParamType, Token},
+        types::{BlockId, BlockNumber, Bytes, CallRequest},
+    },
+};
+
+/// How long to wait for more calls to batch with the first one.
+const BATCH_DELAY: Duration = Duration::from_millis(5);
+
+/// The maximum number of calls to batch into a single `eth_call`.
+const MAX_BATCH_SIZE: usize = 500;
+
+/// The selector of `aggregate3((address,bool,bytes)[])`.
+const AGGREGATE3: [u8; 4] = [0x82, 0xad, 0x56, 0xcb];
+
+/// Batches the contract calls that are made at the same time (e.g. the
+/// lookups for all orders of an auction) into a single `eth_call` to the
+/// Multicall3 contract. The calls are made individually instead if Multicall3
+/// is not deployed on the chain, or if the batched `eth_call` fails (e.g.
+/// because it exceeds the gas limit of the node).
+///
+/// A single instance is shared by the whole solver (see
+/// [`crate::infra::config::dex::Config::multicall`]), so that all kinds of
+/// lookups end up in the same batches.
+#[derive(Clone)]
+pub struct Multicall {
+    requests: mpsc::UnboundedSender<Request>,
+}
+
+/// A contract call.
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub target: eth::H160,
+    pub calldata: Vec<u8>,
+}
+
+impl Call {
+    /// The ERC-20 `decimals()` call of the token.
+    pub fn decimals(token: eth::TokenAddress) -> Self {
+        Self {
+            target: token.0,
+            calldata: ethabi::short_signature("decimals", &[]).to_vec(),
+        }
+    }
+
+    /// The ERC-20 `allowance(owner, spender)` call of the token.
+    pub fn allowance(token: eth::TokenAddress, owner: eth::H160, spender: eth::H160) -> Self {
+        let signature =
+            ethabi::short_signature("allowance", &[ParamType::Address, ParamType::Address]);
+        let arguments = ethabi::encode(&[Token::Address(owner), Token::Address(spender)]);
+        Self {
+            target: token.0,
+            calldata: [&signature[..], &arguments].concat(),
+        }
+    }
+}
+
+/// Decodes the return data of a call that returns a single `uint`.
+pub fn decode_uint(data: &[u8]) -> Option<eth::U256> {
+    match ethabi::decode(&[ParamType::Uint(256)], data)
+        .ok()?
+        .as_slice()
+    {
+        [Token::Uint(value)] => Some(*value),
+        _ => None,
+    }
+}
+
+struct Request {
+    call: Call,
+    block: Option<u64>,
+    result: oneshot::Sender<Result<Vec<u8>, Error>>,
+}
+
+impl Multicall {
+    pub fn new(url: &reqwest::Url) -> Self {
+        let (requests, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(blockchain::rpc(url), receiver));
+        Self { requests }
+    }
+
+    /// Executes the call at the specified block (or the latest one if `None`)
+    /// and returns its return data.
+    pub async fn call(&self, call: Call, block: Option<u64>) -> Result<Vec<u8>, Error> {
+        let (result, receiver) = oneshot::channel();
+        self.requests
+            .send(Request {
+                call,
+                block,
+                result,
+            })
+            .map_err(|_| Error::Closed)?;
+        receiver.await.map_err(|_| Error::Closed)?
+    }
+}
+
+/// Collects the requested calls into batches and executes them.
+async fn run(web3: ethrpc::Web3, mut requests: mpsc::UnboundedReceiver<Request>) {
+    let mut deployed = None;
+    while let Some(first) = requests.recv().await {
+        let mut batch = vec![first];
+        let delay = tokio::time::sleep(BATCH_DELAY);
+        tokio::pin!(delay);
+        while batch.len() < MAX_BATCH_SIZE {
+            tokio::select! {
+                request = requests.recv() => match request {
+                    Some(request) => batch.push(request),
+                    None => break,
+                },
+                _ = &mut delay => break,
+            }
+        }
+
+        if deployed.is_none() {
+            deployed = is_deployed(&web3).await;
+        }
+        let mut blocks = HashMap::<_, Vec<_>>::new();
+        for request in batch {
+            blocks.entry(request.block).or_default().push(request);
+        }
+        for (block, requests) in blocks {
+            tokio::spawn(execute(
+                web3.clone(),
+                deployed.unwrap_or(false),
+                block,
+                requests,
+            ));
+        }
+    }
+}
+
+/// Checks whether Multicall3 is deployed on the chain. Returns `None` if that
+/// can't be determined right now.
+async fn is_deployed(web3: &ethrpc::Web3) -> Option<bool> {
+    match web3.eth().code(contracts::MULTICALL3.0, None).await {
+        Ok(code) => {
+            let deployed = !code.0.is_empty();
+            if !deployed {
+                tracing::info!("Multicall3 is not deployed; making calls individually");
+            }
+            Some(deployed)
+        }
+        Err(err) => {
+            tracing::debug!(?err, "failed to check whether Multicall3 is deployed");
+            None
+        }
+    }
+}
+
+async fn execute(web3: ethrpc::Web3, multicall: bool, block: Option<u64>, requests: Vec<Request>) {
+    let block = block.map(|block| BlockId::Number(BlockNumber::Number(block.into())));
+    let (calls, results): (Vec<_>, Vec<_>) = requests
+        .into_iter()
+        .map(|request| (request.call, request.result))
+        .unzip();
+    if !multicall || calls.len() == 1 {
+        individually(&web3, block, calls, results).await;
+        return;
+    }
+
+    let multicall = Call {
+        target: contracts::MULTICALL3.0,
+        calldata: encode(&calls),
+    };
+    let outputs = call(&web3, &multicall, block)
+        .await
+        .and_then(|data| decode(&data).ok_or(Error::InvalidReturnData))
+        .and_then(|outputs| {
+            (outputs.len() == calls.len())
+                .then_some(outputs)
+                .ok_or(Error::InvalidReturnData)
+        });
+    match outputs {
+        Ok(outputs) => {
+            for (result, output) in results.into_iter().zip(outputs) {
+                let _ = result.send(output);
+            }
+        }
+        Err(err) => {
+            tracing::debug!(
+                ?err,
+                calls = calls.len(),
+                "multicall failed; making calls individually"
+            );
+            individually(&web3, block, calls, results).await;
+        }
+    }
+}
+
+/// Makes each call with its own `eth_call`, concurrently.
+async fn individually(
+    web3: &ethrpc::Web3,
+    block: Option<BlockId>,
+    calls: Vec<Call>,
+    results: Vec<oneshot::Sender<Result<Vec<u8>, Error>>>,
+) {
+    future::join_all(calls.iter().zip(results).map(|(c, result)| async move {
+        let _ = result.send(call(web3, c, block).await);
+    }))
+    .await;
+}
+
+async fn call(web3: &ethrpc::Web3, call: &Call, block: Option<BlockId>) -> Result<Vec<u8>, Error> {
+    let request = CallRequest {
+        to: Some(call.target),
+        data: Some(Bytes(call.calldata.clone())),
+        ..Default::default()
+    };
+    Ok(web3
+        .eth()
+        .call(request, block)
+        .await
+        .map_err(|err| Error::Web3(Arc::new(err)))?
+        .0)
+}
+
+/// Encodes the calls as an `aggregate3` call that doesn't fail as a whole if
+/// single calls revert.
+fn encode(calls: &[Call]) -> Vec<u8> {
+    let calls = calls
+        .iter()
+        .map(|call| {
+            Token::Tuple(vec![
+                Token::Address(call.target),
+                Token::Bool(true),
+                Token::Bytes(call.calldata.clone()),
+            ])
+        })
+        .collect();
+    [&AGGREGATE3[..], &ethabi::encode(&[Token::Array(calls)])].concat()
+}
+
+/// Decodes the return data of each call from the `aggregate3` return data.
+fn decode(data: &[u8]) -> Option<Vec<Result<Vec<u8>, Error>>> {
+    let kind = ParamType::Array(Box::new(ParamType::Tuple(vec![
+        ParamType::Bool,
+        ParamType::Bytes,
+    ])));
+    let Ok(Some(Token::Array(outputs))) =
+        ethabi::decode(&[kind], data).map(|mut tokens| tokens.pop())
+    else {
+        return None;
+    };
+    outputs
+        .into_iter()
+        .map(|output| match output {
+            Token::Tuple(fields) => match fields.as_slice() {
+                [Token::Bool(true), Token::Bytes(data)] => Some(Ok(data.clone())),
+                [Token::Bool(false), Token::Bytes(_)] => Some(Err(Error::Reverted)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Web3(Arc<web3::Error>),
+    #[error("call reverted")]
+    Reverted,
+    #[error("invalid multicall return data")]
+    InvalidReturnData,
+    #[error("multicall batching stopped")]
+    Closed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_aggregate3_calls() {
+        let signature = ethabi::short_signature(
+            "aggregate3",
+            &[ParamType::Array(Box::new(ParamType::Tuple(vec![
+                ParamType::Address,
+                ParamType::Bool,
+                ParamType::Bytes,
+            ])))],
+        );
+        assert_eq!(signature, AGGREGATE3);
+
+        let calls = [
+            Call {
+                target: eth::H160([0x01; 20]),
+                calldata: vec![0x31, 0x3c, 0xe5, 0x67],
+            },
+            Call {
+                target: eth::H160([0x02; 20]),
+                calldata: vec![],
+            },
+        ];
+        assert_eq!(encode(&calls)[..4], AGGREGATE3);
+
+        let output = |success, data: &[u8]| {
+            Token::Tuple(vec![Token::Bool(success), Token::Bytes(data.to_vec())])
+        };
+        let data = ethabi::encode(&[Token::Array(vec![
+            output(true, &[0x12]),
+            output(false, &[]),
+        ])]);
+        let outputs = decode(&data).unwrap();
+        assert!(matches!(outputs.as_slice(), [Ok(data), Err(Error::Reverted)] if data == &[0x12]));
+        assert!(decode(&[0x42]).is_none());
+    }
+}
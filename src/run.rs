@@ -3,9 +3,11 @@ use tokio::signal::unix::{self, SignalKind};
 use {
     crate::{
         domain::solver::{self, Solver},
-        infra::{cli, config, dex},
+        infra::{self, cli, config, dex},
+        util,
     },
     clap::Parser,
+    futures::future,
     std::net::SocketAddr,
     tokio::sync::oneshot,
 };
@@ -27,60 +29,193 @@ pub async fn run(
 async fn run_with(args: cli::Args, bind: Option<oneshot::Sender<SocketAddr>>) {
     observe::tracing::initialize_reentrant(&args.log);
     tracing::info!("running solver engine with {args:#?}");
+    util::http::log_bodies(args.log_http_bodies);
 
-    let solver = match args.command {
+    let (solver, base) = match args.command {
         cli::Command::ZeroEx { config } => {
             let config = config::dex::zeroex::file::load(&config).await;
-            Solver::Dex(solver::Dex::new(
-                dex::Dex::ZeroEx(
-                    dex::zeroex::ZeroEx::new(config.zeroex).expect("invalid 0x configuration"),
-                ),
-                config.base.clone(),
-            ))
+            (
+                Solver::Dex(solver::Dex::new(
+                    dex::Dex::ZeroEx(
+                        dex::zeroex::ZeroEx::new(config.zeroex).expect("invalid 0x configuration"),
+                    ),
+                    config.base.clone(),
+                )),
+                config.base,
+            )
         }
         cli::Command::Balancer { config } => {
             let config = config::dex::balancer::file::load(&config).await;
-            Solver::Dex(solver::Dex::new(
-                dex::Dex::Balancer(
-                    dex::balancer::Sor::new(config.sor).expect("invalid Balancer configuration"),
-                ),
-                config.base.clone(),
-            ))
+            (
+                Solver::Dex(solver::Dex::new(
+                    dex::Dex::Balancer(
+                        dex::balancer::Sor::new(config.sor)
+                            .expect("invalid Balancer configuration"),
+                    ),
+                    config.base.clone(),
+                )),
+                config.base,
+            )
         }
         cli::Command::OneInch { config } => {
             let config = config::dex::oneinch::file::load(&config).await;
-            Solver::Dex(solver::Dex::new(
-                dex::Dex::OneInch(dex::oneinch::OneInch::new(config.oneinch).await),
-                config.base.clone(),
-            ))
+            (
+                Solver::Dex(solver::Dex::new(
+                    dex::Dex::OneInch(dex::oneinch::OneInch::new(config.oneinch).await),
+                    config.base.clone(),
+                )),
+                config.base,
+            )
         }
         cli::Command::ParaSwap { config } => {
             let config = config::dex::paraswap::file::load(&config).await;
-            Solver::Dex(solver::Dex::new(
-                dex::Dex::ParaSwap(dex::paraswap::ParaSwap::new(config.paraswap)),
-                config.base.clone(),
-            ))
+            (
+                Solver::Dex(solver::Dex::new(
+                    dex::Dex::ParaSwap(dex::paraswap::ParaSwap::new(config.paraswap)),
+                    config.base.clone(),
+                )),
+                config.base,
+            )
         }
         cli::Command::Okx { config } => {
             let config = config::dex::okx::file::load(&config).await;
-            Solver::Dex(solver::Dex::new(
-                dex::Dex::Okx(
-                    dex::okx::Okx::try_new(config.okx).expect("invalid OKX configuration"),
-                ),
-                config.base.clone(),
-            ))
+            (
+                Solver::Dex(solver::Dex::new(
+                    dex::Dex::Okx(
+                        dex::okx::Okx::try_new(config.okx).expect("invalid OKX configuration"),
+                    ),
+                    config.base.clone(),
+                )),
+                config.base,
+            )
+        }
+        cli::Command::Replay { config } => {
+            let config = config::dex::replay::file::load(&config).await;
+            (
+                Solver::Dex(solver::Dex::new(
+                    dex::Dex::Replay(
+                        dex::replay::Replay::new(config.replay).expect("invalid replay fixtures"),
+                    ),
+                    config.base.clone(),
+                )),
+                config.base,
+            )
+        }
+        cli::Command::Multi { config } => {
+            let config = config::dex::multi::file::load(&config).await;
+            let client = shared_client(&config.base);
+            let backends = future::join_all(
+                config
+                    .backends
+                    .into_iter()
+                    .map(|config| backend(config, client.clone())),
+            )
+            .await;
+            (
+                Solver::Dex(solver::Dex::new(
+                    dex::Dex::Multi(dex::multi::MultiDex::new(
+                        backends,
+                        config.request_timeout,
+                        config.routes,
+                        config.early_return,
+                        config.mev_penalty,
+                        config.base.backend(),
+                    )),
+                    config.base.clone(),
+                )),
+                config.base,
+            )
+        }
+        cli::Command::Fallback { config } => {
+            let config = config::dex::fallback::file::load(&config).await;
+            let client = shared_client(&config.base);
+            let backends = future::join_all(
+                config
+                    .backends
+                    .into_iter()
+                    .map(|config| backend(config, client.clone())),
+            )
+            .await;
+            (
+                Solver::Dex(solver::Dex::new(
+                    dex::Dex::Fallback(dex::fallback::FallbackDex::new(
+                        backends,
+                        config.request_timeout,
+                        config.base.backend(),
+                    )),
+                    config.base.clone(),
+                )),
+                config.base,
+            )
         }
     };
 
     crate::api::Api {
         addr: args.addr,
         solver,
+        solve_timeout: args.solve_timeout,
+        gas_price: infra::gas_price::GasPrice::new(
+            &base.node_url,
+            base.block_stream,
+            base.gas_price_tip,
+        ),
+        api_key: base.api_key,
+        name: base.name,
+        chain_id: base.chain_id,
     }
     .serve(bind, shutdown_signal())
     .await
     .unwrap();
 }
 
+/// Builds the HTTP client that all backends of a meta-solver or fallback
+/// solver share.
+fn shared_client(config: &config::dex::Config) -> reqwest::Client {
+    dex::shared_client(config.follow_redirects, config.connect_timeout)
+        .expect("failed to build HTTP client")
+}
+
+/// Creates the DEX API client for a meta-solver or fallback solver backend,
+/// sending its requests with the shared HTTP client.
+async fn backend(config: config::dex::multi::Backend, client: reqwest::Client) -> dex::Dex {
+    match config {
+        config::dex::multi::Backend::Balancer(config) => dex::Dex::Balancer(
+            dex::balancer::Sor::new(dex::balancer::Config {
+                shared_client: Some(client),
+                ..config
+            })
+            .expect("invalid Balancer configuration"),
+        ),
+        config::dex::multi::Backend::ZeroEx(config) => dex::Dex::ZeroEx(
+            dex::zeroex::ZeroEx::new(dex::zeroex::Config {
+                shared_client: Some(client),
+                ..config
+            })
+            .expect("invalid 0x configuration"),
+        ),
+        config::dex::multi::Backend::OneInch(config) => dex::Dex::OneInch(
+            dex::oneinch::OneInch::new(dex::oneinch::Config {
+                shared_client: Some(client),
+                ..config
+            })
+            .await,
+        ),
+        config::dex::multi::Backend::ParaSwap(config) => {
+            dex::Dex::ParaSwap(dex::paraswap::ParaSwap::new(dex::paraswap::Config {
+                shared_client: Some(client),
+                ..config
+            }))
+        }
+        config::dex::multi::Backend::Okx(config) => dex::Dex::Okx(
+            dex::okx::Okx::try_new(dex::okx::Config {
+                shared_client: Some(client),
+                ..config
+            })
+            .expect("invalid OKX configuration"),
+        ),
+    }
+}
+
 #[cfg(unix)]
 async fn shutdown_signal() {
     // Intercept main signals for graceful shutdown.
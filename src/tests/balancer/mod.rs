@@ -1,6 +1,7 @@
 use {crate::tests, std::net::SocketAddr};
 
 mod market_order;
+mod native;
 mod not_found;
 mod out_of_price;
 
@@ -18,7 +19,7 @@ chain-id = '1'
 
 // Copy from src/infra/dex/balancer/dto.rs
 pub const SWAP_QUERY: &str = r#"
-query sorGetSwapPaths($callDataInput: GqlSwapCallDataInput!, $chain: GqlChain!, $queryBatchSwap: Boolean!, $swapAmount: AmountHumanReadable!, $swapType: GqlSorSwapType!, $tokenIn: String!, $tokenOut: String!) {
+query sorGetSwapPaths($callDataInput: GqlSwapCallDataInput!, $chain: GqlChain!, $queryBatchSwap: Boolean!, $swapAmount: AmountHumanReadable!, $swapType: GqlSorSwapType!, $tokenIn: String!, $tokenOut: String!, $useProtocolVersion: Int) {
     sorGetSwapPaths(
         callDataInput: $callDataInput,
         chain: $chain,
@@ -27,6 +28,7 @@ query sorGetSwapPaths($callDataInput: GqlSwapCallDataInput!, $chain: GqlChain!,
         swapType: $swapType,
         tokenIn: $tokenIn,
         tokenOut: $tokenOut,
+        useProtocolVersion: $useProtocolVersion,
     ) {
         tokenAddresses
         swaps {
@@ -0,0 +1,173 @@
+//! This test ensures that solving stops at the deadline of the request, and
+//! that the solutions found by then are returned and flagged as truncated.
+
+use {
+    crate::tests::{self, mock},
+    serde_json::json,
+};
+
+fn quoted() -> mock::http::Expectation {
+    mock::http::Expectation::Get {
+        path: mock::http::Path::exact(
+            "swap/allowance-holder/quote?chainId=1&\
+             buyToken=0xe41d2489571d322189246dafa5ebde1f4699f498&\
+             sellToken=0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2&sellAmount=1000000000000000000&\
+             taker=0x9008d19f58aabd9ed0d60971565aa8510560ab41&slippageBps=100&gasPrice=15000000000",
+        ),
+        res: json!({
+            "liquidityAvailable": true,
+            "sellAmount": "1000000000000000000",
+            "buyAmount": "5876422636675954000000",
+            "transaction": {
+                "to": "0xdef1c0ded9bec7f1a1670819833240f027b25eff",
+                "data": "0x6af479b2",
+                "gas": "127886",
+            },
+            "issues": {
+                "allowance": {
+                    "spender": "0xdef1c0ded9bec7f1a1670819833240f027b25eff",
+                    "actual": "0",
+                },
+            },
+        }),
+    }
+}
+
+/// The quote request for the second order, which never gets a response.
+fn hung() -> mock::http::Expectation {
+    mock::http::Expectation::Hang {
+        path: mock::http::Path::glob(
+            "swap/allowance-holder/quote?*sellAmount=2000000000000000000*",
+        ),
+    }
+}
+
+fn auction(solve_deadline: Option<chrono::DateTime<chrono::Utc>>) -> serde_json::Value {
+    json!({
+        "id": "1",
+        "tokens": {
+            "0xe41d2489571d322189246dafa5ebde1f4699f498": {
+                "decimals": 18,
+                "symbol": "ZRX",
+                "referencePrice": "4327903683155778",
+                "availableBalance": "1583034704488033979459",
+                "trusted": true,
+            },
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2": {
+                "decimals": 18,
+                "symbol": "WETH",
+                "referencePrice": "1000000000000000000",
+                "availableBalance": "482725140468789680",
+                "trusted": true,
+            },
+        },
+        "orders": [
+            {
+                "uid": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+                          2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+                          2a2a2a2a",
+                "sellToken": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                "buyToken": "0xe41d2489571d322189246dafa5ebde1f4699f498",
+                "sellAmount": "1000000000000000000",
+                "buyAmount": "200000000000000000000",
+                "fullSellAmount": "1000000000000000000",
+                "fullBuyAmount": "200000000000000000000",
+                "kind": "sell",
+                "partiallyFillable": false,
+                "class": "market",
+                "sellTokenSource": "erc20",
+                "buyTokenDestination": "erc20",
+                "preInteractions": [],
+                "postInteractions": [],
+                "owner": "0x5b1e2c2762667331bc91648052f646d1b0d35984",
+                "validTo": 0,
+                "appData": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "signingScheme": "presign",
+                "signature": "0x",
+            },
+            {
+                "uid": "0x2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b\
+                          2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b\
+                          2b2b2b2b",
+                "sellToken": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                "buyToken": "0xe41d2489571d322189246dafa5ebde1f4699f498",
+                "sellAmount": "2000000000000000000",
+                "buyAmount": "400000000000000000000",
+                "fullSellAmount": "2000000000000000000",
+                "fullBuyAmount": "400000000000000000000",
+                "kind": "sell",
+                "partiallyFillable": false,
+                "class": "market",
+                "sellTokenSource": "erc20",
+                "buyTokenDestination": "erc20",
+                "preInteractions": [],
+                "postInteractions": [],
+                "owner": "0x5b1e2c2762667331bc91648052f646d1b0d35984",
+                "validTo": 0,
+                "appData": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "signingScheme": "presign",
+                "signature": "0x",
+            }
+        ],
+        "liquidity": [],
+        "effectiveGasPrice": "15000000000",
+        "deadline": "2106-01-01T00:00:00.000Z",
+        "surplusCapturingJitOrderOwners": [],
+        "solveDeadline": solve_deadline,
+    })
+}
+
+fn config(api: &mock::http::ServerHandle) -> tests::Config {
+    // Orders are solved one after the other, so that the first one is solved
+    // before the request for the second one hangs.
+    tests::Config::String(format!(
+        r"
+node-url = 'http://localhost:8545'
+concurrent-requests = 1
+[dex]
+chain-id = '1'
+endpoint = 'http://{}/swap/allowance-holder/'
+api-key = 'SUPER_SECRET_API_KEY'
+        ",
+        api.address
+    ))
+}
+
+fn soon() -> chrono::DateTime<chrono::Utc> {
+    chrono::Utc::now() + chrono::Duration::seconds(2)
+}
+
+fn assert_truncated(headers: &reqwest::header::HeaderMap, solutions: &serde_json::Value) {
+    assert_eq!(headers["x-truncated"], "true");
+    let solutions = solutions["solutions"].as_array().unwrap();
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(solutions[0]["id"], json!(0));
+}
+
+#[tokio::test]
+async fn header() {
+    let api = mock::http::setup(vec![quoted(), hung()]).await;
+
+    let engine = tests::SolverEngine::new("zeroex", config(&api)).await;
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-deadline", soon().to_rfc3339().parse().unwrap());
+    let (headers, solutions) = engine
+        .solve_with_headers(auction(None), headers)
+        .await
+        .unwrap();
+
+    assert_truncated(&headers, &solutions);
+}
+
+#[tokio::test]
+async fn request_field() {
+    let api = mock::http::setup(vec![quoted(), hung()]).await;
+
+    let engine = tests::SolverEngine::new("zeroex", config(&api)).await;
+    let (headers, solutions) = engine
+        .solve_with_headers(auction(Some(soon())), Default::default())
+        .await
+        .unwrap();
+
+    assert_truncated(&headers, &solutions);
+}
@@ -1,4 +1,8 @@
 //! Test cases that are specific to the dex solver but not the underlying APIs.
 
+mod batch;
+mod deadline;
+mod dry_run;
 mod partial_fill;
+mod streaming;
 mod wrong_execution;
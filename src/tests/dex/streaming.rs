@@ -0,0 +1,113 @@
+//! This test ensures that the solutions of an auction are streamed as
+//! server-sent events to clients that accept them, followed by a final
+//! `complete` event.
+
+use {
+    crate::tests::{self, mock, zeroex},
+    serde_json::json,
+};
+
+fn swap() -> mock::http::Expectation {
+    mock::http::Expectation::Get {
+        path: mock::http::Path::exact(
+            "swap/allowance-holder/quote?chainId=1&\
+             buyToken=0xe41d2489571d322189246dafa5ebde1f4699f498&\
+             sellToken=0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2&sellAmount=1000000000000000000&\
+             taker=0x9008d19f58aabd9ed0d60971565aa8510560ab41&slippageBps=100&gasPrice=15000000000",
+        ),
+        res: json!({
+            "liquidityAvailable": true,
+            "sellAmount": "1000000000000000000",
+            "buyAmount": "5876422636675954000000",
+            "transaction": {
+                "to": "0xdef1c0ded9bec7f1a1670819833240f027b25eff",
+                "data": "0x6af479b2",
+                "gas": "127886",
+            },
+            "issues": {
+                "allowance": {
+                    "spender": "0xdef1c0ded9bec7f1a1670819833240f027b25eff",
+                    "actual": "0",
+                },
+            },
+        }),
+    }
+}
+
+fn auction() -> serde_json::Value {
+    json!({
+        "id": "1",
+        "tokens": {
+            "0xe41d2489571d322189246dafa5ebde1f4699f498": {
+                "decimals": 18,
+                "symbol": "ZRX",
+                "referencePrice": "4327903683155778",
+                "availableBalance": "1583034704488033979459",
+                "trusted": true,
+            },
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2": {
+                "decimals": 18,
+                "symbol": "WETH",
+                "referencePrice": "1000000000000000000",
+                "availableBalance": "482725140468789680",
+                "trusted": true,
+            },
+        },
+        "orders": [
+            {
+                "uid": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+                          2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+                          2a2a2a2a",
+                "sellToken": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                "buyToken": "0xe41d2489571d322189246dafa5ebde1f4699f498",
+                "sellAmount": "1000000000000000000",
+                "buyAmount": "200000000000000000000",
+                "fullSellAmount": "1000000000000000000",
+                "fullBuyAmount": "200000000000000000000",
+                "kind": "sell",
+                "partiallyFillable": false,
+                "class": "market",
+                "sellTokenSource": "erc20",
+                "buyTokenDestination": "erc20",
+                "preInteractions": [],
+                "postInteractions": [],
+                "owner": "0x5b1e2c2762667331bc91648052f646d1b0d35984",
+                "validTo": 0,
+                "appData": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "signingScheme": "presign",
+                "signature": "0x",
+            }
+        ],
+        "liquidity": [],
+        "effectiveGasPrice": "15000000000",
+        "deadline": "2106-01-01T00:00:00.000Z",
+        "surplusCapturingJitOrderOwners": [],
+    })
+}
+
+#[tokio::test]
+async fn solution_and_complete_events() {
+    let api = mock::http::setup(vec![swap()]).await;
+
+    let engine = tests::SolverEngine::new("zeroex", zeroex::config(&api.address)).await;
+    let events = engine.solve_streaming(auction()).await.unwrap();
+
+    let names = events
+        .iter()
+        .map(|(event, _)| event.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["solution", "complete"]);
+    let (_, solution) = &events[0];
+    assert_eq!(solution["id"], json!(0));
+    assert_eq!(
+        solution["trades"],
+        json!([{
+            "kind": "fulfillment",
+            "order": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+                        2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+                        2a2a2a2a",
+            "executedAmount": "1000000000000000000",
+        }]),
+    );
+    assert_eq!(events[1].1, json!({ "truncated": false }));
+}
@@ -75,6 +75,10 @@ pub enum Expectation {
         req: RequestBody,
         res: serde_json::Value,
     },
+    /// A GET request that is never responded to, like by a hung API.
+    Hang {
+        path: Path,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -143,7 +147,7 @@ pub async fn setup(mut expectations: Vec<Expectation>) -> ServerHandle {
                 |axum::extract::State(state),
                  axum::extract::Path(path),
                  axum::extract::RawQuery(query)| async move {
-                    axum::response::Json(get(state, Some(path), query))
+                    respond(get(state, Some(path), query)).await
                 },
             )
             .post(
@@ -161,7 +165,7 @@ pub async fn setup(mut expectations: Vec<Expectation>) -> ServerHandle {
             "/",
             axum::routing::get(
                 |axum::extract::State(state), axum::extract::RawQuery(query)| async move {
-                    axum::response::Json(get(state, None, query))
+                    respond(get(state, None, query)).await
                 },
             )
             .post(
@@ -210,11 +214,20 @@ where
         .expect("ignore this panic; it was caused by the previous panic")
 }
 
-fn get(state: State, path: Option<String>, query: Option<String>) -> serde_json::Value {
+/// Responds with the JSON body, or never if there is none.
+async fn respond(res: Option<serde_json::Value>) -> axum::response::Json<serde_json::Value> {
+    match res {
+        Some(res) => axum::response::Json(res),
+        None => std::future::pending().await,
+    }
+}
+
+fn get(state: State, path: Option<String>, query: Option<String>) -> Option<serde_json::Value> {
     let expectation = state.expectations.lock().unwrap().pop();
     let assertions = || {
         let (expected_path, res) = match expectation {
-            Some(Expectation::Get { path, res }) => (path, res),
+            Some(Expectation::Get { path, res }) => (path, Some(res)),
+            Some(Expectation::Hang { path }) => (path, None),
             Some(other) => panic!("expected GET request but got {other:?}"),
             None => panic!("got another GET request, but didn't expect any more"),
         };
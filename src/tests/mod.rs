@@ -74,9 +74,26 @@ impl SolverEngine {
 
     /// Solves a raw JSON auction.
     pub async fn solve(&self, auction: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+        self.solve_with_headers(auction, Default::default())
+            .await
+            .map(|(_, solutions)| solutions)
+    }
+
+    /// Solves a raw JSON auction, sending the specified request headers, and
+    /// returns the response headers along with the solutions.
+    pub async fn solve_with_headers(
+        &self,
+        auction: serde_json::Value,
+        headers: reqwest::header::HeaderMap,
+    ) -> anyhow::Result<(reqwest::header::HeaderMap, serde_json::Value)> {
         let client = reqwest::Client::new();
         let url = shared::url::join(&self.url, "solve");
-        let response = client.post(url).json(&auction).send().await?;
+        let response = client
+            .post(url)
+            .headers(headers)
+            .json(&auction)
+            .send()
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -84,10 +101,54 @@ impl SolverEngine {
             anyhow::bail!("HTTP {}: {:?}", status, text);
         }
 
-        response
+        let headers = response.headers().clone();
+        let solutions = response
             .json()
             .await
-            .context("Failed to parse JSON response")
+            .context("Failed to parse JSON response")?;
+        Ok((headers, solutions))
+    }
+
+    /// Solves a raw JSON auction as a stream of server-sent events, and
+    /// returns the name and JSON data of each event.
+    pub async fn solve_streaming(
+        &self,
+        auction: serde_json::Value,
+    ) -> anyhow::Result<Vec<(String, serde_json::Value)>> {
+        let client = reqwest::Client::new();
+        let url = shared::url::join(&self.url, "solve");
+        let response = client
+            .post(url)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .json(&auction)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await?;
+            anyhow::bail!("HTTP {}: {:?}", status, text);
+        }
+
+        // The stream ends once solving is complete.
+        let text = response.text().await?;
+        text.split("\n\n")
+            .filter_map(|event| {
+                let field = |name: &str| {
+                    event
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                        .map(str::trim)
+                };
+                Some((field("event")?, field("data")?))
+            })
+            .map(|(event, data)| {
+                Ok((
+                    event.to_owned(),
+                    serde_json::from_str(data).context("Failed to parse event data")?,
+                ))
+            })
+            .collect()
     }
 }
 
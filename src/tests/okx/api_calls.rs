@@ -25,6 +25,12 @@ async fn swap_sell() {
             H160::from_str("0x9008d19f58aabd9ed0d60971565aa8510560ab41").unwrap(),
         ),
         block_stream: None,
+        follow_redirects: false,
+        rate_limit: None,
+        connect_timeout: crate::infra::dex::DEFAULT_CONNECT_TIMEOUT,
+        request_timeout: crate::infra::dex::DEFAULT_REQUEST_TIMEOUT,
+        shared_client: None,
+        retry: None,
     };
 
     let order = Order {
@@ -37,6 +43,9 @@ async fn swap_sell() {
         side: crate::domain::order::Side::Sell,
         amount: Amount::new(U256::from_dec_str("10000000000000").unwrap()),
         owner: H160::from_str("0x6f9ffea7370310cd0f890dfde5e0e061059dcfb8").unwrap(),
+        partially_fillable: false,
+        fill_ratio: None,
+        slippage: None,
     };
 
     let slippage = Slippage::one_percent();
@@ -69,6 +78,12 @@ async fn swap_buy() {
             H160::from_str("0x9008d19f58aabd9ed0d60971565aa8510560ab41").unwrap(),
         ),
         block_stream: None,
+        follow_redirects: false,
+        rate_limit: None,
+        connect_timeout: crate::infra::dex::DEFAULT_CONNECT_TIMEOUT,
+        request_timeout: crate::infra::dex::DEFAULT_REQUEST_TIMEOUT,
+        shared_client: None,
+        retry: None,
     };
 
     let order = Order {
@@ -81,6 +96,9 @@ async fn swap_buy() {
         side: crate::domain::order::Side::Buy,
         amount: Amount::new(U256::from_dec_str("10000000000000").unwrap()),
         owner: H160::from_str("0x6f9ffea7370310cd0f890dfde5e0e061059dcfb8").unwrap(),
+        partially_fillable: false,
+        fill_ratio: None,
+        slippage: None,
     };
 
     let slippage = Slippage::one_percent();
@@ -111,6 +129,12 @@ async fn swap_api_error() {
             H160::from_str("0x9008d19f58aabd9ed0d60971565aa8510560ab41").unwrap(),
         ),
         block_stream: None,
+        follow_redirects: false,
+        rate_limit: None,
+        connect_timeout: crate::infra::dex::DEFAULT_CONNECT_TIMEOUT,
+        request_timeout: crate::infra::dex::DEFAULT_REQUEST_TIMEOUT,
+        shared_client: None,
+        retry: None,
     };
 
     let order = Order {
@@ -123,6 +147,9 @@ async fn swap_api_error() {
         side: crate::domain::order::Side::Sell,
         amount: Amount::new(U256::from_str("0").unwrap()),
         owner: H160::from_str("6f9ffea7370310cd0f890dfde5e0e061059dcfb8").unwrap(),
+        partially_fillable: false,
+        fill_ratio: None,
+        slippage: None,
     };
 
     let slippage = Slippage::one_percent();
@@ -154,6 +181,12 @@ async fn swap_sell_insufficient_liquidity() {
             H160::from_str("0x9008d19f58aabd9ed0d60971565aa8510560ab41").unwrap(),
         ),
         block_stream: None,
+        follow_redirects: false,
+        rate_limit: None,
+        connect_timeout: crate::infra::dex::DEFAULT_CONNECT_TIMEOUT,
+        request_timeout: crate::infra::dex::DEFAULT_REQUEST_TIMEOUT,
+        shared_client: None,
+        retry: None,
     };
 
     let order = Order {
@@ -166,6 +199,9 @@ async fn swap_sell_insufficient_liquidity() {
         side: crate::domain::order::Side::Sell,
         amount: Amount::new(U256::from_dec_str("10000000000000").unwrap()),
         owner: H160::from_str("6f9ffea7370310cd0f890dfde5e0e061059dcfb8").unwrap(),
+        partially_fillable: false,
+        fill_ratio: None,
+        slippage: None,
     };
 
     let slippage = Slippage::one_percent();
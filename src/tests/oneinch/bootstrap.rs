@@ -0,0 +1,40 @@
+//! This test ensures that transient errors while bootstrapping the 1inch solver
+//! are retried without starting the whole initialization over.
+
+use {
+    crate::tests::{self, mock},
+    serde_json::json,
+};
+
+#[tokio::test]
+async fn retries_spender() {
+    let api = mock::http::setup(vec![
+        mock::http::Expectation::Get {
+            path: mock::http::Path::exact("liquidity-sources"),
+            res: json!({
+                "protocols": [
+                    {
+                        "id": "UNISWAP_V2",
+                        "title": "Uniswap V2",
+                        "img": "https://cdn.1inch.io/liquidity-sources-logo/uniswap.png",
+                        "img_color": "https://cdn.1inch.io/liquidity-sources-logo/uniswap_color.png"
+                    },
+                ]
+            }),
+        },
+        // Malformed response, which should get retried.
+        mock::http::Expectation::Get {
+            path: mock::http::Path::exact("approve/spender"),
+            res: json!({}),
+        },
+        mock::http::Expectation::Get {
+            path: mock::http::Path::exact("approve/spender"),
+            res: json!({ "address": "0x1111111254eeb25477b68fb85ed929f73a960582" }),
+        },
+    ])
+    .await;
+
+    // The engine only starts serving once the solver is initialized, and the
+    // mock server verifies on drop that all expected requests were made.
+    let _engine = tests::SolverEngine::new("oneinch", super::config(&api.address)).await;
+}
@@ -1,8 +1,10 @@
 use {crate::tests, std::net::SocketAddr};
 
+mod bootstrap;
 mod market_order;
 mod not_found;
 mod out_of_price;
+mod v6;
 
 /// Creates a temporary file containing the config of the given solver.
 pub fn config(solver_addr: &SocketAddr) -> tests::Config {
@@ -12,6 +14,7 @@ node-url = 'http://localhost:8545'
 [dex]
 chain-id = '1'
 endpoint = 'http://{solver_addr}'
+version = 'v5'
 exclude-liquidity = ['UNISWAP_V3', 'PMM4']
         ",
     ))
@@ -0,0 +1,169 @@
+//! This test ensures that the 1inch solver supports the v6 swap API, which
+//! renames the query parameters and response fields of v5.
+
+use {
+    crate::tests::{self, mock},
+    serde_json::json,
+};
+
+#[tokio::test]
+async fn sell() {
+    let api = mock::http::setup(vec![
+        mock::http::Expectation::Get {
+            path: mock::http::Path::exact("approve/spender"),
+            res: json!({ "address": "0x111111125421ca6dc452d289314280a0f8842a65" }),
+        },
+        mock::http::Expectation::Get {
+            path: mock::http::Path::exact(
+                "swap?src=0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2&\
+                 dst=0xe41d2489571d322189246dafa5ebde1f4699f498&amount=1000000000000000000&\
+                 from=0x9008d19f58aabd9ed0d60971565aa8510560ab41&\
+                 origin=0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a&slippage=1&\
+                 protocols=UNISWAP_V2&referrer=0x9008d19f58aabd9ed0d60971565aa8510560ab41&\
+                 disableEstimate=true&includeProtocols=true",
+            ),
+            res: json!({
+                "dstAmount": "7849120067437052861364",
+                "protocols": [
+                    [
+                        [
+                            {
+                                "name": "UNISWAP_V2",
+                                "part": 100,
+                                "fromTokenAddress": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                                "toTokenAddress": "0xe41d2489571d322189246dafa5ebde1f4699f498"
+                            }
+                        ]
+                    ]
+                ],
+                "tx": {
+                    "from": "0x9008d19f58aabd9ed0d60971565aa8510560ab41",
+                    "to": "0x111111125421ca6dc452d289314280a0f8842a65",
+                    "data": "0x12aa3caf",
+                    "value": "0",
+                    "gas": 100000,
+                }
+            }),
+        },
+    ])
+    .await;
+
+    let config = tests::Config::String(format!(
+        r"
+node-url = 'http://localhost:8545'
+[dex]
+chain-id = '1'
+endpoint = 'http://{}'
+version = 'v6'
+api-key = 'SUPER_SECRET_API_KEY'
+include-liquidity = ['UNISWAP_V2']
+        ",
+        api.address
+    ));
+    let engine = tests::SolverEngine::new("oneinch", config).await;
+
+    let solution = engine
+        .solve(json!({
+            "id": "1",
+            "tokens": {
+                "0xe41d2489571d322189246dafa5ebde1f4699f498": {
+                    "decimals": 18,
+                    "symbol": "ZRX",
+                    "referencePrice": "4327903683155778",
+                    "availableBalance": "1583034704488033979459",
+                    "trusted": true,
+                },
+                "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2": {
+                    "decimals": 18,
+                    "symbol": "WETH",
+                    "referencePrice": "1000000000000000000",
+                    "availableBalance": "482725140468789680",
+                    "trusted": true,
+                },
+            },
+            "orders": [
+                {
+                    "uid": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+                              2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+                              2a2a2a2a",
+                    "sellToken": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                    "buyToken": "0xe41d2489571d322189246dafa5ebde1f4699f498",
+                    "sellAmount": "1000000000000000000",
+                    "buyAmount": "200000000000000000000",
+                    "fullSellAmount": "1000000000000000000",
+                    "fullBuyAmount": "200000000000000000000",
+                    "kind": "sell",
+                    "partiallyFillable": false,
+                    "class": "market",
+                    "sellTokenSource": "erc20",
+                    "buyTokenDestination": "erc20",
+                    "preInteractions": [],
+                    "postInteractions": [],
+                    "owner": "0x5b1e2c2762667331bc91648052f646d1b0d35984",
+                    "validTo": 0,
+                    "appData": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    "signingScheme": "presign",
+                    "signature": "0x",
+                }
+            ],
+            "liquidity": [],
+            "effectiveGasPrice": "15000000000",
+            "deadline": "2106-01-01T00:00:00.000Z",
+            "surplusCapturingJitOrderOwners": []
+        }))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        solution,
+        json!({
+            "solutions": [
+                {
+                    "id": 0,
+                    "preInteractions": [],
+                    "postInteractions": [],
+                    "interactions": [
+                        {
+                            "allowances": [
+                                {
+                                    "amount": "1000000000000000000",
+                                    "spender": "0x111111125421ca6dc452d289314280a0f8842a65",
+                                    "token": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"
+                                }
+                            ],
+                            "callData": "0x12aa3caf",
+                            "inputs": [
+                                {
+                                    "amount": "1000000000000000000",
+                                    "token": "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"
+                                }
+                            ],
+                            "internalize": false,
+                            "kind": "custom",
+                            "outputs": [
+                                {
+                                    "amount": "7849120067437052861364",
+                                    "token": "0xe41d2489571d322189246dafa5ebde1f4699f498"
+                                }
+                            ],
+                            "target": "0x111111125421ca6dc452d289314280a0f8842a65",
+                            "value": "0"
+                        }
+                    ],
+                    "prices": {
+                        "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2": "7849120067437052861364",
+                        "0xe41d2489571d322189246dafa5ebde1f4699f498": "1000000000000000000"
+                    },
+                    "trades": [
+                        {
+                            "executedAmount": "1000000000000000000",
+                            "kind": "fulfillment",
+                            "order": "0x2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a"
+                        }
+                    ],
+                    "gas": 206391,
+                }
+            ]
+        }),
+    );
+}
@@ -1,5 +1,6 @@
 use {crate::tests, std::net::SocketAddr};
 
+mod allowed_targets;
 mod market_order;
 mod not_found;
 mod options;
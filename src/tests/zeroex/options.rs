@@ -13,7 +13,7 @@ async fn test() {
              buyToken=0xe41d2489571d322189246dafa5ebde1f4699f498&\
              sellToken=0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2&sellAmount=1000000000000000000&\
              taker=0x9008d19f58aabd9ed0d60971565aa8510560ab41&slippageBps=1000&\
-             excludedSources=Uniswap_V2%2CBalancer_V2",
+             gasPrice=15000000000&excludedSources=Uniswap_V2%2CBalancer_V2",
         ),
         res: json!({
             "liquidityAvailable": true,
@@ -48,6 +48,7 @@ async fn test() {
         r"
 node-url = 'http://localhost:8545'
 relative-slippage = '0.1'
+max-slippage-bps = 1000
 [dex]
 chain-id = '1'
 endpoint = 'http://{}/swap/allowance-holder/'
@@ -6,10 +6,17 @@
 //! module.
 
 use {
-    crate::util,
-    reqwest::{Method, RequestBuilder, StatusCode, Url},
-    serde::de::DeserializeOwned,
-    std::str,
+    crate::{infra, util},
+    rand::Rng,
+    reqwest::{header::HeaderMap, Method, RequestBuilder, StatusCode, Url},
+    serde::{de::DeserializeOwned, Deserialize},
+    std::{
+        fmt,
+        future::Future,
+        str,
+        sync::atomic::{self, AtomicBool},
+        time::{Duration, Instant},
+    },
 };
 
 /// Roundtrip an HTTP request. This will `TRACE` log the request and responses.
@@ -22,15 +29,19 @@ macro_rules! roundtrip {
     (<$t:ty, $e:ty>; $request:expr) => {
         $crate::util::http::roundtrip_internal::<$t, $e>(
             $request,
-            |method, url, body, message| {
-                if let Some(body) = body {
-                    tracing::trace!(%method, %url, %body, "{message}");
+            |method, url, details, message| {
+                if let Some($crate::util::http::Details { headers, body }) = details {
+                    tracing::trace!(%method, %url, %headers, ?body, "{message}");
                 } else {
                     tracing::trace!(%method, %url, "{message}");
                 }
             },
             |status, body, message| {
-                tracing::trace!(%status, %body, "{message}");
+                if let Some(body) = body {
+                    tracing::trace!(%status, %body, "{message}");
+                } else {
+                    tracing::trace!(%status, "{message}");
+                }
             },
         )
     };
@@ -41,37 +52,278 @@ macro_rules! roundtrip {
 
 pub(crate) use roundtrip;
 
+/// Roundtrip an HTTP request like [`roundtrip!`], but retry it on transient
+/// failures according to a [`RetryPolicy`].
+///
+/// The request is specified as a function that prepares it, which is called
+/// again for every attempt. This way, each attempt is prepared like a new
+/// request, e.g. it waits for the rate limit of the DEX API client.
+///
+/// Only connection errors, timeouts, server errors and rate limiting are
+/// retried (see [`RoundtripError::is_transient`]), so that genuinely bad
+/// requests are not sent over and over again.
+macro_rules! roundtrip_retry {
+    (<$t:ty, $e:ty>; $policy:expr, $request:expr) => {
+        $crate::util::http::roundtrip_retry_internal::<$t, $e, _>(
+            $policy,
+            $request,
+            |method, url, details, message| {
+                if let Some($crate::util::http::Details { headers, body }) = details {
+                    tracing::trace!(%method, %url, %headers, ?body, "{message}");
+                } else {
+                    tracing::trace!(%method, %url, "{message}");
+                }
+            },
+            |status, body, message| {
+                if let Some(body) = body {
+                    tracing::trace!(%status, %body, "{message}");
+                } else {
+                    tracing::trace!(%status, "{message}");
+                }
+            },
+        )
+    };
+    ($policy:expr, $request:expr) => {
+        $crate::util::http::roundtrip_retry!(<_, _>; $policy, $request)
+    };
+}
+
+pub(crate) use roundtrip_retry;
+
+/// How failed HTTP requests are retried by [`roundtrip_retry!`]: with an
+/// exponential backoff plus jitter, bounded by a maximum number of attempts
+/// and a maximum total time spent on the request.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+
+    /// The backoff before the first retry. It doubles with every subsequent
+    /// retry, unless the server asked for a specific delay with a
+    /// `Retry-After` header.
+    #[serde(with = "humantime_serde", default = "default_initial_backoff")]
+    pub initial_backoff: Duration,
+
+    /// The maximum backoff between two attempts.
+    #[serde(with = "humantime_serde", default = "default_max_backoff")]
+    pub max_backoff: Duration,
+
+    /// No more retries are attempted once the next one would start this long
+    /// after the first attempt.
+    #[serde(with = "humantime_serde", default = "default_max_elapsed")]
+    pub max_elapsed: Duration,
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_initial_backoff() -> Duration {
+    Duration::from_millis(100)
+}
+
+fn default_max_backoff() -> Duration {
+    Duration::from_secs(2)
+}
+
+fn default_max_elapsed() -> Duration {
+    Duration::from_secs(5)
+}
+
+impl RetryPolicy {
+    /// A policy that sends every request exactly once.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            max_elapsed: Duration::ZERO,
+        }
+    }
+
+    /// The backoff after the specified failed attempt (starting at 1). Half
+    /// of the exponential backoff is randomized, so that concurrent requests
+    /// failing at the same time don't all retry at the same time.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        let half = backoff / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+    }
+
+    /// Makes the request until it succeeds, fails with an error that isn't
+    /// transient, or the policy gives up on it, and returns the last result.
+    /// The delay before a retry is the one the error asks for, or else the
+    /// backoff of the policy.
+    pub async fn retry<T, E, F>(
+        &self,
+        mut request: impl FnMut() -> F,
+        transient: impl Fn(&E) -> bool,
+        retry_after: impl Fn(&E) -> Option<Duration>,
+    ) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: fmt::Display,
+    {
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let err = match request().await {
+                Ok(data) => return Ok(data),
+                Err(err) => err,
+            };
+            let delay = retry_after(&err).unwrap_or_else(|| self.backoff(attempt));
+            if !transient(&err)
+                || attempt >= self.max_attempts
+                || start.elapsed() + delay > self.max_elapsed
+            {
+                return Err(err);
+            }
+            tracing::debug!(%err, attempt, ?delay, "request failed; retrying");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff: default_initial_backoff(),
+            max_backoff: default_max_backoff(),
+            max_elapsed: default_max_elapsed(),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub async fn roundtrip_retry_internal<T, E, F>(
+    policy: &RetryPolicy,
+    mut request: impl FnMut() -> F,
+    log_request: impl Fn(&Method, &Url, Option<Details>, &str),
+    log_response: impl Fn(StatusCode, Option<&str>, &str),
+) -> Result<T, RoundtripError<E>>
+where
+    T: DeserializeOwned,
+    E: DeserializeOwned,
+    F: Future<Output = RequestBuilder>,
+{
+    let (log_request, log_response) = (&log_request, &log_response);
+    let mut attempt = 0;
+    policy
+        .retry(
+            || {
+                attempt += 1;
+                let retry = attempt > 1;
+                let request = request();
+                async move {
+                    let request = request.await;
+                    if retry {
+                        infra::metrics::request_retried(&host(&request));
+                    }
+                    roundtrip_internal(request, log_request, log_response).await
+                }
+            },
+            RoundtripError::is_transient,
+            RoundtripError::retry_after,
+        )
+        .await
+}
+
+/// The host a request is sent to, or an empty string if it is invalid or has
+/// a streaming body.
+fn host(request: &RequestBuilder) -> String {
+    request
+        .try_clone()
+        .and_then(|request| request.build().ok())
+        .and_then(|request| request.url().host_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
 #[doc(hidden)]
 pub async fn roundtrip_internal<T, E>(
     mut request: RequestBuilder,
-    log_request: impl FnOnce(&Method, &Url, Option<&str>, &str),
-    log_response: impl FnOnce(StatusCode, &str, &str),
+    log_request: impl FnOnce(&Method, &Url, Option<Details>, &str),
+    log_response: impl FnOnce(StatusCode, Option<&str>, &str),
 ) -> Result<T, RoundtripError<E>>
 where
     T: DeserializeOwned,
     E: DeserializeOwned,
 {
+    let log_bodies = LOG_BODIES.load(atomic::Ordering::Relaxed);
     if let Some(id) = observe::request_id::from_current_span() {
         request = request.header("X-REQUEST-ID", id);
     }
     let (client, request) = request.build_split();
     let request = request.map_err(Error::from)?;
 
-    let body = request
-        .body()
-        .and_then(|body| str::from_utf8(body.as_bytes()?).ok());
+    let details = log_bodies.then(|| Details {
+        headers: redacted(request.headers()),
+        body: request
+            .body()
+            .and_then(|body| str::from_utf8(body.as_bytes()?).ok()),
+    });
 
     log_request(
         request.method(),
         request.url(),
-        body,
+        details,
         "sending HTTP request",
     );
     let response = client.execute(request).await.map_err(Error::from)?;
 
     let status = response.status();
+    if let Some(remaining) = rate_limit_remaining(response.headers()) {
+        infra::metrics::rate_limit_remaining(
+            response.url().host_str().unwrap_or_default(),
+            remaining,
+        );
+    }
+    let retry_after = retry_after(response.headers());
+    if status.is_redirection() {
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_owned);
+        log_response(status, None, "received HTTP redirect");
+        return Err(Error::Redirect(status, location).into());
+    }
     let body = response.text().await.map_err(Error::from)?;
-    log_response(status, &body, "received HTTP response");
+    log_response(
+        status,
+        log_bodies.then_some(body.as_str()),
+        "received HTTP response",
+    );
+
+    parse(status, retry_after, body)
+}
+
+/// Parses the response body into the expected data or the API error.
+///
+/// Rate limiting and server errors are classified by their status code
+/// first, even if the body contains a well-formed API error, so that they
+/// are retried and keep the `Retry-After` delay the server asked for.
+fn parse<T, E>(
+    status: StatusCode,
+    retry_after: Option<Duration>,
+    body: String,
+) -> Result<T, RoundtripError<E>>
+where
+    T: DeserializeOwned,
+    E: DeserializeOwned,
+{
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(Error::TooManyRequests { retry_after, body }.into());
+    }
+    if status.is_server_error() {
+        return Err(Error::Status(status, body).into());
+    }
 
     match serde_json::from_str::<T>(&body) {
         Ok(data) => Ok(data),
@@ -93,6 +345,78 @@ where
     }
 }
 
+/// Whether the headers and bodies of requests and responses are logged.
+static LOG_BODIES: AtomicBool = AtomicBool::new(false);
+
+/// Enables logging the headers and bodies of all HTTP requests and responses
+/// at `TRACE` level. Credential headers are redacted, but bodies are logged
+/// verbatim.
+pub fn log_bodies(enabled: bool) {
+    LOG_BODIES.store(enabled, atomic::Ordering::Relaxed);
+}
+
+/// The headers and body of a request, if they are logged.
+#[doc(hidden)]
+pub struct Details<'a> {
+    pub headers: String,
+    pub body: Option<&'a str>,
+}
+
+/// Formats the headers for logging, with the values of credential headers
+/// (and those marked as sensitive) redacted.
+fn redacted(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let name = name.as_str();
+            let secret = value.is_sensitive()
+                || ["authorization", "key", "passphrase", "secret", "sign"]
+                    .iter()
+                    .any(|secret| name.contains(secret));
+            let value = if secret {
+                "<redacted>"
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            format!("{name}: {value}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The common names of the response header with the remaining request budget
+/// of the current rate limiting window.
+const RATE_LIMIT_REMAINING_HEADERS: [&str; 3] = [
+    "x-ratelimit-remaining",
+    "x-rate-limit-remaining",
+    "ratelimit-remaining",
+];
+
+/// Parses the remaining rate limit budget from the response headers. Some
+/// APIs report the budget for multiple windows (e.g. `10, 100;w=60`), in
+/// which case the first one is used.
+fn rate_limit_remaining(headers: &HeaderMap) -> Option<i64> {
+    let value = RATE_LIMIT_REMAINING_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name))?
+        .to_str()
+        .ok()?;
+    value.split([',', ';']).next()?.trim().parse().ok()
+}
+
+/// Parses the `Retry-After` response header, which is either a number of
+/// seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     /// An error occurred when parsing the JSON body from the HTTP response
@@ -109,6 +433,17 @@ pub enum Error {
     /// code indicating an application-level error.
     #[error("HTTP {0}: {1}")]
     Status(StatusCode, String),
+    /// An error indicating that the server responded with a redirect which
+    /// was not followed because redirects are disabled for the client.
+    #[error("HTTP {0}: redirect to {1:?} not followed")]
+    Redirect(StatusCode, Option<String>),
+    /// An error indicating that the server is rate limiting requests. The
+    /// server may specify how long to wait before retrying.
+    #[error("HTTP 429 Too Many Requests: {body}")]
+    TooManyRequests {
+        retry_after: Option<Duration>,
+        body: String,
+    },
 }
 
 impl From<RoundtripError<util::serialize::Never>> for Error {
@@ -127,3 +462,154 @@ pub enum RoundtripError<E> {
     #[error("API error")]
     Api(E),
 }
+
+impl<E> RoundtripError<E> {
+    /// Whether the request may succeed when sent again: on connection errors,
+    /// timeouts, server errors and rate limiting. Errors that the API
+    /// reported in a well-formed body are never considered transient.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Http(Error::Http(err)) => err.is_connect() || err.is_timeout(),
+            Self::Http(Error::Status(status, _)) => status.is_server_error(),
+            Self::Http(Error::TooManyRequests { .. }) => true,
+            _ => false,
+        }
+    }
+
+    /// How long the server asked us to wait before retrying.
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Http(Error::TooManyRequests { retry_after, .. }) => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, reqwest::header::HeaderValue};
+
+    #[test]
+    fn parses_rate_limit_remaining() {
+        let headers = |name: &'static str, value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static(value));
+            headers
+        };
+
+        assert_eq!(
+            rate_limit_remaining(&headers("x-ratelimit-remaining", "42")),
+            Some(42)
+        );
+        assert_eq!(
+            rate_limit_remaining(&headers("RateLimit-Remaining", "10, 100;w=60")),
+            Some(10)
+        );
+        assert_eq!(
+            rate_limit_remaining(&headers("x-rate-limit-remaining", "unknown")),
+            None
+        );
+        assert_eq!(rate_limit_remaining(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                reqwest::header::RETRY_AFTER,
+                HeaderValue::from_str(value).unwrap(),
+            );
+            headers
+        };
+
+        assert_eq!(retry_after(&headers("3")), Some(Duration::from_secs(3)));
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        assert!(retry_after(&headers(&date)).is_some_and(|delay| delay.as_secs() > 50));
+        assert_eq!(retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn redacts_credential_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("0x-version", HeaderValue::from_static("v2"));
+        headers.insert("0x-api-key", HeaderValue::from_static("secret"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("OK-ACCESS-PASSPHRASE", HeaderValue::from_static("secret"));
+        let mut sensitive = HeaderValue::from_static("secret");
+        sensitive.set_sensitive(true);
+        headers.insert("x-custom", sensitive);
+
+        let logged = redacted(&headers);
+        assert!(logged.contains("0x-version: v2"));
+        assert!(logged.contains("0x-api-key: <redacted>"));
+        assert!(logged.contains("authorization: <redacted>"));
+        assert!(logged.contains("ok-access-passphrase: <redacted>"));
+        assert!(logged.contains("x-custom: <redacted>"));
+        assert!(!logged.contains("secret"));
+    }
+
+    #[test]
+    fn retries_transient_errors_only() {
+        let status = |status| RoundtripError::<()>::Http(Error::Status(status, String::new()));
+
+        assert!(status(StatusCode::BAD_GATEWAY).is_transient());
+        assert!(status(StatusCode::SERVICE_UNAVAILABLE).is_transient());
+        assert!(RoundtripError::<()>::Http(Error::TooManyRequests {
+            retry_after: None,
+            body: String::new(),
+        })
+        .is_transient());
+        assert!(!status(StatusCode::BAD_REQUEST).is_transient());
+        assert!(!status(StatusCode::NOT_FOUND).is_transient());
+        assert!(!RoundtripError::Api(()).is_transient());
+    }
+
+    #[test]
+    fn classifies_rate_limits_and_server_errors_by_status() {
+        #[derive(Debug, Deserialize)]
+        struct ApiError {
+            #[allow(dead_code)]
+            code: i64,
+        }
+        let parse = |status, body: &str| {
+            parse::<u64, ApiError>(status, Some(Duration::from_secs(3)), body.to_owned())
+        };
+
+        let err = parse(StatusCode::TOO_MANY_REQUESTS, r#"{"code":429}"#).unwrap_err();
+        assert!(err.is_transient());
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(3)));
+        let err = parse(StatusCode::INTERNAL_SERVER_ERROR, r#"{"code":500}"#).unwrap_err();
+        assert!(err.is_transient());
+        assert!(matches!(
+            parse(StatusCode::BAD_REQUEST, r#"{"code":100}"#),
+            Err(RoundtripError::Api(ApiError { .. }))
+        ));
+        assert!(matches!(
+            parse(StatusCode::OK, "{}"),
+            Err(RoundtripError::Http(Error::Json(_)))
+        ));
+        assert_eq!(parse(StatusCode::OK, "42").unwrap(), 42);
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_jitter() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            max_elapsed: Duration::from_secs(10),
+        };
+        let within = |attempt, min, max| {
+            let backoff = policy.backoff(attempt);
+            Duration::from_millis(min) <= backoff && backoff <= Duration::from_millis(max)
+        };
+
+        assert!(within(1, 50, 100));
+        assert!(within(2, 100, 200));
+        assert!(within(3, 200, 400));
+        assert!(within(8, 500, 1000));
+        assert!(within(u32::MAX, 500, 1000));
+    }
+}
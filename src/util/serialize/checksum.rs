@@ -0,0 +1,111 @@
+use {
+    serde::{ser::Error as _, Serialize, Serializer},
+    serde_json::Value,
+};
+
+/// Serializes a value either as is, or with all of its addresses in their
+/// EIP-55 checksummed form. Addresses are recognized as hexadecimal strings
+/// of 20 bytes, including map keys, which is what the `H160` fields of the
+/// API schemas serialize to.
+#[derive(Debug)]
+pub enum Addresses<T> {
+    Unchanged(T),
+    Checksummed(T),
+}
+
+impl<T> Addresses<T> {
+    pub fn new(value: T, checksummed: bool) -> Self {
+        if checksummed {
+            Self::Checksummed(value)
+        } else {
+            Self::Unchanged(value)
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Addresses<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Unchanged(value) => value.serialize(serializer),
+            Self::Checksummed(value) => {
+                let mut value = serde_json::to_value(value).map_err(S::Error::custom)?;
+                checksum_all(&mut value);
+                value.serialize(serializer)
+            }
+        }
+    }
+}
+
+fn checksum_all(value: &mut Value) {
+    match value {
+        Value::String(string) => {
+            if let Some(checksummed) = checksum(string) {
+                *string = checksummed;
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(checksum_all),
+        Value::Object(map) => {
+            *map = std::mem::take(map)
+                .into_iter()
+                .map(|(key, mut value)| {
+                    checksum_all(&mut value);
+                    (checksum(&key).unwrap_or(key), value)
+                })
+                .collect();
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+/// Returns the EIP-55 checksummed form of an address, or `None` if the string
+/// is not an address.
+fn checksum(address: &str) -> Option<String> {
+    let hex = address.strip_prefix("0x")?;
+    if hex.len() != 40 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let hex = hex.to_ascii_lowercase();
+    let hash = web3::signing::keccak256(hex.as_bytes());
+    let checksummed = hex
+        .chars()
+        .enumerate()
+        .map(|(i, char)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0xf;
+            if nibble >= 8 {
+                char.to_ascii_uppercase()
+            } else {
+                char
+            }
+        })
+        .collect::<String>();
+    Some(format!("0x{checksummed}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_addresses_only() {
+        let value = serde_json::json!({
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed": "1",
+            "target": "0xfb6916095ca1df60bb79ce92ce3ea74c37c5d359",
+            "calls": ["0xdbf03b407c01e7cd3cbea99509d93f8dddc8c6fb", "0xcafe"],
+            "amount": "1000000000000000000000000000000000000000",
+        });
+
+        assert_eq!(
+            serde_json::to_value(Addresses::new(&value, true)).unwrap(),
+            serde_json::json!({
+                "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed": "1",
+                "target": "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+                "calls": ["0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB", "0xcafe"],
+                "amount": "1000000000000000000000000000000000000000",
+            }),
+        );
+        assert_eq!(
+            serde_json::to_value(Addresses::new(&value, false)).unwrap(),
+            value
+        );
+    }
+}
@@ -1,7 +1,15 @@
 mod chain_id;
+mod checksum;
 mod hex;
 mod never;
 mod str;
 mod u256;
 
-pub use self::{chain_id::ChainId, hex::Hex, never::Never, str::CommaSeparated, u256::U256};
+pub use self::{
+    chain_id::ChainId,
+    checksum::Addresses,
+    hex::Hex,
+    never::Never,
+    str::CommaSeparated,
+    u256::U256,
+};